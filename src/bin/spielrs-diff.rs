@@ -0,0 +1,342 @@
+//! Command line tool to compare two directories or two files
+use spielrs_diff::{
+    diff::{DirDiff, FileDiff},
+    dir_diff_report,
+    report::{
+        color_enabled, file_to_json, to_ascii_tree, to_colored_text, to_html, to_json, to_markdown,
+        to_unified,
+    },
+    try_file_diff,
+};
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: spielrs-diff [OPTIONS] <PATH> <PATH_COMP>
+
+Compare two directories or two files
+
+Options:
+  -x, --exclude <NAME>     exclude a directory or file by name, can be repeated
+  -r, --recursive-exclude  apply the exclusions in every subdirectory, not only in the root
+      --exclude-path <PATH>
+                           exclude an entry by its path relative to the root, like
+                           vlang/purpose, can be repeated
+      --exclude-under <DIR/NAME>
+                           exclude a name only inside of a directory, like var/cache,
+                           a bare name at any depth, can be repeated
+  -q, --brief              only report whether the paths differ
+  -u, --unified            print the lines changed like diff -ru, --format unified
+  -f, --format <FORMAT>    output format: text (default), unified, tree, json,
+                           markdown or html, the last two include the lines
+                           changed in the text files too
+      --color <WHEN>       color the text output: auto (default), always or never,
+                           auto respects NO_COLOR and only colors terminals
+  -h, --help               print this help
+  -V, --version            print the version
+
+Exit status is 0 if the inputs are the same, 1 if different, 2 if trouble";
+
+/// Output format of the result
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+enum Format {
+    #[default]
+    Text,
+    /// lines changed like `diff -ru`
+    Unified,
+    /// differences inside of their directories
+    Tree,
+    Json,
+    /// tables of differences with the lines changed
+    Markdown,
+    /// standalone page with the lines changed
+    Html,
+}
+
+/// When to color the text output
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+enum Color {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Exit status, the same than GNU diff
+const EQUAL: i32 = 0;
+const DIFFERENT: i32 = 1;
+const TROUBLE: i32 = 2;
+
+/// Options parsed from the command line
+#[derive(Debug, PartialEq, Default)]
+struct Options {
+    path: String,
+    path_comp: String,
+    excluding: Vec<String>,
+    recursive_excluding: bool,
+    scoped_excluding: Vec<String>,
+    excluding_paths: Vec<String>,
+    brief: bool,
+    format: Format,
+    color: Color,
+}
+
+/// What the command line asks to do
+#[derive(Debug, PartialEq)]
+enum Command {
+    Compare(Options),
+    Help,
+    Version,
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut paths: Vec<String> = vec![];
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-x" | "--exclude" => match args.next() {
+                Some(name) => options.excluding.push(name),
+                None => return Err(format!("missing value for {}", arg)),
+            },
+            "-r" | "--recursive-exclude" => options.recursive_excluding = true,
+            "--exclude-path" => match args.next() {
+                Some(path) => options.excluding_paths.push(path),
+                None => return Err(format!("missing value for {}", arg)),
+            },
+            "--exclude-under" => match args.next() {
+                Some(pattern) => options.scoped_excluding.push(pattern),
+                None => return Err(format!("missing value for {}", arg)),
+            },
+            "-q" | "--brief" => options.brief = true,
+            "-u" | "--unified" => options.format = Format::Unified,
+            "-f" | "--format" => match args.next() {
+                Some(format) => options.format = parse_format(&format)?,
+                None => return Err(format!("missing value for {}", arg)),
+            },
+            "--color" => match args.next() {
+                Some(color) => options.color = parse_color(&color)?,
+                None => return Err(format!("missing value for {}", arg)),
+            },
+            _ if arg.starts_with("--color=") => {
+                options.color = parse_color(&arg["--color=".len()..])?
+            }
+            _ if arg.starts_with("--format=") => {
+                options.format = parse_format(&arg["--format=".len()..])?
+            }
+            _ if arg.starts_with("--exclude=") => options
+                .excluding
+                .push(arg["--exclude=".len()..].to_string()),
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option {}", arg))
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.len() != 2 {
+        return Err("expected two paths to compare".to_string());
+    }
+    options.path_comp = paths.pop().unwrap();
+    options.path = paths.pop().unwrap();
+
+    Ok(Command::Compare(options))
+}
+
+fn parse_format(format: &str) -> Result<Format, String> {
+    match format {
+        "text" => Ok(Format::Text),
+        "unified" => Ok(Format::Unified),
+        "tree" => Ok(Format::Tree),
+        "json" => Ok(Format::Json),
+        "markdown" => Ok(Format::Markdown),
+        "html" => Ok(Format::Html),
+        _ => Err(format!("unknown format {}", format)),
+    }
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    match color {
+        "auto" => Ok(Color::Auto),
+        "always" => Ok(Color::Always),
+        "never" => Ok(Color::Never),
+        _ => Err(format!("unknown color {}", color)),
+    }
+}
+
+/// Compare the paths, print the result and return true if are different
+async fn compare(options: Options) -> Result<bool, String> {
+    let path = Path::new(&options.path);
+    let path_comp = Path::new(&options.path_comp);
+
+    for item in [path, path_comp].iter() {
+        if !item.exists() {
+            return Err(format!("{}: No such file or directory", item.display()));
+        }
+    }
+
+    match (path.is_dir(), path_comp.is_dir()) {
+        (true, true) => {
+            let report = dir_diff_report(DirDiff {
+                dir: options.path.clone(),
+                dir_comp: options.path_comp.clone(),
+                excluding: if options.excluding.is_empty() {
+                    None
+                } else {
+                    Some(options.excluding)
+                },
+                recursive_excluding: options.recursive_excluding,
+                scoped_excluding: if options.scoped_excluding.is_empty() {
+                    None
+                } else {
+                    Some(options.scoped_excluding)
+                },
+                excluding_paths: if options.excluding_paths.is_empty() {
+                    None
+                } else {
+                    Some(options.excluding_paths)
+                },
+                report_text_diffs: matches!(
+                    options.format,
+                    Format::Unified | Format::Markdown | Format::Html
+                ),
+                ..Default::default()
+            })
+            .await;
+
+            match options.format {
+                Format::Json => println!("{}", to_json(&report)),
+                Format::Unified => print!("{}", to_unified(&report)),
+                Format::Tree => print!("{}", to_ascii_tree(&report)),
+                Format::Markdown => print!("{}", to_markdown(&report)),
+                Format::Html => print!("{}", to_html(&report)),
+                Format::Text if options.brief => {
+                    if !report.is_equal() {
+                        println!(
+                            "Directories {} and {} differ",
+                            options.path, options.path_comp
+                        );
+                    }
+                }
+                Format::Text => {
+                    let colored = match options.color {
+                        Color::Always => true,
+                        Color::Never => false,
+                        Color::Auto => color_enabled() && io::stdout().is_terminal(),
+                    };
+
+                    if colored {
+                        print!("{}", to_colored_text(&report));
+                    } else {
+                        print!("{}", report);
+                    }
+                }
+            }
+
+            Ok(!report.is_equal())
+        }
+        (false, false) => {
+            let files = FileDiff {
+                file: options.path.clone(),
+                file_comp: options.path_comp.clone(),
+                ..Default::default()
+            };
+            let diff = try_file_diff(files.clone())
+                .await
+                .map_err(|error| error.to_string())?;
+
+            match options.format {
+                Format::Json => println!("{}", file_to_json(&files, !diff)),
+                _ if diff => {
+                    println!("Files {} and {} differ", options.path, options.path_comp)
+                }
+                _ => {}
+            }
+
+            Ok(diff)
+        }
+        _ => Err(format!(
+            "{} and {} must be both directories or both files",
+            options.path, options.path_comp
+        )),
+    }
+}
+
+// WASI has only one thread
+#[cfg_attr(not(target_family = "wasm"), tokio::main)]
+#[cfg_attr(target_family = "wasm", tokio::main(flavor = "current_thread"))]
+async fn main() {
+    let status = match parse_args(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            EQUAL
+        }
+        Ok(Command::Version) => {
+            println!("spielrs-diff {}", env!("CARGO_PKG_VERSION"));
+            EQUAL
+        }
+        // the comparison runs in its own task so a panic reading the files is reported as trouble
+        Ok(Command::Compare(options)) => match tokio::spawn(compare(options)).await {
+            Ok(Ok(true)) => DIFFERENT,
+            Ok(Ok(false)) => EQUAL,
+            Ok(Err(error)) => {
+                eprintln!("spielrs-diff: {}", error);
+                TROUBLE
+            }
+            Err(_) => TROUBLE,
+        },
+        Err(error) => {
+            eprintln!("spielrs-diff: {}\n\n{}", error, USAGE);
+            TROUBLE
+        }
+    };
+
+    process::exit(status);
+}
+
+#[test]
+fn should_parse_compare_options() {
+    let args = vec![
+        "-x",
+        "purpose",
+        "--exclude=hello.js",
+        "-r",
+        "--exclude-under",
+        "var/cache",
+        "--exclude-path",
+        "vlang/purpose",
+        "-q",
+        "--format",
+        "json",
+        "--color=never",
+        "dir_one",
+        "dir_two",
+    ];
+
+    assert_eq!(
+        parse_args(args.into_iter().map(String::from)),
+        Ok(Command::Compare(Options {
+            path: "dir_one".to_string(),
+            path_comp: "dir_two".to_string(),
+            excluding: vec!["purpose".to_string(), "hello.js".to_string()],
+            recursive_excluding: true,
+            scoped_excluding: vec!["var/cache".to_string()],
+            excluding_paths: vec!["vlang/purpose".to_string()],
+            brief: true,
+            format: Format::Json,
+            color: Color::Never,
+        }))
+    );
+}
+
+#[test]
+fn should_fail_without_two_paths() {
+    assert!(parse_args(vec!["dir_one".to_string()]).is_err());
+    assert!(parse_args(vec!["-x".to_string()]).is_err());
+    assert!(parse_args(vec!["-f".to_string(), "xml".to_string()]).is_err());
+    assert_eq!(parse_args(vec!["--help".to_string()]), Ok(Command::Help));
+}
@@ -0,0 +1,89 @@
+//! Blocking versions of the comparisons for the programs without an async runtime, like
+//! the command line tools and the build scripts. Every call blocks the current thread
+//! until the async comparison ends, with tokio in its own current thread runtime, so
+//! they panic if they are called from an async context, where the async functions of
+//! the crate root must be used
+use crate::diff::{DirDiff, FileDiff};
+use crate::error::DiffError;
+use crate::report::DiffReport;
+use crate::rt::block_on;
+
+/// Compare two directories and return true if both are different, see `spielrs_diff::dir_diff`
+///
+/// # Example
+/// ```rust
+/// use spielrs_diff::{blocking, diff::DirDiff};
+///
+/// let diff = blocking::dir_diff(DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_three".to_string(),
+///     ..Default::default()
+/// });
+///
+/// assert_eq!(diff, true);
+/// ```
+pub fn dir_diff(dir_diff_options: DirDiff) -> bool {
+    block_on(crate::dir_diff(dir_diff_options))
+}
+
+/// Compare two directories and return true if both are different, or
+/// `DiffError::Timeout` if it takes more than the `timeout` of the options, see
+/// `spielrs_diff::try_dir_diff`
+pub fn try_dir_diff(dir_diff_options: DirDiff) -> Result<bool, DiffError> {
+    block_on(crate::try_dir_diff(dir_diff_options))
+}
+
+/// Compare two directories entry by entry and return the differences found, see
+/// `spielrs_diff::dir_diff_report`
+pub fn dir_diff_report(dir_diff_options: DirDiff) -> DiffReport {
+    block_on(crate::dir_diff_report(dir_diff_options))
+}
+
+/// Compare two files and return true if both are different, see `spielrs_diff::file_diff`
+///
+/// # Example
+/// ```rust
+/// use spielrs_diff::{blocking, diff::FileDiff};
+///
+/// let diff = blocking::file_diff(FileDiff {
+///     file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
+///     file_comp: "./mocks/dir_five/vlang/purpose/purpose.txt".to_string(),
+///     ..Default::default()
+/// });
+///
+/// assert_eq!(diff, true);
+/// ```
+pub fn file_diff(file_diff_options: FileDiff) -> bool {
+    block_on(crate::file_diff(file_diff_options))
+}
+
+/// Compare two files and return true if both are different, or the error of the first
+/// file which can't be read, see `spielrs_diff::try_file_diff`
+pub fn try_file_diff(file_diff_options: FileDiff) -> Result<bool, DiffError> {
+    block_on(crate::try_file_diff(file_diff_options))
+}
+
+#[test]
+fn should_compare_without_an_async_runtime() {
+    assert!(!dir_diff(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_two".to_string(),
+        ..Default::default()
+    }));
+    assert!(try_file_diff(FileDiff {
+        file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
+        file_comp: "./mocks/dir_one/missing.txt".to_string(),
+        ..Default::default()
+    })
+    .is_err());
+    assert_eq!(
+        dir_diff_report(DirDiff {
+            dir: "./mocks/dir_one".to_string(),
+            dir_comp: "./mocks/dir_four".to_string(),
+            ..Default::default()
+        })
+        .stats
+        .modified,
+        1
+    );
+}
@@ -0,0 +1,299 @@
+//! Digests of the files kept between comparisons, so the files whose size and
+//! modification time didn't change are not read again
+use crate::delta::patch::{invalid, read_varint, write_varint};
+use crate::hash::ContentHasher;
+use crate::tree::Tree;
+use crate::vfs::DiffFs;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// First bytes of a saved cache
+const MAGIC: &[u8; 4] = b"SPHC";
+/// Version of the format of the saved caches, increased on every incompatible change
+pub const CACHE_VERSION: u8 = 2;
+
+/// Size and modification time of a file when its digest was computed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Stamp {
+    size: u64,
+    modified: SystemTime,
+}
+
+impl Stamp {
+    /// Return the stamp of the file, `None` if the source doesn't know its modification time
+    pub(crate) async fn of<F: DiffFs + ?Sized>(fs: &F, entry: &Tree) -> Option<Stamp> {
+        let metadata = fs.metadata(&entry.path).await.ok()?;
+
+        Some(Stamp {
+            size: metadata.len,
+            modified: metadata.modified?,
+        })
+    }
+}
+
+/// Cache of the digests of the files by path, size and modification time. Share it between
+/// the `dir_diff` calls of a watcher loop through `DirDiff::hash_cache`, the files present in
+/// both directories which didn't change since the last call are equal if their digests
+/// were equal, without reading them.
+///
+/// The digests are computed with `DirDiff::hasher`, use a cache with the same hasher and
+/// the same sources. Files modified twice in the granularity of the modification time of
+/// the filesystem with the same size are not detected
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{cache::HashCache, dir_diff, diff::DirDiff};
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let hash_cache = Arc::new(HashCache::new());
+///     let options = DirDiff {
+///         dir: "./mocks/dir_one".to_string(),
+///         dir_comp: "./mocks/dir_two".to_string(),
+///         hash_cache: Some(hash_cache.clone()),
+///         ..Default::default()
+///     };
+///
+///     // the second call only reads the files modified after the first one
+///     assert_eq!(dir_diff(options.clone()).await, dir_diff(options).await);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct HashCache {
+    files: Mutex<HashMap<String, (Stamp, Vec<u8>)>>,
+}
+
+impl HashCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        HashCache::default()
+    }
+
+    /// Number of files cached
+    pub fn len(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+
+    /// Return true if no file is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all the files
+    pub fn clear(&self) {
+        self.files.lock().unwrap().clear();
+    }
+
+    /// Digest of the file if it has the same stamp than when it was cached
+    pub(crate) fn get(&self, path: &str, stamp: &Stamp) -> Option<Vec<u8>> {
+        match self.files.lock().unwrap().get(path) {
+            Some((cached, digest)) if cached == stamp => Some(digest.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, path: &str, stamp: Stamp, digest: Vec<u8>) {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), (stamp, digest));
+    }
+
+    /// Encode the cache with the version of the format and the name of the `hasher` of
+    /// its digests, the files modified before the unix epoch are not saved.
+    /// `DefaultContentHasher` is not stable between builds, save only the caches of a
+    /// stable `DirDiff::hasher` like `Blake3Hasher`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::cache::HashCache;
+    /// use spielrs_diff::hash::DefaultContentHasher;
+    ///
+    /// let bytes = HashCache::new().to_bytes(&DefaultContentHasher);
+    /// assert!(HashCache::from_bytes(&bytes, &DefaultContentHasher).unwrap().is_empty());
+    /// assert!(HashCache::from_bytes(b"SPHC\x01", &DefaultContentHasher).is_err());
+    /// ```
+    pub fn to_bytes(&self, hasher: &dyn ContentHasher) -> Vec<u8> {
+        let files = self.files.lock().unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(CACHE_VERSION);
+        write_varint(hasher.name().len() as u64, &mut bytes);
+        bytes.extend_from_slice(hasher.name().as_bytes());
+
+        let saved: Vec<(&String, &Stamp, &Vec<u8>, Duration)> = files
+            .iter()
+            .filter_map(|(path, (stamp, digest))| {
+                let modified = stamp.modified.duration_since(UNIX_EPOCH).ok()?;
+                Some((path, stamp, digest, modified))
+            })
+            .collect();
+        write_varint(saved.len() as u64, &mut bytes);
+        for (path, stamp, digest, modified) in saved {
+            write_varint(path.len() as u64, &mut bytes);
+            bytes.extend_from_slice(path.as_bytes());
+            write_varint(stamp.size, &mut bytes);
+            write_varint(modified.as_secs(), &mut bytes);
+            write_varint(modified.subsec_nanos() as u64, &mut bytes);
+            write_varint(digest.len() as u64, &mut bytes);
+            bytes.extend_from_slice(digest);
+        }
+
+        bytes
+    }
+
+    /// Decode a cache encoded by `to_bytes`, an error of kind `InvalidData` is returned
+    /// if it is not a cache, was saved with another version of the format or its digests
+    /// were not computed by a hasher with the name of `hasher`
+    pub fn from_bytes(bytes: &[u8], hasher: &dyn ContentHasher) -> io::Result<HashCache> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(invalid("not a hash cache"));
+        }
+        match bytes.get(MAGIC.len()) {
+            Some(&CACHE_VERSION) => {}
+            Some(version) => {
+                return Err(invalid(&format!("unsupported cache version {}", version)))
+            }
+            None => return Err(invalid("truncated cache")),
+        }
+
+        let mut position = MAGIC.len() + 1;
+        let take = |position: &mut usize, len: u64| -> io::Result<Vec<u8>> {
+            let end = position
+                .checked_add(len as usize)
+                .filter(|end| *end <= bytes.len())
+                .ok_or_else(|| invalid("truncated cache"))?;
+            let taken = bytes[*position..end].to_vec();
+            *position = end;
+            Ok(taken)
+        };
+
+        let len = read_varint(bytes, &mut position)?;
+        let name = take(&mut position, len)?;
+        if name != hasher.name().as_bytes() {
+            return Err(invalid(&format!(
+                "cache of the digests of {}, not of {}",
+                String::from_utf8_lossy(&name),
+                hasher.name()
+            )));
+        }
+
+        let mut files: HashMap<String, (Stamp, Vec<u8>)> = HashMap::new();
+        for _ in 0..read_varint(bytes, &mut position)? {
+            let len = read_varint(bytes, &mut position)?;
+            let path = String::from_utf8(take(&mut position, len)?)
+                .map_err(|_| invalid("path is not utf-8"))?;
+            let size = read_varint(bytes, &mut position)?;
+            let secs = read_varint(bytes, &mut position)?;
+            let nanos = read_varint(bytes, &mut position)?;
+            if nanos >= 1_000_000_000 {
+                return Err(invalid("invalid modification time"));
+            }
+            let modified = UNIX_EPOCH
+                .checked_add(Duration::new(secs, nanos as u32))
+                .ok_or_else(|| invalid("invalid modification time"))?;
+            let len = read_varint(bytes, &mut position)?;
+            let digest = take(&mut position, len)?;
+            files.insert(path, (Stamp { size, modified }, digest));
+        }
+
+        Ok(HashCache {
+            files: Mutex::new(files),
+        })
+    }
+
+    /// Save the cache in a file, see `to_bytes`. It is written in a temporary file
+    /// renamed at the end so a crash never leaves a partial cache
+    pub async fn save(&self, path: &str, hasher: &dyn ContentHasher) -> io::Result<()> {
+        let temporary = format!("{}.tmp", path);
+        crate::rt::fs::write(&temporary, &self.to_bytes(hasher)).await?;
+
+        crate::rt::fs::rename(&temporary, path).await
+    }
+
+    /// Load a cache saved by `save` with a hasher of the same name, see `from_bytes`
+    pub async fn load(path: &str, hasher: &dyn ContentHasher) -> io::Result<HashCache> {
+        HashCache::from_bytes(&crate::rt::fs::read(path).await?, hasher)
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_save_and_load_the_cache() {
+    use crate::hash::DefaultContentHasher;
+
+    let hash_cache = HashCache::new();
+    let stamp = Stamp {
+        size: 11,
+        modified: UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789),
+    };
+    hash_cache.insert("dir_one/hello.txt", stamp, vec![1, 2, 3]);
+
+    let path = std::env::temp_dir().join(format!("spielrs_diff_cache_{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    hash_cache.save(path, &DefaultContentHasher).await.unwrap();
+    let loaded = HashCache::load(path, &DefaultContentHasher).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded.get("dir_one/hello.txt", &stamp), Some(vec![1, 2, 3]));
+
+    let mut bytes = hash_cache.to_bytes(&DefaultContentHasher);
+    bytes[MAGIC.len()] = CACHE_VERSION + 1;
+    assert_eq!(
+        HashCache::from_bytes(&bytes, &DefaultContentHasher)
+            .err()
+            .unwrap()
+            .kind(),
+        io::ErrorKind::InvalidData
+    );
+    let bytes = hash_cache.to_bytes(&DefaultContentHasher);
+    assert!(HashCache::from_bytes(&bytes[..12], &DefaultContentHasher).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn should_reject_the_caches_of_other_hashers() {
+    use crate::hash::DefaultContentHasher;
+
+    struct Length;
+
+    impl ContentHasher for Length {
+        fn hash(&self, content: &[u8]) -> Vec<u8> {
+            (content.len() as u64).to_le_bytes().to_vec()
+        }
+    }
+
+    let hash_cache = HashCache::new();
+    let stamp = |nanos: u32| Stamp {
+        size: 11,
+        modified: UNIX_EPOCH + Duration::new(1_600_000_000, nanos),
+    };
+    hash_cache.insert("dir_one/hello.txt", stamp(999_999_999), vec![1, 2, 3]);
+    let bytes = hash_cache.to_bytes(&Length);
+
+    assert_eq!(HashCache::from_bytes(&bytes, &Length).unwrap().len(), 1);
+    assert_eq!(
+        HashCache::from_bytes(&bytes, &DefaultContentHasher)
+            .err()
+            .unwrap()
+            .kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    // the nanoseconds are the last bytes before the varint length of the digest
+    let position = bytes.len() - 4 - 5;
+    let mut overflowed = bytes.clone();
+    overflowed[position..position + 5].copy_from_slice(&[0x80, 0x94, 0xeb, 0xdc, 0x03]);
+    assert_eq!(
+        HashCache::from_bytes(&overflowed, &Length)
+            .err()
+            .unwrap()
+            .kind(),
+        io::ErrorKind::InvalidData
+    );
+}
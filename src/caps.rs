@@ -0,0 +1,176 @@
+//! Linux file capabilities, stored in the `security.capability` extended attribute of the
+//! binaries to grant them some privileges of root without being setuid
+use std::fmt;
+
+/// Names of the capabilities by number, like in `linux/capability.h`
+const NAMES: [&str; 41] = [
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+const REVISION_MASK: u32 = 0xff00_0000;
+const REVISION_1: u32 = 0x0100_0000;
+const REVISION_2: u32 = 0x0200_0000;
+const REVISION_3: u32 = 0x0300_0000;
+const FLAG_EFFECTIVE: u32 = 0x0000_0001;
+
+/// Capabilities of a file, empty if it has none. Displayed like `getcap`, for example
+/// `cap_net_bind_service=ep`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::caps::FileCapabilities;
+///
+/// let capabilities = FileCapabilities {
+///     permitted: 1 << 10,
+///     effective: true,
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(capabilities.to_string(), "cap_net_bind_service=ep");
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct FileCapabilities {
+    /// capabilities granted to the process which executes the file, the bit `n` is the
+    /// capability number `n`
+    pub permitted: u64,
+    /// capabilities kept from the process which executes the file if it has them
+    pub inheritable: u64,
+    /// true if the permitted capabilities are effective since the file is executed
+    pub effective: bool,
+}
+
+impl FileCapabilities {
+    /// Decode the value of the `security.capability` extended attribute, any revision of
+    /// the format. `None` if it is not valid
+    pub fn from_xattr(value: &[u8]) -> Option<Self> {
+        let word = |index: usize| -> Option<u64> {
+            let bytes = value.get(index * 4..index * 4 + 4)?;
+            Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64)
+        };
+        let magic = word(0)? as u32;
+        let words = match magic & REVISION_MASK {
+            REVISION_1 if value.len() == 12 => 1,
+            REVISION_2 if value.len() == 20 => 2,
+            REVISION_3 if value.len() == 24 => 2,
+            _ => return None,
+        };
+
+        let mut capabilities = FileCapabilities {
+            effective: magic & FLAG_EFFECTIVE != 0,
+            ..Default::default()
+        };
+        for index in 0..words {
+            capabilities.permitted |= word(1 + index * 2)? << (32 * index);
+            capabilities.inheritable |= word(2 + index * 2)? << (32 * index);
+        }
+
+        Some(capabilities)
+    }
+
+    /// Return true if the file has no capability
+    pub fn is_empty(&self) -> bool {
+        self.permitted == 0 && self.inheritable == 0
+    }
+}
+
+/// Name of the capability number, `cap_` followed by the number if it is unknown
+fn name(capability: usize) -> String {
+    match NAMES.get(capability) {
+        Some(name) => format!("cap_{}", name),
+        None => format!("cap_{}", capability),
+    }
+}
+
+impl fmt::Display for FileCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the capabilities with the same flags are grouped, like `cap_a,cap_b=ep cap_c=i`
+        let mut groups: Vec<(String, Vec<String>)> = vec![];
+        for capability in 0..64 {
+            let bit = 1u64 << capability;
+            let permitted = self.permitted & bit != 0;
+            let inheritable = self.inheritable & bit != 0;
+            if !permitted && !inheritable {
+                continue;
+            }
+            let mut flags = String::new();
+            if permitted && self.effective {
+                flags.push('e');
+            }
+            if inheritable {
+                flags.push('i');
+            }
+            if permitted {
+                flags.push('p');
+            }
+            match groups.iter_mut().find(|(group, _)| *group == flags) {
+                Some((_, names)) => names.push(name(capability)),
+                None => groups.push((flags, vec![name(capability)])),
+            }
+        }
+
+        let groups: Vec<String> = groups
+            .into_iter()
+            .map(|(flags, names)| format!("{}={}", names.join(","), flags))
+            .collect();
+        write!(f, "{}", groups.join(" "))
+    }
+}
+
+#[test]
+fn should_decode_the_capabilities_xattr() {
+    // what `setcap cap_net_bind_service,cap_net_raw=ep` writes, revision 2
+    let mut value: Vec<u8> = vec![];
+    for word in [REVISION_2 | FLAG_EFFECTIVE, (1 << 10) | (1 << 13), 0, 0, 0] {
+        value.extend_from_slice(&word.to_le_bytes());
+    }
+    let capabilities = FileCapabilities::from_xattr(&value).unwrap();
+
+    assert_eq!(capabilities.permitted, (1 << 10) | (1 << 13));
+    assert_eq!(
+        capabilities.to_string(),
+        "cap_net_bind_service,cap_net_raw=ep"
+    );
+    assert_eq!(FileCapabilities::from_xattr(&value[..16]), None);
+    assert_eq!(FileCapabilities::default().to_string(), "");
+}
@@ -0,0 +1,227 @@
+//! Content defined chunking, [FastCDC](https://www.usenix.org/conference/atc16/technical-sessions/presentation/xia)
+//! style, to know which parts of a big file changed without a byte by byte diff. The
+//! boundaries of the chunks depend on the content, so an insertion only changes the
+//! chunks around it instead of shifting all the following ones
+use crate::hash::content_hash;
+use std::collections::HashMap;
+
+/// Random values of the gear hash, one per byte, generated with splitmix64
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x5350_4945_4c52_5344;
+    let mut i = 0;
+    while i < 256 {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+
+    table
+}
+
+/// Mask with the `bits` highest bits set, the high bits of the gear hash depend on the
+/// last 64 bytes while the low bits only on the last ones
+fn mask(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        u64::MAX << (64 - bits.min(64))
+    }
+}
+
+/// Sizes of the chunks, a cut point is searched between `min_size` and `max_size`
+/// so the chunks have `avg_size` bytes on average
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Chunker {
+    /// minimum size of a chunk, except the last one
+    pub min_size: usize,
+    /// expected size of a chunk
+    pub avg_size: usize,
+    /// maximum size of a chunk
+    pub max_size: usize,
+}
+
+/// 2 KiB, 8 KiB and 64 KiB
+impl Default for Chunker {
+    fn default() -> Self {
+        Chunker {
+            min_size: 2 * 1024,
+            avg_size: 8 * 1024,
+            max_size: 64 * 1024,
+        }
+    }
+}
+
+/// Part of a content
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Chunk {
+    /// position of the first byte of the chunk in the content
+    pub offset: u64,
+    /// size in bytes
+    pub len: usize,
+    /// hash of the bytes of the chunk
+    pub hash: u64,
+}
+
+/// Result of comparing two contents by chunks
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct ChunkDiff {
+    /// number of chunks of the first content
+    pub chunks: usize,
+    /// number of chunks of the comparation content
+    pub chunks_comp: usize,
+    /// chunks of the comparation content which are in the first one too
+    pub common_chunks: usize,
+    /// size of the comparation content
+    pub len_comp: u64,
+    /// bytes of the comparation content in chunks which are not in the first one
+    pub changed_bytes: u64,
+}
+
+impl Chunker {
+    /// Create a chunker, panics unless `0 < min_size <= avg_size <= max_size`
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Self {
+        assert!(
+            0 < min_size && min_size <= avg_size && avg_size <= max_size,
+            "the sizes of the chunks must be 0 < min_size <= avg_size <= max_size"
+        );
+
+        Chunker {
+            min_size,
+            avg_size,
+            max_size,
+        }
+    }
+
+    /// Size of the first chunk of the content
+    fn cut_point(&self, content: &[u8]) -> usize {
+        let len = content.len().min(self.max_size);
+        if len <= self.min_size {
+            return len;
+        }
+
+        // normalized chunking: harder to cut before the average size and easier after it
+        let bits = (self.avg_size as f64).log2().round() as u32;
+        let mask_small = mask(bits + 1);
+        let mask_large = mask(bits.saturating_sub(1));
+        let normal = self.avg_size.clamp(self.min_size, len);
+
+        let mut hash: u64 = 0;
+        for (i, byte) in content.iter().enumerate().take(len).skip(self.min_size) {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            let mask = if i < normal { mask_small } else { mask_large };
+            if hash & mask == 0 {
+                return i + 1;
+            }
+        }
+
+        len
+    }
+
+    /// Split the content in chunks
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::chunk::Chunker;
+    ///
+    /// let content = vec![7u8; 100 * 1024];
+    /// let chunks = Chunker::default().chunks(&content);
+    ///
+    /// assert_eq!(chunks.iter().map(|chunk| chunk.len).sum::<usize>(), content.len());
+    /// assert!(chunks.iter().all(|chunk| chunk.len <= 64 * 1024));
+    /// ```
+    pub fn chunks(&self, content: &[u8]) -> Vec<Chunk> {
+        let mut chunks: Vec<Chunk> = vec![];
+        let mut offset: usize = 0;
+
+        while offset < content.len() {
+            let len = self.cut_point(&content[offset..]);
+            chunks.push(Chunk {
+                offset: offset as u64,
+                len,
+                hash: content_hash(&content[offset..offset + len]),
+            });
+            offset += len;
+        }
+
+        chunks
+    }
+
+    /// Compare two contents by the hashes of their chunks
+    pub fn diff(&self, content: &[u8], content_comp: &[u8]) -> ChunkDiff {
+        let chunks = self.chunks(content);
+        let chunks_comp = self.chunks(content_comp);
+
+        let mut counts: HashMap<(u64, usize), usize> = HashMap::new();
+        for chunk in &chunks {
+            *counts.entry((chunk.hash, chunk.len)).or_default() += 1;
+        }
+
+        let mut diff = ChunkDiff {
+            chunks: chunks.len(),
+            chunks_comp: chunks_comp.len(),
+            len_comp: content_comp.len() as u64,
+            ..ChunkDiff::default()
+        };
+        for chunk in &chunks_comp {
+            match counts.get_mut(&(chunk.hash, chunk.len)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    diff.common_chunks += 1;
+                }
+                _ => diff.changed_bytes += chunk.len as u64,
+            }
+        }
+
+        diff
+    }
+}
+
+/// Content which doesn't repeat, so the chunks are not all of `max_size`
+#[cfg(test)]
+fn noise(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn should_split_in_chunks_between_min_and_max_size() {
+    let chunker = Chunker::new(256, 1024, 4096);
+    let content = noise(64 * 1024, 1);
+    let chunks = chunker.chunks(&content);
+
+    assert_eq!(
+        chunks.iter().map(|chunk| chunk.len).sum::<usize>(),
+        content.len()
+    );
+    assert!(chunks[..chunks.len() - 1]
+        .iter()
+        .all(|chunk| chunk.len >= 256 && chunk.len <= 4096));
+    assert!(chunks.len() > 8);
+}
+
+#[test]
+fn should_only_change_the_chunks_around_an_insertion() {
+    let chunker = Chunker::new(256, 1024, 4096);
+    let content = noise(64 * 1024, 2);
+    let mut content_comp = content.clone();
+    content_comp.splice(30_000..30_000, b"inserted bytes".iter().copied());
+
+    let diff = chunker.diff(&content, &content_comp);
+    assert_eq!(diff.len_comp, content.len() as u64 + 14);
+    assert!(diff.changed_bytes > 0 && diff.changed_bytes <= 2 * 4096 + 14);
+    assert_eq!(chunker.diff(&content, &content).changed_bytes, 0);
+}
@@ -0,0 +1,212 @@
+//! Equality of the files present in both trees, pluggable through the `FileComparator` trait
+use async_trait::async_trait;
+use std::fmt;
+use std::io;
+
+/// Decide if two files with the same relative path are equal from their contents.
+/// Set it in `DirDiff::comparator` to replace the byte comparison, for example to
+/// ignore the timestamps embedded in generated files
+///
+/// # Example
+///
+/// ```rust
+/// use async_trait::async_trait;
+/// use spielrs_diff::compare::FileComparator;
+/// use std::io;
+///
+/// /// Ignore the first line of the `.log` files
+/// struct SkipLogHeader;
+///
+/// #[async_trait]
+/// impl FileComparator for SkipLogHeader {
+///     async fn equal(&self, path: &str, content: &[u8], content_comp: &[u8]) -> io::Result<bool> {
+///         if !path.ends_with(".log") {
+///             return Ok(content == content_comp);
+///         }
+///         let body = |content: &[u8]| -> Vec<u8> {
+///             content.splitn(2, |byte| *byte == b'\n').nth(1).unwrap_or_default().to_vec()
+///         };
+///
+///         Ok(body(content) == body(content_comp))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait FileComparator: Send + Sync {
+    /// Return true if both contents are equal, `path` is relative to the root of the trees.
+    /// The files are reported as modified if it fails
+    async fn equal(&self, path: &str, content: &[u8], content_comp: &[u8]) -> io::Result<bool>;
+}
+
+impl fmt::Debug for dyn FileComparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FileComparator")
+    }
+}
+
+/// Compare the files byte by byte, the default comparator
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ByteComparator;
+
+#[async_trait]
+impl FileComparator for ByteComparator {
+    async fn equal(&self, _path: &str, content: &[u8], content_comp: &[u8]) -> io::Result<bool> {
+        Ok(content == content_comp)
+    }
+}
+
+/// Compare the `.csv` files by their records, optionally ignoring the order of the rows
+/// and some columns, and the other files byte by byte. The first record is the header,
+/// which names the columns
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::compare::CsvComparator;
+/// use spielrs_diff::diff::DirDiff;
+/// use std::sync::Arc;
+///
+/// let options = DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_two".to_string(),
+///     comparator: Some(Arc::new(CsvComparator {
+///         ignore_row_order: true,
+///         ignore_columns: vec!["exported_at".to_string()],
+///         ..Default::default()
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct CsvComparator {
+    /// separator of the fields, `,` by default
+    pub delimiter: u8,
+    /// compare the rows after the header as a set, with repetitions
+    pub ignore_row_order: bool,
+    /// names in the header of the columns which are not compared
+    pub ignore_columns: Vec<String>,
+}
+
+impl Default for CsvComparator {
+    fn default() -> Self {
+        CsvComparator {
+            delimiter: b',',
+            ignore_row_order: false,
+            ignore_columns: vec![],
+        }
+    }
+}
+
+impl CsvComparator {
+    /// Return the records of the content without the ignored columns, the rows sorted
+    /// if their order is ignored
+    fn records(&self, content: &[u8]) -> Vec<Vec<Vec<u8>>> {
+        let mut records = parse_csv(content, self.delimiter);
+        let ignored: Vec<bool> = match records.first() {
+            Some(header) => header
+                .iter()
+                .map(|name| {
+                    self.ignore_columns
+                        .iter()
+                        .any(|column| column.as_bytes() == name.as_slice())
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        for record in records.iter_mut() {
+            let mut column = 0;
+            record.retain(|_| {
+                column += 1;
+                !ignored.get(column - 1).copied().unwrap_or(false)
+            });
+        }
+        if self.ignore_row_order && records.len() > 1 {
+            records[1..].sort();
+        }
+
+        records
+    }
+}
+
+#[async_trait]
+impl FileComparator for CsvComparator {
+    async fn equal(&self, path: &str, content: &[u8], content_comp: &[u8]) -> io::Result<bool> {
+        if !path.to_ascii_lowercase().ends_with(".csv") {
+            return Ok(content == content_comp);
+        }
+
+        Ok(self.records(content) == self.records(content_comp))
+    }
+}
+
+/// Split the content in records of fields, unquoting the fields between `"`. The empty
+/// lines are skipped and `\r\n` ends a record as `\n`
+fn parse_csv(content: &[u8], delimiter: u8) -> Vec<Vec<Vec<u8>>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = vec![];
+    let mut quoted = false;
+    let mut i = 0;
+
+    while i < content.len() {
+        let byte = content[i];
+        if quoted {
+            match (byte, content.get(i + 1)) {
+                (b'"', Some(b'"')) => {
+                    field.push(b'"');
+                    i += 1;
+                }
+                (b'"', _) => quoted = false,
+                _ => field.push(byte),
+            }
+        } else if byte == b'"' {
+            quoted = true;
+        } else if byte == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if byte == b'\n' {
+            if field.last() == Some(&b'\r') {
+                field.pop();
+            }
+            record.push(std::mem::take(&mut field));
+            if record.len() > 1 || !record[0].is_empty() {
+                records.push(std::mem::take(&mut record));
+            }
+        } else {
+            field.push(byte);
+        }
+        i += 1;
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_csv_records() {
+    let content = b"id,name,exported_at\n1,\"Doe, John\",2020\n2,Jane,2020\n";
+    let content_comp = b"id,name,exported_at\r\n2,Jane,2021\r\n1,\"Doe, John\",2021\r\n";
+    let comparator = |ignore_row_order: bool, ignore_columns: Vec<String>| CsvComparator {
+        ignore_row_order,
+        ignore_columns,
+        ..Default::default()
+    };
+    let exported_at = vec!["exported_at".to_string()];
+
+    assert!(comparator(true, exported_at.clone())
+        .equal("data.csv", content, content_comp)
+        .await
+        .unwrap());
+    assert!(!comparator(false, exported_at)
+        .equal("data.csv", content, content_comp)
+        .await
+        .unwrap());
+    assert!(!comparator(true, vec![])
+        .equal("data.csv", content, content_comp)
+        .await
+        .unwrap());
+}
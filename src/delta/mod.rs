@@ -0,0 +1,320 @@
+//! Rsync style delta: the signature of a file, the weak rolling checksum and the strong hash
+//! of each of its blocks, is enough to compute from another file which blocks can be copied
+//! and which bytes must be transferred to rebuild it
+use crate::hash::content_hash;
+use crate::tree::Tree;
+use crate::vfs::{DiffFs, EntryKind};
+use std::collections::{BTreeMap, HashMap};
+
+pub mod patch;
+
+/// Default size of the blocks of a signature
+pub const BLOCK_SIZE: usize = 2048;
+
+/// Weak and strong hashes of a block
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BlockSignature {
+    /// rolling checksum of the block
+    pub weak: u32,
+    /// hash of the block, checked when the weak checksum matches
+    pub strong: u64,
+}
+
+/// Signature of a file, the last block can be smaller than `block_size`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Signature {
+    /// size of the blocks in bytes
+    pub block_size: usize,
+    /// size of the file in bytes
+    pub len: u64,
+    /// hashes of every block
+    pub blocks: Vec<BlockSignature>,
+}
+
+/// Operation to rebuild the new file from the file of the signature
+#[derive(Debug, PartialEq, Clone)]
+pub enum DeltaOp {
+    /// copy the block with this index of the file of the signature
+    Copy(usize),
+    /// bytes of the new file which are not in the file of the signature
+    Data(Vec<u8>),
+}
+
+/// Operations which transform the file of a signature into the new file
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Delta {
+    /// operations in order
+    pub ops: Vec<DeltaOp>,
+}
+
+impl Delta {
+    /// Bytes which must be transferred, the literal data
+    pub fn data_bytes(&self) -> u64 {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                DeltaOp::Data(data) => data.len() as u64,
+                DeltaOp::Copy(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Number of blocks reused from the file of the signature
+    pub fn copied_blocks(&self) -> usize {
+        self.ops
+            .iter()
+            .filter(|op| matches!(op, DeltaOp::Copy(_)))
+            .count()
+    }
+
+    /// Return true if the new file is the same than the file of the signature
+    pub fn is_copy(&self, signature: &Signature) -> bool {
+        self.ops.len() == signature.blocks.len()
+            && self
+                .ops
+                .iter()
+                .enumerate()
+                .all(|(index, op)| *op == DeltaOp::Copy(index))
+    }
+
+    fn push_data(&mut self, byte: u8) {
+        match self.ops.last_mut() {
+            Some(DeltaOp::Data(data)) => data.push(byte),
+            _ => self.ops.push(DeltaOp::Data(vec![byte])),
+        }
+    }
+}
+
+/// Checksum of rsync, it can be rolled one byte forward without reading the whole block
+#[derive(Debug, Default, Clone, Copy)]
+struct Rolling {
+    a: u16,
+    b: u16,
+    len: usize,
+}
+
+impl Rolling {
+    fn new(block: &[u8]) -> Self {
+        let mut rolling = Rolling {
+            len: block.len(),
+            ..Rolling::default()
+        };
+        for (i, byte) in block.iter().enumerate() {
+            rolling.a = rolling.a.wrapping_add(*byte as u16);
+            rolling.b = rolling
+                .b
+                .wrapping_add(((block.len() - i) as u16).wrapping_mul(*byte as u16));
+        }
+
+        rolling
+    }
+
+    fn roll(&mut self, out: u8, into: u8) {
+        self.a = self.a.wrapping_sub(out as u16).wrapping_add(into as u16);
+        self.b = self
+            .b
+            .wrapping_sub((self.len as u16).wrapping_mul(out as u16))
+            .wrapping_add(self.a);
+    }
+
+    fn value(&self) -> u32 {
+        (self.a as u32) | ((self.b as u32) << 16)
+    }
+}
+
+/// Compute the signature of a content, panics if `block_size` is 0
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::delta::{delta, signature};
+///
+/// let old = b"The quick brown fox jumps over the lazy dog".to_vec();
+/// let new = b"The quick brown cat jumps over the lazy dog".to_vec();
+///
+/// let delta = delta(&signature(&old, 8), &new);
+/// assert_eq!(delta.copied_blocks(), 5);
+/// assert_eq!(delta.data_bytes(), 8);
+/// ```
+pub fn signature(content: &[u8], block_size: usize) -> Signature {
+    assert!(block_size > 0, "the block size must be greater than 0");
+
+    Signature {
+        block_size,
+        len: content.len() as u64,
+        blocks: content
+            .chunks(block_size)
+            .map(|block| BlockSignature {
+                weak: Rolling::new(block).value(),
+                strong: content_hash(block),
+            })
+            .collect(),
+    }
+}
+
+/// Compute the delta which rebuilds `content` from the file of the signature
+pub fn delta(signature: &Signature, content: &[u8]) -> Delta {
+    let mut weak_index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, block) in signature.blocks.iter().enumerate() {
+        weak_index.entry(block.weak).or_default().push(index);
+    }
+
+    let block_size = signature.block_size;
+    let mut delta = Delta::default();
+    let mut start: usize = 0;
+    let mut rolling: Option<Rolling> = None;
+
+    while start < content.len() {
+        let end = (start + block_size).min(content.len());
+        let window = &content[start..end];
+        let checksum = match rolling {
+            Some(checksum) if checksum.len == window.len() => checksum,
+            _ => Rolling::new(window),
+        };
+
+        let matched = weak_index.get(&checksum.value()).and_then(|candidates| {
+            let strong = content_hash(window);
+            candidates.iter().copied().find(|index| {
+                let block = signature.blocks[*index];
+                block.strong == strong && block_len(signature, *index) == window.len()
+            })
+        });
+
+        match matched {
+            Some(index) => {
+                delta.ops.push(DeltaOp::Copy(index));
+                start = end;
+                rolling = None;
+            }
+            None => {
+                delta.push_data(content[start]);
+                if end < content.len() {
+                    let mut next = checksum;
+                    next.roll(content[start], content[end]);
+                    rolling = Some(next);
+                } else {
+                    rolling = None;
+                }
+                start += 1;
+            }
+        }
+    }
+
+    delta
+}
+
+fn block_len(signature: &Signature, index: usize) -> usize {
+    let offset = (index * signature.block_size) as u64;
+    (signature.len - offset).min(signature.block_size as u64) as usize
+}
+
+/// Compute the signatures of every file of the tree, by path relative to its root
+pub async fn tree_signature<F: DiffFs + ?Sized>(
+    fs: &F,
+    tree: &[Tree],
+    block_size: usize,
+) -> BTreeMap<String, Signature> {
+    let mut signatures: BTreeMap<String, Signature> = BTreeMap::new();
+    for (path, entry) in Tree::iter(tree) {
+        if entry.kind == EntryKind::File {
+            let content = fs.read(&entry.path).await.unwrap();
+            signatures.insert(path, signature(&content, block_size));
+        }
+    }
+
+    signatures
+}
+
+/// Compute the deltas which rebuild the files of the tree from the signatures of another
+/// tree, the files with the same content are not included and the files without a
+/// signature with the same path are sent whole
+pub async fn tree_delta<F: DiffFs + ?Sized>(
+    fs: &F,
+    tree: &[Tree],
+    signatures: &BTreeMap<String, Signature>,
+) -> BTreeMap<String, Delta> {
+    let mut deltas: BTreeMap<String, Delta> = BTreeMap::new();
+    for (path, entry) in Tree::iter(tree) {
+        if entry.kind != EntryKind::File {
+            continue;
+        }
+
+        let content = fs.read(&entry.path).await.unwrap();
+        match signatures.get(&path) {
+            Some(signature) => {
+                let delta = delta(signature, &content);
+                if !delta.is_copy(signature) {
+                    deltas.insert(path, delta);
+                }
+            }
+            None => {
+                let ops = if content.is_empty() {
+                    vec![]
+                } else {
+                    vec![DeltaOp::Data(content)]
+                };
+                deltas.insert(path, Delta { ops });
+            }
+        }
+    }
+
+    deltas
+}
+
+#[test]
+fn should_copy_the_blocks_moved_to_another_offset() {
+    let old: Vec<u8> = (0..=255u8).cycle().take(64).collect();
+    let mut new = b"prefix".to_vec();
+    new.extend_from_slice(&old);
+
+    let signature = signature(&old, 16);
+    let moved = delta(&signature, &new);
+
+    assert_eq!(
+        moved.ops,
+        vec![
+            DeltaOp::Data(b"prefix".to_vec()),
+            DeltaOp::Copy(0),
+            DeltaOp::Copy(1),
+            DeltaOp::Copy(2),
+            DeltaOp::Copy(3),
+        ]
+    );
+    assert!(delta(&signature, &old).is_copy(&signature));
+}
+
+#[test]
+fn should_match_the_last_short_block() {
+    let old = b"0123456789abc".to_vec();
+    let signature = signature(&old, 5);
+    let shifted = delta(&signature, b"XX0123456789abc");
+
+    assert_eq!(shifted.data_bytes(), 2);
+    assert_eq!(shifted.copied_blocks(), 3);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compute_the_delta_of_a_tree() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/same.txt", "same content")
+        .file("dir_one/changed.txt", "Hello world, how are you?")
+        .file("dir_two/same.txt", "same content")
+        .file("dir_two/changed.txt", "Hello world, who are you?")
+        .file("dir_two/new.txt", "new")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let signatures = tree_signature(&fs, &tree, 4).await;
+    let deltas = tree_delta(&fs, &tree_comp, &signatures).await;
+
+    assert_eq!(
+        deltas.keys().collect::<Vec<_>>(),
+        vec!["changed.txt", "new.txt"]
+    );
+    assert_eq!(deltas["new.txt"].ops, vec![DeltaOp::Data(b"new".to_vec())]);
+    assert!(deltas["changed.txt"].data_bytes() < 25);
+}
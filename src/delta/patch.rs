@@ -0,0 +1,188 @@
+//! Compact binary patches, in the style of VCDIFF, built from a `Delta`. A patch is a
+//! header with the size of the new file followed by instructions to copy a range of the
+//! old file or to add literal bytes, the numbers are written as LEB128 varints
+use super::{delta, signature, Delta, DeltaOp, Signature};
+use std::io;
+
+/// First bytes of every patch
+const MAGIC: &[u8; 4] = b"SPD1";
+const COPY: u8 = 1;
+const ADD: u8 = 2;
+
+/// Size of the blocks used by `create_patch`, small enough to find the common parts of
+/// binary files with small changes
+pub const PATCH_BLOCK_SIZE: usize = 64;
+
+pub(crate) fn write_varint(mut value: u64, patch: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            patch.push(byte);
+            return;
+        }
+        patch.push(byte | 0x80);
+    }
+}
+
+pub(crate) fn read_varint(patch: &[u8], position: &mut usize) -> io::Result<u64> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *patch
+            .get(*position)
+            .ok_or_else(|| invalid("truncated varint"))?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid("varint too long"))
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Delta {
+    /// Encode the delta as a binary patch, the consecutive blocks are copied as one range
+    pub fn to_patch(&self, signature: &Signature) -> Vec<u8> {
+        let mut instructions: Vec<u8> = vec![];
+        let mut copy: Option<(u64, u64)> = None;
+        let mut len: u64 = 0;
+
+        for op in &self.ops {
+            match op {
+                DeltaOp::Copy(index) => {
+                    let offset = (*index * signature.block_size) as u64;
+                    let block_len = (signature.len - offset).min(signature.block_size as u64);
+                    len += block_len;
+                    copy = match copy {
+                        Some((start, copy_len)) if start + copy_len == offset => {
+                            Some((start, copy_len + block_len))
+                        }
+                        Some((start, copy_len)) => {
+                            write_copy(start, copy_len, &mut instructions);
+                            Some((offset, block_len))
+                        }
+                        None => Some((offset, block_len)),
+                    };
+                }
+                DeltaOp::Data(data) => {
+                    if let Some((start, copy_len)) = copy.take() {
+                        write_copy(start, copy_len, &mut instructions);
+                    }
+                    len += data.len() as u64;
+                    instructions.push(ADD);
+                    write_varint(data.len() as u64, &mut instructions);
+                    instructions.extend_from_slice(data);
+                }
+            }
+        }
+        if let Some((start, copy_len)) = copy {
+            write_copy(start, copy_len, &mut instructions);
+        }
+
+        let mut patch = MAGIC.to_vec();
+        write_varint(len, &mut patch);
+        patch.extend(instructions);
+
+        patch
+    }
+}
+
+fn write_copy(offset: u64, len: u64, patch: &mut Vec<u8>) {
+    patch.push(COPY);
+    write_varint(offset, patch);
+    write_varint(len, patch);
+}
+
+/// Create the patch which transforms `old` into `new`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::delta::patch::{apply_patch, create_patch};
+///
+/// let old: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+/// let mut new = old.clone();
+/// new[2000] = 0xff;
+///
+/// let patch = create_patch(&old, &new);
+/// assert!(patch.len() < 100);
+/// assert_eq!(apply_patch(&old, &patch).unwrap(), new);
+/// ```
+pub fn create_patch(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let signature = signature(old, PATCH_BLOCK_SIZE);
+
+    delta(&signature, new).to_patch(&signature)
+}
+
+/// Apply a patch to `old` and return the new content, fails with `InvalidData` if the
+/// patch is malformed or doesn't belong to `old`
+pub fn apply_patch(old: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    if !patch.starts_with(MAGIC) {
+        return Err(invalid("not a patch"));
+    }
+
+    let mut position = MAGIC.len();
+    let len = read_varint(patch, &mut position)?;
+    let mut new: Vec<u8> = Vec::with_capacity(len.min(1 << 30) as usize);
+
+    while position < patch.len() {
+        let instruction = patch[position];
+        position += 1;
+        match instruction {
+            COPY => {
+                let offset = read_varint(patch, &mut position)? as usize;
+                let copy_len = read_varint(patch, &mut position)? as usize;
+                let range = old
+                    .get(offset..offset.saturating_add(copy_len))
+                    .ok_or_else(|| invalid("copy out of the old content"))?;
+                new.extend_from_slice(range);
+            }
+            ADD => {
+                let add_len = read_varint(patch, &mut position)? as usize;
+                let data = patch
+                    .get(position..position.saturating_add(add_len))
+                    .ok_or_else(|| invalid("truncated patch"))?;
+                new.extend_from_slice(data);
+                position += add_len;
+            }
+            _ => return Err(invalid("unknown instruction")),
+        }
+    }
+
+    if new.len() as u64 != len {
+        return Err(invalid("the size of the patched content doesn't match"));
+    }
+
+    Ok(new)
+}
+
+#[test]
+fn should_apply_a_patch_with_insertions_and_deletions() {
+    let old: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 253) as u8).collect();
+    let mut new = old.clone();
+    new.drain(1000..1500);
+    new.splice(5000..5000, b"inserted".iter().copied());
+    new.truncate(9000);
+
+    let patch = create_patch(&old, &new);
+    assert!(patch.len() < 300);
+    assert_eq!(apply_patch(&old, &patch).unwrap(), new);
+    assert_eq!(apply_patch(b"", &create_patch(b"", b"")).unwrap(), b"");
+}
+
+#[test]
+fn should_reject_invalid_patches() {
+    let old = b"Hello world, ".repeat(20);
+    let mut new = old.clone();
+    new.extend_from_slice(b"hello vlang");
+    let patch = create_patch(&old, &new);
+
+    assert!(apply_patch(b"Hello", &patch).is_err());
+    assert!(apply_patch(&old, &patch[..patch.len() - 1]).is_err());
+    assert!(apply_patch(b"Hello world", b"diff").is_err());
+}
@@ -1,3 +1,15 @@
+use crate::cache::HashCache;
+use crate::chunk::Chunker;
+use crate::compare::FileComparator;
+use crate::filter::EntryFilter;
+use crate::hash::{ContentHasher, DefaultContentHasher};
+use crate::normalize::Normalization;
+use crate::vfs::VfsMetadata;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone)]
 pub struct DirDiff {
     /// directory to compare
     pub dir: String,
@@ -7,11 +19,382 @@ pub struct DirDiff {
     pub excluding: Option<Vec<String>>,
     /// exclude recursively or only the from the root path
     pub recursive_excluding: bool,
+    /// exclude the entries by name only inside of a directory, at any depth, written as
+    /// the path of the directory relative to the root and the name, like `var/cache`
+    /// which excludes `var/cache` and `var/lib/cache` but not `cache` nor `usr/cache`. A
+    /// name without a directory is scoped to the root, excluded at any depth
+    pub scoped_excluding: Option<Vec<String>>,
+    /// exclude the entries by their path relative to the root, like `vlang/purpose`,
+    /// without excluding the entries with the same name in other directories
+    pub excluding_paths: Option<Vec<String>>,
+    /// exclude the entries whose path relative to the root matches any of these patterns
+    /// anchored like the lines of a `.gitignore`: `/build` only in the root, `build/`
+    /// only directories at any depth and `**/build` at any depth, see `PathPattern`
+    pub excluding_patterns: Option<Vec<String>>,
+    /// exclude the entries whose path relative to the root matches the glob set, already
+    /// compiled by the application so it is not parsed again on every comparison
+    #[cfg(feature = "globset")]
+    pub excluding_globs: Option<globset::GlobSet>,
+    /// exclude the files with any of these extensions, with or without the dot and
+    /// ignoring the case, in every subdirectory. Directories are never excluded by it
+    pub exclude_extensions: Option<Vec<String>>,
+    /// exclude the entries whose path relative to the root matches any of the regular
+    /// expressions of the set, like `^target/` or `\.bak$`, compiled by the application
+    /// with `RegexSet::new` which returns an error if one is invalid
+    #[cfg(feature = "regex")]
+    pub excluding_regex: Option<regex::RegexSet>,
+    /// exclude the files smaller than this size in bytes
+    pub min_size: Option<u64>,
+    /// exclude the files bigger than this size in bytes. The sources which don't know
+    /// the size of the files, like html index listings, report them as empty
+    pub max_size: Option<u64>,
+    /// keep only the entries for which it returns true, consulted after the exclusions by
+    /// name, path, size and extension and before `exclude_by_metadata`. The content of an
+    /// excluded directory is never listed
+    pub filter: Option<Arc<dyn EntryFilter>>,
+    /// exclude the entries for which it returns true reading their metadata, like the
+    /// files not modified for a month. Consulted after the other exclusions, it reads the
+    /// metadata of every entry which is not already excluded
+    pub exclude_by_metadata: Option<MetadataExclusion>,
+    /// skip the directories without files, after the exclusions, as if they didn't
+    /// exist. Useful to compare with sources like git or S3 which can't store them
+    pub ignore_empty_dirs: bool,
+    /// stop listing the directories of each tree after this number of entries, so a
+    /// directory with millions of them can't exhaust the memory. The report of the
+    /// truncated trees is flagged with `DiffReport::truncated`
+    pub max_entries: Option<usize>,
+    /// follow the symbolic links while building the trees, walking the directories and
+    /// comparing the files they point to. A link to one of the directories which contain
+    /// it is recorded as an `EntryKind::Cycle` instead of being walked forever
+    pub follow_symlinks: bool,
+    /// detect the content type of the files from their first bytes while building the
+    /// trees, stored in `Tree::content_type` and reported in `DiffReport::content_types`
+    pub detect_content_type: bool,
+    /// report a removed and an added file as a rename with changes when the similarity
+    /// of their content is at least this ratio, from 0.0 to 1.0. By default only the
+    /// files with the same content are renamed
+    pub similarity_threshold: Option<f64>,
+    /// compare the modified files by content defined chunks to report how many of
+    /// their bytes changed
+    pub chunking: Option<Chunker>,
+    /// don't read again the files hard linked to already compared ones, using the device
+    /// and inode recorded in the `Tree`. Their digests are kept, so the links to equal
+    /// files are equal without reading them. Not used with a `comparator` or a `normalization`
+    pub hardlinks: bool,
+    /// report the files with the same content which are hard linked to different files
+    /// in each directory
+    pub report_hardlinks: bool,
+    /// compare the linux capabilities of the files present in both directories, like
+    /// `cap_net_bind_service` on binaries, if both sources know them. The files whose
+    /// capabilities differ are modified. `LocalFs` needs the `capabilities` feature
+    pub compare_capabilities: bool,
+    /// compare the NTFS alternate data streams of the files present in both directories,
+    /// where some content can hide on windows, if both sources know them. The files whose
+    /// streams differ are modified. `LocalFs` needs the `streams` feature
+    pub compare_streams: bool,
+    /// compare only the allocated regions of the sparse files with the same size, if
+    /// the `DiffFs` knows them. `LocalFs` needs the `sparse` feature
+    pub sparse: bool,
+    /// normalizations applied to the content of the files before comparing them, not
+    /// used by the `comparator`
+    pub normalization: Normalization,
+    /// decide if the files present in both directories are equal instead of comparing
+    /// their bytes, not used by `CompareMode::Content`
+    pub comparator: Option<Arc<dyn FileComparator>>,
+    /// digest of the contents used by the comparisons based on hashes, by default
+    /// `DefaultContentHasher`
+    pub hasher: Option<Arc<dyn ContentHasher>>,
+    /// skip reading the files present in both directories whose size and modification
+    /// time didn't change since they were cached with equal digests. Not used with a
+    /// `comparator`
+    pub hash_cache: Option<Arc<HashCache>>,
+    /// return the digest of both sides of the files present in both directories in
+    /// `DiffReport::digests`, computed with the `hasher`
+    pub report_digests: bool,
+    /// return the lines changed in the modified text files in `DiffReport::text_diffs`,
+    /// grouped in hunks with 3 lines of context like `diff -u`
+    pub report_text_diffs: bool,
+    /// report the directories which can't be listed and the files which can't be read as
+    /// `Difference::Modified`. By default they are only listed in `DiffReport::unreadable`
+    /// and the comparison goes on without them
+    pub unreadable_as_modified: bool,
+    /// what to do when an entry can't be read, by default the errors are collected in
+    /// `DiffReport::errors` and the comparison goes on
+    pub on_error: ErrorPolicy,
+    /// what is compared, by default the structure and the content of the files
+    pub mode: CompareMode,
+    /// compare the modification times of the files too, a file with the same content
+    /// and a different modification time is modified
+    pub compare_mtime: bool,
+    /// modification times which differ less than this are equal, useful to compare
+    /// filesystems with different granularity like FAT or zip archives (2 seconds)
+    pub mtime_tolerance: Duration,
+    /// abort the comparison with `DiffError::Timeout` if it takes more than this, to not
+    /// hang forever on a dead network mount
+    pub timeout: Option<Duration>,
+    /// called with the relative path of every entry of both trees when it is compared,
+    /// the content of the directories only present in one side is not visited
+    pub on_entry_visited: Option<EntryHook>,
+    /// called with the relative path of every file present in both trees once it is
+    /// compared and true if it is equal. Not called with `CompareMode::Content`
+    pub on_file_compared: Option<CompareHook>,
+}
+
+impl DirDiff {
+    /// Options which report any difference: the modification times and the hard links
+    /// are compared, the hidden files are included, nothing is normalized and the first
+    /// entry which can't be read aborts the comparison. Set the directories with
+    /// `..DirDiff::strict()`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::diff::DirDiff;
+    ///
+    /// let options = DirDiff {
+    ///     dir: "./mocks/dir_one".to_string(),
+    ///     dir_comp: "./mocks/dir_two".to_string(),
+    ///     ..DirDiff::strict()
+    /// };
+    ///
+    /// assert!(options.compare_mtime);
+    /// ```
+    pub fn strict() -> Self {
+        DirDiff {
+            compare_mtime: true,
+            report_hardlinks: true,
+            unreadable_as_modified: true,
+            on_error: ErrorPolicy::FailFast,
+            ..Default::default()
+        }
+    }
+
+    /// Options which report only the changes of the contents which matter: the hidden
+    /// files and the empty directories are skipped, the trailing newlines are ignored and
+    /// the json, yaml and toml files are compared by their values if their features are
+    /// enabled. The modification times are not compared and the entries which can't be
+    /// read are only collected in the report
+    pub fn lenient() -> Self {
+        DirDiff {
+            filter: Some(Arc::new(|entry: &DirEntryInfo| {
+                !entry.name.starts_with('.')
+            })),
+            ignore_empty_dirs: true,
+            normalization: Normalization {
+                ignore_trailing_newline: true,
+                #[cfg(feature = "json")]
+                structural_json: true,
+                #[cfg(feature = "yaml")]
+                structural_yaml: true,
+                #[cfg(feature = "toml")]
+                structural_toml: true,
+                ..Default::default()
+            },
+            on_error: ErrorPolicy::Collect,
+            ..Default::default()
+        }
+    }
+
+    /// Return the `hasher` of the options or the default one
+    pub fn content_hasher(&self) -> &dyn ContentHasher {
+        match &self.hasher {
+            Some(hasher) => hasher.as_ref(),
+            None => &DefaultContentHasher,
+        }
+    }
+}
+
+/// Entry found while the tree is built, passed to the `filter` of `DirDiff`
+#[derive(Debug, PartialEq, Clone)]
+pub struct DirEntryInfo {
+    /// name of the file or directory
+    pub name: String,
+    /// path relative to the root of the tree, separated by `/`
+    pub rel_path: String,
+    /// true if the entry is a directory
+    pub is_dir: bool,
+    /// size in bytes of the file, 0 for directories or if the source doesn't know it
+    pub len: u64,
+    /// number of directories between the root and the entry, 0 in the root
+    pub depth: usize,
+}
+
+/// Closure which excludes the entries of the trees by their metadata, see
+/// `DirDiff::exclude_by_metadata`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::diff::{DirDiff, MetadataExclusion};
+/// use std::time::{Duration, SystemTime};
+///
+/// // skip the files not modified in the last 30 days
+/// let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+/// let options = DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_two".to_string(),
+///     exclude_by_metadata: Some(MetadataExclusion::new(move |entry, metadata| {
+///         !entry.is_dir && metadata.modified.is_some_and(|modified| modified < month_ago)
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct MetadataExclusion(Arc<MetadataFn>);
+
+type MetadataFn = dyn Fn(&DirEntryInfo, &VfsMetadata) -> bool + Send + Sync;
+
+impl MetadataExclusion {
+    /// Create an exclusion of the entries for which `exclusion` returns true
+    pub fn new<F: Fn(&DirEntryInfo, &VfsMetadata) -> bool + Send + Sync + 'static>(
+        exclusion: F,
+    ) -> Self {
+        MetadataExclusion(Arc::new(exclusion))
+    }
+
+    /// Return true if the entry is excluded
+    pub fn excludes(&self, entry: &DirEntryInfo, metadata: &VfsMetadata) -> bool {
+        (self.0)(entry, metadata)
+    }
+}
+
+impl fmt::Debug for MetadataExclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetadataExclusion")
+    }
+}
+
+/// Closure called with the entries compared, see `DirDiff::on_entry_visited`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::diff::{DirDiff, EntryHook};
+///
+/// let options = DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_two".to_string(),
+///     on_entry_visited: Some(EntryHook::new(|path| println!("visited {}", path))),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct EntryHook(Arc<dyn Fn(&str) + Send + Sync>);
+
+impl EntryHook {
+    /// Create a hook which calls `hook`
+    pub fn new<F: Fn(&str) + Send + Sync + 'static>(hook: F) -> Self {
+        EntryHook(Arc::new(hook))
+    }
+
+    /// Call the hook with the relative path of the entry
+    pub fn call(&self, path: &str) {
+        (self.0)(path)
+    }
+}
+
+impl fmt::Debug for EntryHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EntryHook")
+    }
+}
+
+/// Closure called with the files compared and if they are equal, see
+/// `DirDiff::on_file_compared`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::diff::{CompareHook, DirDiff};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// let modified = Arc::new(AtomicUsize::new(0));
+/// let counter = modified.clone();
+/// let options = DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_two".to_string(),
+///     on_file_compared: Some(CompareHook::new(move |_, equal| {
+///         if !equal {
+///             counter.fetch_add(1, Ordering::Relaxed);
+///         }
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct CompareHook(Arc<CompareFn>);
+
+type CompareFn = dyn Fn(&str, bool) + Send + Sync;
+
+impl CompareHook {
+    /// Create a hook which calls `hook`
+    pub fn new<F: Fn(&str, bool) + Send + Sync + 'static>(hook: F) -> Self {
+        CompareHook(Arc::new(hook))
+    }
+
+    /// Call the hook with the relative path of the file and if it is equal
+    pub fn call(&self, path: &str, equal: bool) {
+        (self.0)(path, equal)
+    }
+}
+
+impl fmt::Debug for CompareHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CompareHook")
+    }
+}
+
+/// What is compared between both directories
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum CompareMode {
+    /// structure, names and content of the files
+    #[default]
+    Full,
+    /// only the structure and the names of the files, their content is never read so
+    /// the modified files and the renames are not detected
+    Structure,
+    /// only the contents of the files, whatever their names and paths are. Both are equal
+    /// if they contain the same contents the same number of times
+    Content,
+}
+
+/// What a comparison does with the IO errors of the entries of the directories. The
+/// errors of the roots always abort it
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum ErrorPolicy {
+    /// abort the comparison with the first error, returned by the `try_` functions and
+    /// raised as a panic by the others
+    FailFast,
+    /// skip the entries which can't be read and return all the errors in
+    /// `DiffReport::errors`, for the long running jobs which can't stop on every error
+    #[default]
+    Collect,
+}
+
+/// What is different between two directories, returned by `dir_diff_outcome`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DirDiffOutcome {
+    /// both directories are equal
+    Equal,
+    /// an entry is only present in one of them, or is a file in one and a directory in
+    /// the other. The content of the files is not compared
+    StructureDiffers,
+    /// both have the same entries but the content of some files is different
+    ContentDiffers,
 }
 
+#[derive(Debug, Default, Clone)]
 pub struct FileDiff {
     /// file to compare
     pub file: String,
     /// comparation file
     pub file_comp: String,
+    /// normalizations applied to the content of both files before comparing them
+    pub normalization: Normalization,
+    /// compare a file which doesn't exist as an empty file instead of failing with
+    /// `DiffError::NotFound`, like a file added or removed
+    pub missing_as_empty: bool,
+    /// abort the comparison with `DiffError::Timeout` if it takes more than this
+    pub timeout: Option<Duration>,
 }
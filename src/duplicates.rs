@@ -0,0 +1,141 @@
+//! Detection of files with the same content under different paths
+use crate::hash::content_hash;
+use crate::tree::Tree;
+use crate::vfs::{DiffFs, EntryKind};
+use std::collections::BTreeMap;
+
+/// Group of files with the same content, the paths are relative to the root of every tree
+#[derive(Debug, PartialEq, Clone)]
+pub struct Duplicate {
+    /// hash of the content
+    pub hash: u64,
+    /// size in bytes of the content
+    pub size: u64,
+    /// files with the content in the first tree
+    pub paths: Vec<String>,
+    /// files with the content in the comparation tree
+    pub paths_comp: Vec<String>,
+}
+
+type Groups = BTreeMap<(u64, u64), (Vec<String>, Vec<String>)>;
+
+async fn hash_files<F: DiffFs + ?Sized>(fs: &F, tree: &[Tree], comp: bool, groups: &mut Groups) {
+    for (path, entry) in Tree::iter(tree) {
+        if entry.kind == EntryKind::File {
+            let content = fs.read(&entry.path).await.unwrap();
+            let group = groups
+                .entry((content_hash(&content), content.len() as u64))
+                .or_default();
+
+            if comp {
+                group.1.push(path);
+            } else {
+                group.0.push(path);
+            }
+        }
+    }
+}
+
+fn into_duplicates(groups: Groups) -> Vec<Duplicate> {
+    let mut duplicates: Vec<Duplicate> = groups
+        .into_iter()
+        .filter(|(_, (paths, paths_comp))| {
+            let mut all: Vec<&String> = paths.iter().chain(paths_comp.iter()).collect();
+            all.sort();
+            all.dedup();
+            all.len() > 1
+        })
+        .map(|((hash, size), (paths, paths_comp))| Duplicate {
+            hash,
+            size,
+            paths,
+            paths_comp,
+        })
+        .collect();
+    duplicates.sort_by(|a, b| {
+        let first = |d: &Duplicate| d.paths.iter().chain(d.paths_comp.iter()).min().cloned();
+        first(a).cmp(&first(b))
+    });
+
+    duplicates
+}
+
+/// Find the files with the same content under different paths inside of one tree
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{duplicates::find_duplicates, tree::{Tree, TreeBuilder}, vfs::mem::MemFs};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let fs = MemFs::builder()
+///         .file("conf/a.conf", "port=80")
+///         .file("conf/old/a.conf", "port=80")
+///         .build();
+///     let tree = Tree::build_tree_with(&fs, "conf".to_string(), None, false).await;
+///
+///     let duplicates = find_duplicates(&fs, &tree).await;
+///     assert_eq!(duplicates[0].paths, vec!["a.conf", "old/a.conf"]);
+/// }
+/// ```
+pub async fn find_duplicates<F: DiffFs + ?Sized>(fs: &F, tree: &[Tree]) -> Vec<Duplicate> {
+    let mut groups: Groups = BTreeMap::new();
+    hash_files(fs, tree, false, &mut groups).await;
+
+    into_duplicates(groups)
+}
+
+/// Find the files with the same content under different paths in two trees, a content
+/// is reported when it is in more than one path, in any of the two trees
+pub async fn find_cross_duplicates<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    tree: &[Tree],
+    fs_comp: &C,
+    tree_comp: &[Tree],
+) -> Vec<Duplicate> {
+    let mut groups: Groups = BTreeMap::new();
+    hash_files(fs, tree, false, &mut groups).await;
+    hash_files(fs_comp, tree_comp, true, &mut groups).await;
+
+    into_duplicates(groups)
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_find_duplicates_inside_of_one_tree() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("conf/a.conf", "port=80")
+        .file("conf/b.conf", "port=8080")
+        .file("conf/old/a.conf", "port=80")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "conf".to_string(), None, false).await;
+
+    let duplicates = find_duplicates(&fs, &tree).await;
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].paths, vec!["a.conf", "old/a.conf"]);
+    assert_eq!(duplicates[0].size, 7);
+    assert!(duplicates[0].paths_comp.is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_find_duplicates_between_two_trees() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("one/a.conf", "port=80")
+        .file("one/same.conf", "same")
+        .file("two/copy/a.conf", "port=80")
+        .file("two/same.conf", "same")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "two".to_string(), None, false).await;
+
+    let duplicates = find_cross_duplicates(&fs, &tree, &fs, &tree_comp).await;
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].paths, vec!["a.conf"]);
+    assert_eq!(duplicates[0].paths_comp, vec!["copy/a.conf"]);
+}
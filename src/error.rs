@@ -0,0 +1,127 @@
+//! Errors returned by the comparisons which don't panic
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::time::Duration;
+
+/// Error of a comparison with the path which failed
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DiffError {
+    /// the path doesn't exist
+    NotFound {
+        /// path of the source which failed
+        path: String,
+    },
+    /// the path can't be read with the permissions of the process
+    PermissionDenied {
+        /// path of the source which failed
+        path: String,
+    },
+    /// the comparison took more than the timeout of the options
+    Timeout {
+        /// file or directory compared
+        path: String,
+        /// timeout of the options
+        timeout: Duration,
+    },
+    /// any other error of the source reading the path
+    Io {
+        /// path of the source which failed
+        path: String,
+        /// error returned by the source
+        source: io::Error,
+    },
+}
+
+impl DiffError {
+    /// Create the error of the path from the error of the source
+    pub fn from_io(path: &str, error: io::Error) -> Self {
+        let path = path.to_string();
+        match error.kind() {
+            io::ErrorKind::NotFound => DiffError::NotFound { path },
+            io::ErrorKind::PermissionDenied => DiffError::PermissionDenied { path },
+            _ => DiffError::Io {
+                path,
+                source: error,
+            },
+        }
+    }
+
+    /// Path of the source which failed
+    pub fn path(&self) -> &str {
+        match self {
+            DiffError::NotFound { path }
+            | DiffError::PermissionDenied { path }
+            | DiffError::Timeout { path, .. }
+            | DiffError::Io { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffError::NotFound { path } => write!(f, "{}: No such file or directory", path),
+            DiffError::PermissionDenied { path } => write!(f, "{}: Permission denied", path),
+            DiffError::Timeout { path, timeout } => {
+                write!(f, "{}: Timed out after {:?}", path, timeout)
+            }
+            DiffError::Io { path, source } => write!(f, "{}: {}", path, source),
+        }
+    }
+}
+
+// `io::Error` can't be cloned nor compared, the copy keeps its kind and its description
+impl Clone for DiffError {
+    fn clone(&self) -> Self {
+        match self {
+            DiffError::NotFound { path } => DiffError::NotFound { path: path.clone() },
+            DiffError::PermissionDenied { path } => {
+                DiffError::PermissionDenied { path: path.clone() }
+            }
+            DiffError::Timeout { path, timeout } => DiffError::Timeout {
+                path: path.clone(),
+                timeout: *timeout,
+            },
+            DiffError::Io { path, source } => DiffError::Io {
+                path: path.clone(),
+                source: io::Error::new(source.kind(), source.to_string()),
+            },
+        }
+    }
+}
+
+impl PartialEq for DiffError {
+    fn eq(&self, other: &Self) -> bool {
+        mem::discriminant(self) == mem::discriminant(other) && self.to_string() == other.to_string()
+    }
+}
+
+impl Error for DiffError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DiffError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn should_keep_the_path_which_failed() {
+    let error = DiffError::from_io(
+        "./mocks/missing.txt",
+        io::Error::new(io::ErrorKind::NotFound, "not found"),
+    );
+
+    assert_eq!(error.path(), "./mocks/missing.txt");
+    assert_eq!(
+        error.to_string(),
+        "./mocks/missing.txt: No such file or directory"
+    );
+    assert!(matches!(
+        DiffError::from_io("file", io::Error::other("broken pipe")),
+        DiffError::Io { .. }
+    ));
+}
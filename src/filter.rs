@@ -0,0 +1,191 @@
+//! Selection of the entries of the trees through `DirDiff::filter`
+use crate::diff::DirEntryInfo;
+use std::fmt;
+
+/// Decide which entries of the trees are compared, consulted with the entries which are not
+/// excluded by name, path, size or extension by `DirDiff`. The closures taking a
+/// `DirEntryInfo` are filters too
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::diff::{DirDiff, DirEntryInfo};
+/// use std::sync::Arc;
+///
+/// // only the files of the root and the first level of subdirectories
+/// let options = DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_two".to_string(),
+///     filter: Some(Arc::new(|entry: &DirEntryInfo| entry.is_dir || entry.depth <= 1)),
+///     ..Default::default()
+/// };
+/// ```
+///
+/// ```rust
+/// use spielrs_diff::{diff::{DirDiff, DirEntryInfo}, filter::EntryFilter};
+/// use std::sync::Arc;
+///
+/// // only the directories and the sources, at any depth
+/// struct Sources;
+///
+/// impl EntryFilter for Sources {
+///     fn keep(&self, entry: &DirEntryInfo) -> bool {
+///         entry.is_dir || entry.name.ends_with(".v")
+///     }
+/// }
+///
+/// let options = DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_two".to_string(),
+///     filter: Some(Arc::new(Sources)),
+///     ..Default::default()
+/// };
+/// ```
+pub trait EntryFilter: Send + Sync {
+    /// Return true if the entry is kept, the content of a directory which is not kept is
+    /// never listed
+    fn keep(&self, entry: &DirEntryInfo) -> bool;
+}
+
+impl fmt::Debug for dyn EntryFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EntryFilter")
+    }
+}
+
+impl<F: Fn(&DirEntryInfo) -> bool + Send + Sync> EntryFilter for F {
+    fn keep(&self, entry: &DirEntryInfo) -> bool {
+        self(entry)
+    }
+}
+
+/// Keep only the files with one of the extensions, with or without the dot and ignoring
+/// the case, and all the directories. The opposite of `DirDiff::exclude_extensions`
+#[derive(Debug, Clone)]
+pub struct ExtensionFilter {
+    extensions: Vec<String>,
+}
+
+impl ExtensionFilter {
+    /// Create a filter of the files with any of the extensions
+    pub fn new<S: AsRef<str>>(extensions: &[S]) -> Self {
+        ExtensionFilter {
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.as_ref().trim_start_matches('.').to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl EntryFilter for ExtensionFilter {
+    fn keep(&self, entry: &DirEntryInfo) -> bool {
+        entry.is_dir
+            || entry
+                .name
+                .rsplit_once('.')
+                .is_some_and(|(_, extension)| self.extensions.contains(&extension.to_lowercase()))
+    }
+}
+
+/// Keep only the entries whose path relative to the root matches any of the regular
+/// expressions, and all the directories since they may contain one. The opposite of
+/// `DirDiff::excluding_regex`
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct RegexFilter {
+    regex: regex::RegexSet,
+}
+
+#[cfg(feature = "regex")]
+impl RegexFilter {
+    /// Create a filter of the entries matching any of the patterns, an error is returned
+    /// if one is invalid
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, regex::Error> {
+        Ok(RegexFilter {
+            regex: regex::RegexSet::new(patterns)?,
+        })
+    }
+}
+
+#[cfg(feature = "regex")]
+impl EntryFilter for RegexFilter {
+    fn keep(&self, entry: &DirEntryInfo) -> bool {
+        entry.is_dir || self.regex.is_match(&entry.rel_path)
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_keep_the_entries_of_the_filters() {
+    use crate::diff::DirDiff;
+    use crate::report::Difference;
+    use crate::vfs::mem::MemFs;
+    use std::sync::Arc;
+
+    let fs = MemFs::builder()
+        .file("dir_one/hello.V", "fn main() {}")
+        .file("dir_one/vlang/purpose.txt", "one")
+        .file("dir_one/vlang/lib.v", "fn one() {}")
+        .file("dir_two/hello.V", "fn main() {}")
+        .file("dir_two/vlang/purpose.txt", "two")
+        .file("dir_two/vlang/lib.v", "fn two() {}")
+        .build();
+    let report = |filter: Arc<dyn EntryFilter>| {
+        crate::dir_diff_report_with(
+            &fs,
+            &fs,
+            DirDiff {
+                dir: "dir_one".to_string(),
+                dir_comp: "dir_two".to_string(),
+                filter: Some(filter),
+                ..Default::default()
+            },
+        )
+    };
+
+    assert_eq!(
+        report(Arc::new(ExtensionFilter::new(&[".v"])))
+            .await
+            .differences,
+        vec![Difference::Modified("vlang/lib.v".to_string())]
+    );
+    assert_eq!(
+        report(Arc::new(|entry: &DirEntryInfo| entry.depth == 0))
+            .await
+            .differences,
+        vec![]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_consult_the_filter_after_the_exclusions() {
+    use crate::diff::DirDiff;
+    use crate::tree::Tree;
+    use crate::vfs::mem::MemFs;
+    use std::sync::{Arc, Mutex};
+
+    let fs = MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/debug.log", "")
+        .file("dir_one/target/app", "")
+        .build();
+    let consulted = Arc::new(Mutex::new(vec![]));
+    let filter = {
+        let consulted = consulted.clone();
+        move |entry: &DirEntryInfo| {
+            consulted.lock().unwrap().push(entry.rel_path.clone());
+            true
+        }
+    };
+    let options = DirDiff {
+        excluding: Some(vec!["target".to_string()]),
+        exclude_extensions: Some(vec!["log".to_string()]),
+        filter: Some(Arc::new(filter)),
+        ..Default::default()
+    };
+    Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(*consulted.lock().unwrap(), vec!["hello.txt"]);
+}
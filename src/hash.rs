@@ -0,0 +1,203 @@
+//! Hashes of the content of the files, used to match files with the same content
+#[cfg(not(feature = "fast-hash"))]
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+#[cfg(not(feature = "fast-hash"))]
+use std::hash::Hasher;
+
+/// Hash the content of a file, it is not a cryptographic hash and is only stable
+/// inside of the same process, don't store it. With the `fast-hash` feature it is xxHash3
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::hash::content_hash;
+///
+/// assert_eq!(content_hash(b"Hello world"), content_hash(b"Hello world"));
+/// assert_ne!(content_hash(b"Hello world"), content_hash(b"Hello"));
+/// ```
+pub fn content_hash(content: &[u8]) -> u64 {
+    #[cfg(feature = "fast-hash")]
+    return xxhash_rust::xxh3::xxh3_64(content);
+
+    #[cfg(not(feature = "fast-hash"))]
+    {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(content);
+
+        hasher.finish()
+    }
+}
+
+/// Digest of the content of the files used by the comparisons based on hashes, like
+/// `CompareMode::Content` or the detection of renames. Set it in `DirDiff::hasher`
+/// to use your own digest, for example an HMAC to detect tampered files
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::hash::ContentHasher;
+///
+/// /// Digest of the length and the first bytes, enough for files with a fixed header
+/// struct HeaderHasher;
+///
+/// impl ContentHasher for HeaderHasher {
+///     fn hash(&self, content: &[u8]) -> Vec<u8> {
+///         let mut digest = (content.len() as u64).to_le_bytes().to_vec();
+///         digest.extend(content.iter().take(16));
+///         digest
+///     }
+/// }
+///
+/// assert_eq!(HeaderHasher.hash(b"Hello world"), HeaderHasher.hash(b"Hello world"));
+/// ```
+pub trait ContentHasher: Send + Sync {
+    /// Return the digest of the content, equal contents must have the same digest
+    fn hash(&self, content: &[u8]) -> Vec<u8>;
+
+    /// Name of the digest saved with a `HashCache`, which is only loaded with a hasher of
+    /// the same name. The name of the type by default
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl fmt::Debug for dyn ContentHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContentHasher")
+    }
+}
+
+/// Hasher of `content_hash`, used when `DirDiff::hasher` is not set
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultContentHasher;
+
+impl ContentHasher for DefaultContentHasher {
+    fn hash(&self, content: &[u8]) -> Vec<u8> {
+        content_hash(content).to_le_bytes().to_vec()
+    }
+
+    fn name(&self) -> &str {
+        if cfg!(feature = "fast-hash") {
+            "default-xxh3"
+        } else {
+            "default-sip"
+        }
+    }
+}
+
+/// xxHash64 digest of the content, needs the `fast-hash` feature
+#[cfg(feature = "fast-hash")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Xxh64Hasher;
+
+#[cfg(feature = "fast-hash")]
+impl ContentHasher for Xxh64Hasher {
+    fn hash(&self, content: &[u8]) -> Vec<u8> {
+        xxhash_rust::xxh64::xxh64(content, 0).to_le_bytes().to_vec()
+    }
+
+    fn name(&self) -> &str {
+        "xxh64"
+    }
+}
+
+/// 128 bits xxHash3 digest of the content, needs the `fast-hash` feature
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "fast-hash")]
+/// # {
+/// use spielrs_diff::{diff::DirDiff, hash::Xxh3Hasher};
+/// use std::sync::Arc;
+///
+/// let options = DirDiff {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_two".to_string(),
+///     hasher: Some(Arc::new(Xxh3Hasher)),
+///     ..Default::default()
+/// };
+/// # }
+/// ```
+#[cfg(feature = "fast-hash")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Xxh3Hasher;
+
+#[cfg(feature = "fast-hash")]
+impl ContentHasher for Xxh3Hasher {
+    fn hash(&self, content: &[u8]) -> Vec<u8> {
+        xxhash_rust::xxh3::xxh3_128(content).to_le_bytes().to_vec()
+    }
+
+    fn name(&self) -> &str {
+        "xxh3-128"
+    }
+}
+
+/// BLAKE3 digest of the content, the files bigger than 128 KiB are digested in parallel.
+/// Needs the `blake3` feature
+#[cfg(feature = "blake3")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Blake3Hasher;
+
+#[cfg(feature = "blake3")]
+impl ContentHasher for Blake3Hasher {
+    fn hash(&self, content: &[u8]) -> Vec<u8> {
+        if content.len() < 128 * 1024 {
+            return blake3::hash(content).as_bytes().to_vec();
+        }
+        let mut hasher = blake3::Hasher::new();
+        hasher.update_rayon(content);
+
+        hasher.finalize().as_bytes().to_vec()
+    }
+
+    fn name(&self) -> &str {
+        "blake3"
+    }
+}
+
+/// Format a digest as lowercase hexadecimal
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::hash::to_hex;
+///
+/// assert_eq!(to_hex(&[0, 15, 255]), "000fff");
+/// ```
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(feature = "fast-hash")]
+#[test]
+fn should_hash_with_xxhash() {
+    assert_eq!(Xxh64Hasher.hash(b"Hello world").len(), 8);
+    assert_eq!(Xxh3Hasher.hash(b"Hello world").len(), 16);
+    assert_eq!(
+        Xxh3Hasher.hash(b"Hello world"),
+        Xxh3Hasher.hash(b"Hello world")
+    );
+    assert_ne!(Xxh3Hasher.hash(b"Hello world"), Xxh3Hasher.hash(b"Hello"));
+    assert_eq!(
+        content_hash(b"Hello world"),
+        xxhash_rust::xxh3::xxh3_64(b"Hello world")
+    );
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn should_hash_big_contents_with_blake3() {
+    let content = vec![7u8; 1024 * 1024];
+
+    assert_eq!(
+        Blake3Hasher.hash(&content),
+        blake3::hash(&content).as_bytes().to_vec()
+    );
+    assert_eq!(
+        to_hex(&Blake3Hasher.hash(b"")),
+        "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+    );
+}
@@ -23,6 +23,7 @@
 //!        dir_comp: "./mocks/dir_five".to_string(),
 //!        excluding: Some(vec!["purpose".to_string()]),
 //!        recursive_excluding: true,
+//!        ..Default::default()
 //!    })
 //!    .await;
 //!
@@ -39,18 +40,108 @@
 //!     let diff = file_diff(FileDiff {
 //!         file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
 //!         file_comp: "./mocks/dir_five/vlang/purpose/purpose.txt".to_string(),
+//!         ..Default::default()
 //!     })
 //!     .await;
 //!
 //!     assert_eq!(diff, true);
 //! }
 //! ```
+// the original tests compare the results with `assert_eq!(diff, true)`
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
+
+#[macro_use]
+mod trace;
+
+pub mod blocking;
+pub mod cache;
+pub mod caps;
+pub mod chunk;
+pub mod compare;
+pub mod delta;
 pub mod diff;
+pub mod duplicates;
+pub mod error;
+pub mod filter;
+pub mod hash;
+pub mod lines;
+pub mod mime;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod multi;
+pub mod normalize;
+pub mod pattern;
+pub mod report;
+pub mod rt;
+pub mod session;
+pub mod similarity;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod sync;
 pub mod tree;
+pub mod vfs;
 
-use diff::{DirDiff, FileDiff};
-use tokio::fs;
+use diff::{CompareMode, DirDiff, DirDiffOutcome, ErrorPolicy, FileDiff};
+use duplicates::Duplicate;
+use error::DiffError;
+use futures::stream::{self, StreamExt};
+use multi::MultiDifference;
+use report::{DiffMetrics, DiffReport, DiffStats, Difference, FileDiffResult};
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use sync::SyncPlan;
 use tree::{Tree, TreeBuilder};
+use vfs::EntryKind;
+use vfs::{DiffFs, LocalFs};
+
+/// Build the trees of both directories with the exclusions of the options
+pub(crate) async fn build_trees<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: &DirDiff,
+) -> (Vec<Tree>, Vec<Tree>) {
+    let tree_one =
+        Tree::build_tree_with_options(fs, dir_diff_options.dir.clone(), dir_diff_options).await;
+    let tree_two =
+        Tree::build_tree_with_options(fs_comp, dir_diff_options.dir_comp.clone(), dir_diff_options)
+            .await;
+
+    (tree_one, tree_two)
+}
+
+/// Build the trees of both directories and return the errors of the subdirectories which
+/// couldn't be listed, or the first one with `ErrorPolicy::FailFast`, and true if any of
+/// them was truncated by `DirDiff::max_entries`
+pub(crate) async fn try_build_trees<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: &DirDiff,
+) -> Result<(Vec<Tree>, Vec<Tree>, Vec<DiffError>, bool), DiffError> {
+    let (tree_one, mut errors, truncated) =
+        Tree::try_build_tree_with_options(fs, dir_diff_options.dir.clone(), dir_diff_options)
+            .await?;
+    let (tree_two, errors_comp, truncated_comp) = Tree::try_build_tree_with_options(
+        fs_comp,
+        dir_diff_options.dir_comp.clone(),
+        dir_diff_options,
+    )
+    .await?;
+    errors.extend(errors_comp);
+
+    Ok((tree_one, tree_two, errors, truncated || truncated_comp))
+}
+
+/// Differences of the comparison, or its first error with `ErrorPolicy::FailFast`
+fn differences_of(
+    comparison: report::Comparison,
+    dir_diff_options: &DirDiff,
+) -> Result<Vec<Difference>, DiffError> {
+    match comparison.errors.into_iter().next() {
+        Some(error) if dir_diff_options.on_error == ErrorPolicy::FailFast => Err(error),
+        _ => Ok(comparison.differences),
+    }
+}
 
 /// Compare two directories and return true if both are different
 /// You can exclude directories or files in the comparation only from the root path
@@ -67,6 +158,7 @@ use tree::{Tree, TreeBuilder};
 ///        dir_comp: "./mocks/dir_five".to_string(),
 ///        excluding: Some(vec!["purpose".to_string()]),
 ///        recursive_excluding: true,
+///        ..Default::default()
 ///    })
 ///    .await;
 ///
@@ -75,28 +167,538 @@ use tree::{Tree, TreeBuilder};
 /// ```
 ///
 pub async fn dir_diff(dir_diff_options: DirDiff) -> bool {
-    let tree_one: Vec<Tree> = Tree::build_tree(
-        dir_diff_options.dir,
-        dir_diff_options.excluding.clone(),
-        dir_diff_options.recursive_excluding,
-    )
-    .await;
-    let tree_two: Vec<Tree> = Tree::build_tree(
-        dir_diff_options.dir_comp,
-        dir_diff_options.excluding,
-        dir_diff_options.recursive_excluding,
-    )
-    .await;
+    dir_diff_with(&LocalFs, &LocalFs, dir_diff_options).await
+}
+
+/// Compare two directories read from any `DiffFs` and return true if both are different,
+/// `dir` is read from `fs` and `dir_comp` from `fs_comp`
+///
+/// # Example
+/// ```rust
+/// use spielrs_diff::{dir_diff_with, diff::DirDiff, vfs::LocalFs};
+///
+/// #[tokio::test]
+/// async fn should_return_true_if_both_dir_tree_are_different() {
+///    let diff = dir_diff_with(&LocalFs, &LocalFs, DirDiff {
+///        dir: "./mocks/dir_one".to_string(),
+///        dir_comp: "./mocks/dir_three".to_string(),
+///        excluding: None,
+///        recursive_excluding: false,
+///        ..Default::default()
+///    })
+///    .await;
+///
+///    assert_eq!(diff, true);
+/// }
+/// ```
+///
+pub async fn dir_diff_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: DirDiff,
+) -> bool {
+    try_dir_diff_with(fs, fs_comp, dir_diff_options)
+        .await
+        .unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Compare two directories and return true if both are different, or `DiffError::Timeout`
+/// if it takes more than the `timeout` of the options. The roots which can't be listed,
+/// and with `ErrorPolicy::FailFast` any entry which can't be read, return its error
+///
+/// # Example
+/// ```rust
+/// use spielrs_diff::{try_dir_diff, diff::DirDiff};
+/// use std::time::Duration;
+///
+/// #[tokio::test]
+/// async fn should_return_true_if_both_dir_tree_are_different() {
+///    let diff = try_dir_diff(DirDiff {
+///        dir: "./mocks/dir_one".to_string(),
+///        dir_comp: "./mocks/dir_three".to_string(),
+///        timeout: Some(Duration::from_secs(5)),
+///        ..Default::default()
+///    })
+///    .await;
+///
+///    assert_eq!(diff.unwrap(), true);
+/// }
+/// ```
+pub async fn try_dir_diff(dir_diff_options: DirDiff) -> Result<bool, DiffError> {
+    try_dir_diff_with(&LocalFs, &LocalFs, dir_diff_options).await
+}
+
+/// Compare two directories read from any `DiffFs`, see `try_dir_diff`
+pub async fn try_dir_diff_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: DirDiff,
+) -> Result<bool, DiffError> {
+    with_timeout(dir_diff_options.timeout, &dir_diff_options.dir, async {
+        Ok(dirs_outcome(fs, fs_comp, &dir_diff_options).await? != DirDiffOutcome::Equal)
+    })
+    .await
+}
+
+/// Run the comparison of the path, aborting it with `DiffError::Timeout` if it takes
+/// more than the timeout
+async fn with_timeout<T, C: Future<Output = Result<T, DiffError>>>(
+    timeout: Option<Duration>,
+    path: &str,
+    comparison: C,
+) -> Result<T, DiffError> {
+    match timeout {
+        Some(timeout) => rt::timeout(timeout, comparison).await.unwrap_or_else(|| {
+            Err(DiffError::Timeout {
+                path: path.to_string(),
+                timeout,
+            })
+        }),
+        None => comparison.await,
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(dir = %dir_diff_options.dir, dir_comp = %dir_diff_options.dir_comp))
+)]
+async fn dirs_outcome<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: &DirDiff,
+) -> Result<DirDiffOutcome, DiffError> {
+    let (tree_one, tree_two, _, _) = try_build_trees(fs, fs_comp, dir_diff_options).await?;
+    let content_differs = |differs: bool| {
+        if differs {
+            DirDiffOutcome::ContentDiffers
+        } else {
+            DirDiffOutcome::Equal
+        }
+    };
+    // only the comparison entry by entry knows if the unreadable directories are different
+    let unreadable =
+        |tree: &[Tree]| Tree::iter(tree).any(|(_, entry)| entry.kind == EntryKind::Unreadable);
+    if dir_diff_options.mode == CompareMode::Content
+        || unreadable(&tree_one)
+        || unreadable(&tree_two)
+    {
+        let comparison =
+            report::compare_trees(fs, &tree_one, fs_comp, &tree_two, dir_diff_options).await;
+        return Ok(content_differs(
+            !differences_of(comparison, dir_diff_options)?.is_empty(),
+        ));
+    }
     if Tree::tree_diff(tree_one.clone(), tree_two.clone()) {
-        return true;
+        return Ok(DirDiffOutcome::StructureDiffers);
+    }
+    if dir_diff_options.mode == CompareMode::Structure {
+        return Ok(DirDiffOutcome::Equal);
+    }
+
+    // `mismatched_files_with` only compares the contents and the targets of the links and
+    // skips the files which can't be read
+    if dir_diff_options.comparator.is_some()
+        || dir_diff_options.hash_cache.is_some()
+        || dir_diff_options.normalization.is_enabled()
+        || dir_diff_options.unreadable_as_modified
+        || dir_diff_options.on_error == ErrorPolicy::FailFast
+        || dir_diff_options.compare_capabilities
+        || dir_diff_options.compare_streams
+        || dir_diff_options.compare_mtime
+    {
+        let comparison =
+            report::compare_trees(fs, &tree_one, fs_comp, &tree_two, dir_diff_options).await;
+        Ok(content_differs(
+            !differences_of(comparison, dir_diff_options)?.is_empty(),
+        ))
+    } else {
+        Ok(content_differs(
+            !Tree::mismatched_files_with(fs, &tree_one, fs_comp, &tree_two)
+                .await
+                .is_empty(),
+        ))
+    }
+}
+
+/// Compare two directories and return if both are equal, have a different structure or
+/// only the content of their files is different. In `CompareMode::Content` the structure
+/// is not compared, it never returns `StructureDiffers`
+///
+/// # Example
+/// ```rust
+/// use spielrs_diff::{dir_diff_outcome, diff::{DirDiff, DirDiffOutcome}};
+///
+/// #[tokio::test]
+/// async fn should_return_that_only_the_content_differs() {
+///    let outcome = dir_diff_outcome(DirDiff {
+///        dir: "./mocks/dir_one".to_string(),
+///        dir_comp: "./mocks/dir_five".to_string(),
+///        ..Default::default()
+///    })
+///    .await;
+///
+///    assert_eq!(outcome, DirDiffOutcome::ContentDiffers);
+/// }
+/// ```
+pub async fn dir_diff_outcome(dir_diff_options: DirDiff) -> DirDiffOutcome {
+    dir_diff_outcome_with(&LocalFs, &LocalFs, dir_diff_options).await
+}
+
+/// Compare two directories read from any `DiffFs`, see `dir_diff_outcome`
+pub async fn dir_diff_outcome_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: DirDiff,
+) -> DirDiffOutcome {
+    with_timeout(dir_diff_options.timeout, &dir_diff_options.dir, async {
+        dirs_outcome(fs, fs_comp, &dir_diff_options).await
+    })
+    .await
+    .unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Compare two directories entry by entry and return the differences found,
+/// with the same exclusion options than `dir_diff`
+///
+/// # Example
+/// ```rust
+/// use spielrs_diff::{dir_diff_report, diff::DirDiff, report::Difference};
+///
+/// #[tokio::test]
+/// async fn should_return_the_modified_files() {
+///    let report = dir_diff_report(DirDiff {
+///        dir: "./mocks/dir_one".to_string(),
+///        dir_comp: "./mocks/dir_four".to_string(),
+///        excluding: None,
+///        recursive_excluding: false,
+///        ..Default::default()
+///    })
+///    .await;
+///
+///    assert_eq!(
+///        report.differences,
+///        vec![Difference::Modified("vlang/purpose/purpose.txt".to_string())]
+///    );
+/// }
+/// ```
+///
+pub async fn dir_diff_report(dir_diff_options: DirDiff) -> DiffReport {
+    dir_diff_report_with(&LocalFs, &LocalFs, dir_diff_options).await
+}
+
+/// Compare two directories read from any `DiffFs` entry by entry and return the differences found
+pub async fn dir_diff_report_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: DirDiff,
+) -> DiffReport {
+    with_timeout(dir_diff_options.timeout, &dir_diff_options.dir, async {
+        let started = Instant::now();
+        let (tree_one, tree_two, errors, truncated) =
+            try_build_trees(fs, fs_comp, &dir_diff_options).await?;
+        let build_duration = started.elapsed();
+        let mut report =
+            report_trees(fs, &tree_one, fs_comp, &tree_two, errors, &dir_diff_options).await?;
+        report.truncated = truncated;
+        report.metrics.build_duration = build_duration;
+
+        Ok(report)
+    })
+    .await
+    .unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Compare two trees already built and return the report of their differences with the
+/// `errors` of their build, or the first error with `ErrorPolicy::FailFast`. The
+/// `build_duration` of the metrics and `truncated` are left to the caller
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(dir = %dir_diff_options.dir, dir_comp = %dir_diff_options.dir_comp))
+)]
+pub(crate) async fn report_trees<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    tree_one: &[Tree],
+    fs_comp: &C,
+    tree_two: &[Tree],
+    mut errors: Vec<DiffError>,
+    dir_diff_options: &DirDiff,
+) -> Result<DiffReport, DiffError> {
+    let started = Instant::now();
+    let report::Comparison {
+        differences,
+        bytes_compared,
+        changes,
+        digests,
+        capabilities,
+        streams,
+        text_diffs,
+        files_read,
+        unreadable,
+        errors: comparison_errors,
+    } = report::compare_trees(fs, tree_one, fs_comp, tree_two, dir_diff_options).await;
+    if let Some(error) = comparison_errors
+        .first()
+        .filter(|_| dir_diff_options.on_error == ErrorPolicy::FailFast)
+    {
+        return Err(error.clone());
+    }
+    errors.extend(comparison_errors);
+    let metrics = DiffMetrics {
+        entries_scanned: Tree::iter(tree_one).count() + Tree::iter(tree_two).count(),
+        files_read,
+        bytes_read: bytes_compared,
+        compare_duration: started.elapsed(),
+        ..Default::default()
+    };
+    let count = |kind: fn(&Difference) -> bool| differences.iter().filter(|d| kind(d)).count();
+    let stats = DiffStats {
+        tree: Tree::stats(tree_one),
+        tree_comp: Tree::stats(tree_two),
+        bytes_compared,
+        added: count(|d| matches!(d, Difference::Added(_))),
+        removed: count(|d| matches!(d, Difference::Removed(_))),
+        modified: count(|d| matches!(d, Difference::Modified(_))),
+        renamed: count(|d| matches!(d, Difference::Renamed { .. })),
+        relinked: count(|d| matches!(d, Difference::Relinked(_))),
+    };
+    let content_types = differences
+        .iter()
+        .filter_map(|difference| {
+            let path = difference.path();
+            let entry = Tree::find(tree_two, path).or_else(|| Tree::find(tree_one, path))?;
+
+            Some((path.to_string(), entry.content_type.clone()?))
+        })
+        .collect();
+    let mut special_files: Vec<String> = Tree::iter(tree_one)
+        .chain(Tree::iter(tree_two))
+        .filter(|(_, entry)| entry.kind.is_special())
+        .map(|(path, _)| path)
+        .collect();
+    special_files.sort();
+    special_files.dedup();
+    let mut unreadable: Vec<String> = Tree::iter(tree_one)
+        .chain(Tree::iter(tree_two))
+        .filter(|(_, entry)| entry.kind == EntryKind::Unreadable)
+        .map(|(path, _)| path)
+        .chain(unreadable)
+        .collect();
+    unreadable.sort();
+    unreadable.dedup();
+
+    Ok(DiffReport {
+        dir: dir_diff_options.dir.clone(),
+        dir_comp: dir_diff_options.dir_comp.clone(),
+        differences,
+        stats,
+        changes,
+        digests,
+        capabilities,
+        streams,
+        text_diffs,
+        content_types,
+        special_files,
+        unreadable,
+        errors,
+        truncated: false,
+        metrics,
+    })
+}
+
+/// Compare two directories and return the copy and delete operations which would make
+/// `dir_comp` equal to `dir`, without performing them
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{sync_plan, diff::DirDiff};
+///
+/// #[tokio::test]
+/// async fn should_plan_the_sync_of_the_dirs() {
+///    let plan = sync_plan(DirDiff {
+///        dir: "./mocks/dir_one".to_string(),
+///        dir_comp: "./mocks/dir_two".to_string(),
+///        ..Default::default()
+///    })
+///    .await;
+///
+///    println!("{}", plan);
+/// }
+/// ```
+pub async fn sync_plan(dir_diff_options: DirDiff) -> SyncPlan {
+    sync_plan_with(&LocalFs, &LocalFs, dir_diff_options).await
+}
+
+/// Plan the sync of two directories read from any `DiffFs`
+pub async fn sync_plan_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: DirDiff,
+) -> SyncPlan {
+    sync::plan(&dir_diff_report_with(fs, fs_comp, dir_diff_options).await)
+}
+
+/// Find the files with the same content under different paths in the directories,
+/// both between the two directories and inside of each one
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{dir_duplicates, diff::DirDiff};
+///
+/// #[tokio::test]
+/// async fn should_return_the_duplicated_files() {
+///    let duplicates = dir_duplicates(DirDiff {
+///        dir: "./mocks/dir_one".to_string(),
+///        dir_comp: "./mocks/dir_two".to_string(),
+///        excluding: None,
+///        recursive_excluding: false,
+///        ..Default::default()
+///    })
+///    .await;
+///
+///    assert!(duplicates.iter().all(|d| d.paths.len() + d.paths_comp.len() > 1));
+/// }
+/// ```
+pub async fn dir_duplicates(dir_diff_options: DirDiff) -> Vec<Duplicate> {
+    dir_duplicates_with(&LocalFs, &LocalFs, dir_diff_options).await
+}
+
+/// Find the duplicated files of two directories read from any `DiffFs`
+pub async fn dir_duplicates_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: DirDiff,
+) -> Vec<Duplicate> {
+    let (tree_one, tree_two) = build_trees(fs, fs_comp, &dir_diff_options).await;
+
+    duplicates::find_cross_duplicates(fs, &tree_one, fs_comp, &tree_two).await
+}
+
+/// Compare more than two directories at once and return the entries which are missing in
+/// some of them or whose content diverges, with the directories which agree grouped.
+/// The `dir` and `dir_comp` of the options are ignored, the rest of options are applied
+/// to every directory
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{multi_dir_diff, diff::DirDiff};
+///
+/// #[tokio::test]
+/// async fn should_return_the_divergent_entries() {
+///    let differences = multi_dir_diff(
+///        vec![
+///            "./mocks/dir_one".to_string(),
+///            "./mocks/dir_two".to_string(),
+///            "./mocks/dir_five".to_string(),
+///        ],
+///        DirDiff::default(),
+///    )
+///    .await;
+///
+///    assert_eq!(differences[0].path, "vlang/purpose/purpose.txt");
+///    assert_eq!(differences[0].groups, vec![vec![0, 1], vec![2]]);
+/// }
+/// ```
+pub async fn multi_dir_diff(dirs: Vec<String>, dir_diff_options: DirDiff) -> Vec<MultiDifference> {
+    multi_dir_diff_with(&LocalFs, dirs, dir_diff_options).await
+}
+
+/// Compare more than two directories read from any `DiffFs`
+pub async fn multi_dir_diff_with<F: DiffFs + ?Sized>(
+    fs: &F,
+    dirs: Vec<String>,
+    dir_diff_options: DirDiff,
+) -> Vec<MultiDifference> {
+    let mut trees: Vec<Vec<Tree>> = vec![];
+    for dir in dirs {
+        trees.push(Tree::build_tree_with_options(fs, dir, &dir_diff_options).await);
     }
 
-    let content_one: Vec<String> = Tree::get_content_files(tree_one).await;
-    let content_two: Vec<String> = Tree::get_content_files(tree_two).await;
-    !Tree::compare_dir_content(content_one, content_two)
+    multi::compare_many(fs, &trees, &dir_diff_options).await
+}
+
+/// Return how similar are both directories, from 0.0 (nothing in common) to 1.0 (equal).
+/// It is the fraction of bytes of the files present in both directories with the same
+/// path and content, weighted by count if all the files are empty
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{dir_similarity, diff::DirDiff};
+///
+/// #[tokio::test]
+/// async fn should_return_mostly_similar() {
+///    let similarity = dir_similarity(DirDiff {
+///        dir: "./mocks/dir_one".to_string(),
+///        dir_comp: "./mocks/dir_four".to_string(),
+///        excluding: None,
+///        recursive_excluding: false,
+///        ..Default::default()
+///    })
+///    .await;
+///
+///    assert!(similarity > 0.5 && similarity < 1.0);
+/// }
+/// ```
+pub async fn dir_similarity(dir_diff_options: DirDiff) -> f64 {
+    dir_similarity_with(&LocalFs, &LocalFs, dir_diff_options).await
+}
+
+/// Return how similar are two directories read from any `DiffFs`
+pub async fn dir_similarity_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    dir_diff_options: DirDiff,
+) -> f64 {
+    let (tree_one, tree_two) = build_trees(fs, fs_comp, &dir_diff_options).await;
+
+    let differences = report::compare_trees(fs, &tree_one, fs_comp, &tree_two, &dir_diff_options)
+        .await
+        .differences;
+    let files = |tree: &[Tree]| -> Vec<(String, u64)> {
+        Tree::iter(tree)
+            .filter(|(_, entry)| entry.kind == EntryKind::File)
+            .map(|(path, entry)| (path, entry.size))
+            .collect()
+    };
+    let files_one = files(&tree_one);
+    let files_two = files(&tree_two);
+    let paths_two: HashSet<&str> = files_two.iter().map(|(path, _)| path.as_str()).collect();
+    let modified: HashSet<&str> = differences
+        .iter()
+        .filter_map(|difference| match difference {
+            Difference::Modified(modified) => Some(modified.as_str()),
+            _ => None,
+        })
+        .collect();
+    let is_modified = |path: &str| {
+        modified.contains(path)
+            || path
+                .match_indices('/')
+                .any(|(index, _)| modified.contains(&path[..index]))
+    };
+
+    // files in both sides which are not reported as modified or under a modified entry
+    let matching: Vec<&(String, u64)> = files_one
+        .iter()
+        .filter(|(path, _)| paths_two.contains(path.as_str()) && !is_modified(path))
+        .collect();
+
+    let total = files_one.len() + files_two.len();
+    let total_bytes: u64 = files_one.iter().chain(files_two.iter()).map(|f| f.1).sum();
+    if total == 0 {
+        1.0
+    } else if total_bytes == 0 {
+        (matching.len() * 2) as f64 / total as f64
+    } else {
+        matching.iter().map(|f| f.1 * 2).sum::<u64>() as f64 / total_bytes as f64
+    }
 }
 
-/// Compare two files and return true if both are different
+/// Compare two files and return true if both are different, it panics if any of them
+/// can't be read, see `try_file_diff`
 ///
 /// #Example
 /// ```rust
@@ -107,6 +709,7 @@ pub async fn dir_diff(dir_diff_options: DirDiff) -> bool {
 ///     let diff = file_diff(FileDiff {
 ///         file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
 ///         file_comp: "./mocks/dir_five/vlang/purpose/purpose.txt".to_string(),
+///         ..Default::default()
 ///     })
 ///     .await;
 ///
@@ -114,92 +717,1627 @@ pub async fn dir_diff(dir_diff_options: DirDiff) -> bool {
 /// }
 /// ```
 pub async fn file_diff(file_diff_options: FileDiff) -> bool {
-    let file_one = fs::read_to_string(file_diff_options.file).await.unwrap();
-    let file_two = fs::read_to_string(file_diff_options.file_comp)
+    file_diff_with(&LocalFs, &LocalFs, file_diff_options).await
+}
+
+/// Compare two files read from any `DiffFs` and return true if both are different,
+/// `file` is read from `fs` and `file_comp` from `fs_comp`. It panics if any of them
+/// can't be read, see `try_file_diff_with`
+///
+/// #Example
+/// ```rust
+/// use spielrs_diff::{file_diff_with, diff::FileDiff, vfs::LocalFs};
+///
+/// #[tokio::test]
+/// async fn should_return_true_if_both_files_are_not_equal() {
+///     let diff = file_diff_with(&LocalFs, &LocalFs, FileDiff {
+///         file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
+///         file_comp: "./mocks/dir_five/vlang/purpose/purpose.txt".to_string(),
+///         ..Default::default()
+///     })
+///     .await;
+///
+///     assert_eq!(diff, true);
+/// }
+/// ```
+pub async fn file_diff_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    file_diff_options: FileDiff,
+) -> bool {
+    try_file_diff_with(fs, fs_comp, file_diff_options)
         .await
-        .unwrap();
+        .unwrap_or_else(|error| panic!("{}", error))
+}
 
-    file_one != file_two
+/// Compare two files and return true if both are different, or the error of the
+/// first file which can't be read
+///
+/// #Example
+/// ```rust
+/// use spielrs_diff::{try_file_diff, diff::FileDiff, error::DiffError};
+///
+/// #[tokio::test]
+/// async fn should_return_the_missing_file() {
+///     let diff = try_file_diff(FileDiff {
+///         file: "./mocks/dir_one/hello.txt".to_string(),
+///         file_comp: "./mocks/dir_one/missing.txt".to_string(),
+///         ..Default::default()
+///     })
+///     .await;
+///
+///     assert!(matches!(diff, Err(DiffError::NotFound { .. })));
+/// }
+/// ```
+pub async fn try_file_diff(file_diff_options: FileDiff) -> Result<bool, DiffError> {
+    try_file_diff_with(&LocalFs, &LocalFs, file_diff_options).await
 }
 
-#[tokio::test]
-async fn should_return_true_if_both_dir_tree_are_different() {
-    let diff = dir_diff(DirDiff {
-        dir: "./mocks/dir_one".to_string(),
-        dir_comp: "./mocks/dir_three".to_string(),
-        excluding: None,
-        recursive_excluding: false,
-    })
-    .await;
-    assert_eq!(diff, true);
+/// Compare two files read from any `DiffFs` and return true if both are different, or
+/// the error of the first file which can't be read
+pub async fn try_file_diff_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    file_diff_options: FileDiff,
+) -> Result<bool, DiffError> {
+    file_equal_streaming_with(fs, fs_comp, file_diff_options, FILE_BUFFER_SIZE)
+        .await
+        .map(|equal| !equal)
 }
 
-#[tokio::test]
-async fn should_return_false_if_both_dir_tree_are_equal() {
-    let diff = dir_diff(DirDiff {
-        dir: "./mocks/dir_one".to_string(),
-        dir_comp: "./mocks/dir_two".to_string(),
-        excluding: None,
-        recursive_excluding: false,
-    })
-    .await;
-    assert_eq!(diff, false);
+/// Return true if both files are equal reading them by blocks of `buffer_size` bytes, it
+/// stops at the first block which is different so the memory used doesn't depend on the
+/// size of the files
+///
+/// #Example
+/// ```rust
+/// use spielrs_diff::{file_equal_streaming, diff::FileDiff};
+///
+/// #[tokio::test]
+/// async fn should_compare_the_files_by_blocks() {
+///     let equal = file_equal_streaming(
+///         FileDiff {
+///             file: "./mocks/dir_one/hello.txt".to_string(),
+///             file_comp: "./mocks/dir_two/hello.txt".to_string(),
+///             ..Default::default()
+///         },
+///         4096,
+///     )
+///     .await;
+///
+///     assert_eq!(equal.unwrap(), true);
+/// }
+/// ```
+pub async fn file_equal_streaming(
+    file_diff_options: FileDiff,
+    buffer_size: usize,
+) -> Result<bool, DiffError> {
+    file_equal_streaming_with(&LocalFs, &LocalFs, file_diff_options, buffer_size).await
 }
 
-#[tokio::test]
-async fn should_return_true_if_both_dir_tree_have_different_content() {
-    let diff = dir_diff(DirDiff {
-        dir: "./mocks/dir_one".to_string(),
-        dir_comp: "./mocks/dir_four".to_string(),
-        excluding: None,
-        recursive_excluding: false,
-    })
-    .await;
-    assert_eq!(diff, true);
+/// Return true if both files read from any `DiffFs` are equal, see `file_equal_streaming`.
+/// The memory is only bounded if the sources implement `DiffFs::open` without reading
+/// the whole file, like `LocalFs`
+pub async fn file_equal_streaming_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    file_diff_options: FileDiff,
+    buffer_size: usize,
+) -> Result<bool, DiffError> {
+    with_timeout(
+        file_diff_options.timeout,
+        &file_diff_options.file,
+        equal_streams(fs, fs_comp, &file_diff_options, buffer_size),
+    )
+    .await
 }
 
-#[tokio::test]
-async fn should_return_false_if_both_dir_have_different_subdir_excluded_recursively() {
-    let diff = dir_diff(DirDiff {
-        dir: "./mocks/dir_one".to_string(),
-        dir_comp: "./mocks/dir_five".to_string(),
-        excluding: Some(vec!["purpose".to_string()]),
-        recursive_excluding: true,
-    })
-    .await;
-    assert_eq!(diff, false);
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(file = %file_diff_options.file, file_comp = %file_diff_options.file_comp))
+)]
+async fn equal_streams<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    file_diff_options: &FileDiff,
+    buffer_size: usize,
+) -> Result<bool, DiffError> {
+    let file = &file_diff_options.file;
+    let file_comp = &file_diff_options.file_comp;
+    if file_diff_options.normalization.is_enabled() || file_diff_options.missing_as_empty {
+        // the normalizations need the whole content
+        let content = read_file(fs, file, file_diff_options.missing_as_empty).await?;
+        let content_comp =
+            read_file(fs_comp, file_comp, file_diff_options.missing_as_empty).await?;
+
+        return Ok(file_diff_options
+            .normalization
+            .equal(file, &content, &content_comp));
+    }
+    #[cfg(feature = "mmap")]
+    if let Some((path, path_comp)) = mmap::local_paths(fs, file, fs_comp, file_comp) {
+        return mmap::equal(&path, &path_comp).await;
+    }
+    let mut reader = fs
+        .open(file)
+        .await
+        .map_err(|error| DiffError::from_io(file, error))?;
+    let mut reader_comp = fs_comp
+        .open(file_comp)
+        .await
+        .map_err(|error| DiffError::from_io(file_comp, error))?;
+
+    readers_equal(file, &mut reader, file_comp, &mut reader_comp, buffer_size).await
 }
 
-#[tokio::test]
-async fn should_return_true_if_both_dir_have_different_subdir_excluded_not_recursively() {
+/// Return true if both readers return the same bytes, comparing them by blocks of
+/// `buffer_size` bytes. The readers can be any `AsyncRead` of the runtime, not only the
+/// ones of a `DiffFs`, and the paths only name them in the errors
+///
+/// #Example
+/// ```rust
+/// use spielrs_diff::readers_equal;
+/// use std::io::Cursor;
+///
+/// #[tokio::test]
+/// async fn should_compare_the_readers() {
+///     let mut reader = Cursor::new(b"Hello world".to_vec());
+///     let mut reader_comp = Cursor::new(b"Hello world".to_vec());
+///     let equal = readers_equal("one", &mut reader, "two", &mut reader_comp, 4).await;
+///
+///     assert_eq!(equal.unwrap(), true);
+/// }
+/// ```
+pub async fn readers_equal<R: rt::AsyncRead + Unpin + ?Sized, S: rt::AsyncRead + Unpin + ?Sized>(
+    file: &str,
+    reader: &mut R,
+    file_comp: &str,
+    reader_comp: &mut S,
+    buffer_size: usize,
+) -> Result<bool, DiffError> {
+    let buffer_size = buffer_size.max(1);
+    let mut buffer = vec![0u8; buffer_size];
+    let mut buffer_comp = vec![0u8; buffer_size];
+    loop {
+        let len = fill(reader, &mut buffer)
+            .await
+            .map_err(|error| DiffError::from_io(file, error))?;
+        let len_comp = fill(reader_comp, &mut buffer_comp)
+            .await
+            .map_err(|error| DiffError::from_io(file_comp, error))?;
+        if buffer[..len] != buffer_comp[..len_comp] {
+            return Ok(false);
+        }
+        if len < buffer_size {
+            return Ok(true);
+        }
+    }
+}
+
+/// Compare two files line by line and return the lines added and removed, the binary
+/// files are only compared by their bytes
+///
+/// #Example
+/// ```rust
+/// use spielrs_diff::{file_diff_report, diff::FileDiff};
+///
+/// #[tokio::test]
+/// async fn should_return_the_changed_lines() {
+///     let result = file_diff_report(FileDiff {
+///         file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
+///         file_comp: "./mocks/dir_five/vlang/purpose/purpose.txt".to_string(),
+///         ..Default::default()
+///     })
+///     .await
+///     .unwrap();
+///
+///     assert!(result.different && !result.binary);
+///     println!("+{} -{}", result.additions, result.deletions);
+/// }
+/// ```
+pub async fn file_diff_report(file_diff_options: FileDiff) -> Result<FileDiffResult, DiffError> {
+    file_diff_report_with(&LocalFs, &LocalFs, file_diff_options).await
+}
+
+/// Compare two files read from any `DiffFs` line by line, see `file_diff_report`
+pub async fn file_diff_report_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    file_diff_options: FileDiff,
+) -> Result<FileDiffResult, DiffError> {
+    with_timeout(
+        file_diff_options.timeout,
+        &file_diff_options.file,
+        report_files(fs, fs_comp, &file_diff_options),
+    )
+    .await
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(file = %file_diff_options.file, file_comp = %file_diff_options.file_comp))
+)]
+async fn report_files<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    file_diff_options: &FileDiff,
+) -> Result<FileDiffResult, DiffError> {
+    let file = &file_diff_options.file;
+    let file_comp = &file_diff_options.file_comp;
+    let content = read_file(fs, file, file_diff_options.missing_as_empty).await?;
+    let content_comp = read_file(fs_comp, file_comp, file_diff_options.missing_as_empty).await?;
+
+    let different = !file_diff_options
+        .normalization
+        .equal(file, &content, &content_comp);
+    if !different || lines::is_binary(&content) || lines::is_binary(&content_comp) {
+        return Ok(FileDiffResult {
+            different,
+            binary: lines::is_binary(&content) || lines::is_binary(&content_comp),
+            ..Default::default()
+        });
+    }
+
+    let text = String::from_utf8_lossy(&content);
+    let text_comp = String::from_utf8_lossy(&content_comp);
+    let changes = lines::diff_lines(
+        &text.lines().collect::<Vec<_>>(),
+        &text_comp.lines().collect::<Vec<_>>(),
+    );
+
+    Ok(FileDiffResult {
+        different,
+        binary: false,
+        additions: changes.iter().map(|change| change.len_comp).sum(),
+        deletions: changes.iter().map(|change| change.len).sum(),
+        changes,
+    })
+}
+
+/// Content of the file, empty if it doesn't exist with `FileDiff::missing_as_empty`
+async fn read_file<F: DiffFs + ?Sized>(
+    fs: &F,
+    file: &str,
+    missing_as_empty: bool,
+) -> Result<Vec<u8>, DiffError> {
+    match fs.read(file).await {
+        Err(error) if missing_as_empty && error.kind() == std::io::ErrorKind::NotFound => {
+            Ok(vec![])
+        }
+        result => result.map_err(|error| DiffError::from_io(file, error)),
+    }
+}
+
+/// Bytes of every file read at once by `file_diff`
+const FILE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Read until the buffer is full or the end of the file, return the bytes read
+async fn fill<R: rt::AsyncRead + Unpin + ?Sized>(
+    reader: &mut R,
+    buffer: &mut [u8],
+) -> std::io::Result<usize> {
+    use rt::AsyncReadExt;
+
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]).await? {
+            0 => break,
+            read => len += read,
+        }
+    }
+
+    Ok(len)
+}
+
+/// Compare many pairs of files, at most `concurrency` pairs at the same time, and return
+/// for every pair true if its files are different or the error of the file which can't be
+/// read, in the same order than the pairs
+///
+/// #Example
+/// ```rust
+/// use spielrs_diff::{files_diff, diff::FileDiff};
+///
+/// #[tokio::test]
+/// async fn should_compare_all_the_pairs() {
+///     let pair = |file: &str, file_comp: &str| FileDiff {
+///         file: file.to_string(),
+///         file_comp: file_comp.to_string(),
+///         ..Default::default()
+///     };
+///     let diffs = files_diff(
+///         vec![
+///             pair("./mocks/dir_one/hello.txt", "./mocks/dir_two/hello.txt"),
+///             pair(
+///                 "./mocks/dir_one/vlang/purpose/purpose.txt",
+///                 "./mocks/dir_five/vlang/purpose/purpose.txt",
+///             ),
+///         ],
+///         4,
+///     )
+///     .await;
+///
+///     assert_eq!(diffs[0].as_ref().ok(), Some(&false));
+///     assert_eq!(diffs[1].as_ref().ok(), Some(&true));
+/// }
+/// ```
+pub async fn files_diff(files: Vec<FileDiff>, concurrency: usize) -> Vec<Result<bool, DiffError>> {
+    files_diff_with(&LocalFs, &LocalFs, files, concurrency).await
+}
+
+/// Compare many pairs of files read from any `DiffFs`, see `files_diff`
+pub async fn files_diff_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    fs_comp: &C,
+    files: Vec<FileDiff>,
+    concurrency: usize,
+) -> Vec<Result<bool, DiffError>> {
+    stream::iter(files)
+        .map(|file_diff_options| try_file_diff_with(fs, fs_comp, file_diff_options))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_true_if_both_dir_tree_are_different() {
+    let diff = dir_diff(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_three".to_string(),
+        excluding: None,
+        recursive_excluding: false,
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(diff, true);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_false_if_both_dir_tree_are_equal() {
+    let diff = dir_diff(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_two".to_string(),
+        excluding: None,
+        recursive_excluding: false,
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(diff, false);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_true_if_both_dir_tree_have_different_content() {
+    let diff = dir_diff(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_four".to_string(),
+        excluding: None,
+        recursive_excluding: false,
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(diff, true);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_false_if_both_dir_have_different_subdir_excluded_recursively() {
+    let diff = dir_diff(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_five".to_string(),
+        excluding: Some(vec!["purpose".to_string()]),
+        recursive_excluding: true,
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(diff, false);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_true_if_both_dir_have_different_subdir_excluded_not_recursively() {
     let diff = dir_diff(DirDiff {
         dir: "./mocks/dir_one".to_string(),
         dir_comp: "./mocks/dir_five".to_string(),
         excluding: Some(vec!["purpose".to_string()]),
         recursive_excluding: false,
+        ..Default::default()
     })
     .await;
     assert_eq!(diff, true);
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_false_if_both_files_are_equal() {
     let diff = file_diff(FileDiff {
         file: "./mocks/dir_one/hello.txt".to_string(),
         file_comp: "./mocks/dir_two/hello.txt".to_string(),
+        ..Default::default()
     })
     .await;
 
     assert_eq!(diff, false);
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_true_if_both_files_are_not_equal() {
     let diff = file_diff(FileDiff {
         file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
         file_comp: "./mocks/dir_five/vlang/purpose/purpose.txt".to_string(),
+        ..Default::default()
     })
     .await;
 
     assert_eq!(diff, true);
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_false_if_both_dir_tree_are_equal_with_fs() {
+    let diff = dir_diff_with(
+        &LocalFs,
+        &LocalFs,
+        DirDiff {
+            dir: "./mocks/dir_one".to_string(),
+            dir_comp: "./mocks/dir_two".to_string(),
+            excluding: None,
+            recursive_excluding: false,
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(!diff);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_true_if_both_files_are_not_equal_with_fs() {
+    let diff = file_diff_with(
+        &LocalFs,
+        &LocalFs,
+        FileDiff {
+            file: "./mocks/dir_one/vlang/purpose/purpose.txt".to_string(),
+            file_comp: "./mocks/dir_five/vlang/purpose/purpose.txt".to_string(),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert!(diff);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_true_if_both_mem_dir_have_different_content() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_two/hello.txt", "Hello world")
+        .file("dir_two/vlang/hello.v", "fn main() { println(\"hello\") }")
+        .build();
+
+    let diff = dir_diff_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            excluding: None,
+            recursive_excluding: false,
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(diff);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_entries_only_in_one_dir() {
+    let report = dir_diff_report(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_three".to_string(),
+        excluding: None,
+        recursive_excluding: false,
+        ..Default::default()
+    })
+    .await;
+
+    assert_eq!(
+        report.differences,
+        vec![
+            report::Difference::Added("hello.js".to_string()),
+            report::Difference::Removed("hello.py".to_string()),
+            report::Difference::Removed("hello.txt".to_string()),
+            report::Difference::Removed("vlang".to_string()),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_modified_files() {
+    let report = dir_diff_report(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_four".to_string(),
+        excluding: None,
+        recursive_excluding: false,
+        ..Default::default()
+    })
+    .await;
+
+    assert_eq!(
+        report.differences,
+        vec![report::Difference::Modified(
+            "vlang/purpose/purpose.txt".to_string()
+        )]
+    );
+    assert_eq!(report.stats.modified, 1);
+    assert_eq!(report.stats.tree.files, 4);
+    assert_eq!(report.stats.bytes_compared, 2 * 99 + 15);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_nothing_if_both_dir_are_equal() {
+    let report = dir_diff_report(DirDiff {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_five".to_string(),
+        excluding: Some(vec!["purpose".to_string()]),
+        recursive_excluding: true,
+        ..Default::default()
+    })
+    .await;
+
+    assert!(report.is_equal());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_the_duplicated_files_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/copy.txt", "Hello world")
+        .file("dir_two/moved/hello.txt", "Hello world")
+        .file("dir_two/other.txt", "Other")
+        .build();
+
+    let duplicates = dir_duplicates_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            excluding: None,
+            recursive_excluding: false,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].paths, vec!["copy.txt", "hello.txt"]);
+    assert_eq!(duplicates[0].paths_comp, vec!["moved/hello.txt"]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_score_similarity_by_size_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/same.txt", "123456")
+        .file("dir_one/changed.txt", "12")
+        .file("dir_two/same.txt", "123456")
+        .file("dir_two/changed.txt", "ab")
+        .build();
+    let options = || DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        excluding: None,
+        recursive_excluding: false,
+        ..Default::default()
+    };
+
+    assert_eq!(dir_similarity_with(&fs, &fs, options()).await, 0.75);
+
+    let empty = vfs::mem::MemFs::builder()
+        .dir("dir_one")
+        .dir("dir_two")
+        .build();
+    assert_eq!(dir_similarity_with(&empty, &empty, options()).await, 1.0);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_score_similarity_with_the_normalization() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello World")
+        .file("dir_two/hello.txt", "hello world")
+        .build();
+    let options = DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        normalization: normalize::Normalization {
+            ignore_case: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    assert_eq!(dir_similarity_with(&fs, &fs, options).await, 1.0);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_changed_bytes_of_big_files_with_fs() {
+    let content: Vec<u8> = (0..64 * 1024u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+        .collect();
+    let mut content_comp = content.clone();
+    content_comp[40_000] ^= 0xff;
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/big.bin", content)
+        .file("dir_two/big.bin", content_comp)
+        .build();
+
+    let report = dir_diff_report_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            chunking: Some(chunk::Chunker::new(256, 1024, 4096)),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let change = report.changes["big.bin"];
+    assert!(change.changed_bytes > 0 && change.changed_bytes <= 4096);
+    assert_eq!(change.len_comp, 64 * 1024);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_true_if_files_swap_contents_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/a.txt", "A")
+        .file("dir_one/b.txt", "B")
+        .file("dir_two/a.txt", "B")
+        .file("dir_two/b.txt", "A")
+        .build();
+
+    let diff = dir_diff_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert!(diff);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_not_read_the_files_comparing_the_structure_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .build();
+    let options = |mode| DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        mode,
+        ..Default::default()
+    };
+
+    assert!(!dir_diff_with(&fs, &fs, options(CompareMode::Structure)).await);
+    assert!(dir_diff_with(&fs, &fs, options(CompareMode::Full)).await);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_false_if_the_contents_are_reorganized_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_two/archive/2020/greetings.txt", "Hello world")
+        .file("dir_two/main.v", "fn main() {}")
+        .build();
+    let options = |mode| DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        mode,
+        ..Default::default()
+    };
+
+    assert!(!dir_diff_with(&fs, &fs, options(CompareMode::Content)).await);
+    assert!(dir_diff_with(&fs, &fs, options(CompareMode::Full)).await);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_files_with_the_comparator_with_fs() {
+    use async_trait::async_trait;
+    use compare::FileComparator;
+    use std::sync::Arc;
+
+    struct IgnoreCase;
+
+    #[async_trait]
+    impl FileComparator for IgnoreCase {
+        async fn equal(
+            &self,
+            _path: &str,
+            content: &[u8],
+            content_comp: &[u8],
+        ) -> std::io::Result<bool> {
+            Ok(content.eq_ignore_ascii_case(content_comp))
+        }
+    }
+
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/hello.txt", "HELLO WORLD")
+        .build();
+    let options = |comparator: Option<Arc<dyn FileComparator>>| DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        comparator,
+        ..Default::default()
+    };
+
+    assert!(dir_diff_with(&fs, &fs, options(None)).await);
+    assert!(!dir_diff_with(&fs, &fs, options(Some(Arc::new(IgnoreCase)))).await);
+    assert!(
+        dir_diff_report_with(&fs, &fs, options(Some(Arc::new(IgnoreCase))))
+            .await
+            .is_equal()
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_the_digests_of_the_files_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/only.txt", "Only in one")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .build();
+    let report = dir_diff_report_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            report_digests: true,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let hash = |content: &[u8]| hash::to_hex(&hash::content_hash(content).to_le_bytes());
+    assert_eq!(report.digests.keys().collect::<Vec<_>>(), vec!["hello.txt"]);
+    assert_eq!(
+        report.digests["hello.txt"],
+        report::FileDigest {
+            digest: hash(b"Hello world"),
+            digest_comp: hash(b"Hello vlang"),
+        }
+    );
+    assert!(report::to_json(&report).ends_with(&format!(
+        r#","digests":{{"hello.txt":{{"digest":"{}","digest_comp":"{}"}}}}}}"#,
+        hash(b"Hello world"),
+        hash(b"Hello vlang")
+    )));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_plan_the_sync_of_the_dirs_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .file("dir_two/only.txt", "Only in two")
+        .build();
+    let plan = sync_plan_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(plan.copies(), vec!["hello.txt", "vlang"]);
+    assert_eq!(plan.deletes(), vec!["only.txt"]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_symlinks_by_their_target_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .symlink("dir_one/current", "hello.txt")
+        .symlink("dir_one/latest", "hello.txt")
+        .symlink("dir_one/config", "hello.txt")
+        .file("dir_two/hello.txt", "Hello world")
+        .symlink("dir_two/current", "hello.txt")
+        .symlink("dir_two/latest", "hello.v")
+        .file("dir_two/config", "Hello world")
+        .build();
+    let report = dir_diff_report_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            ..Default::default()
+        },
+    )
+    .await;
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+
+    assert_eq!(
+        report.differences,
+        vec![
+            Difference::Modified("config".to_string()),
+            Difference::Modified("latest".to_string()),
+        ]
+    );
+    assert_eq!(
+        Tree::find(&tree, "current").map(|entry| entry.kind),
+        Some(EntryKind::Symlink)
+    );
+    assert_eq!(report.stats.tree.files, 1);
+}
+
+#[cfg(unix)]
+#[cfg(test)]
+#[tokio::test]
+async fn should_follow_the_symlinks_without_looping() {
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_cycles_{}", std::process::id()));
+    for side in ["one", "two"] {
+        std::fs::create_dir_all(dir.join(side).join("sub")).unwrap();
+        std::fs::write(dir.join(side).join("sub/hello.txt"), side).unwrap();
+        std::os::unix::fs::symlink("..", dir.join(side).join("sub/parent")).unwrap();
+    }
+    let report = dir_diff_report(DirDiff {
+        dir: dir.join("one").to_string_lossy().into_owned(),
+        dir_comp: dir.join("two").to_string_lossy().into_owned(),
+        follow_symlinks: true,
+        ..Default::default()
+    })
+    .await;
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        report.differences,
+        vec![Difference::Modified("sub/hello.txt".to_string())]
+    );
+}
+
+#[cfg(unix)]
+#[cfg(test)]
+#[tokio::test]
+async fn should_not_read_the_special_files() {
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_sockets_{}", std::process::id()));
+    for side in ["dir_one", "dir_two"] {
+        std::fs::create_dir_all(dir.join(side)).unwrap();
+        std::fs::write(dir.join(side).join("hello.txt"), "Hello world").unwrap();
+        std::os::unix::net::UnixListener::bind(dir.join(side).join("server.sock")).unwrap();
+    }
+    std::fs::write(dir.join("dir_two/server.sock.txt"), "").unwrap();
+
+    let report = dir_diff_report(DirDiff {
+        dir: dir.join("dir_one").to_string_lossy().into_owned(),
+        dir_comp: dir.join("dir_two").to_string_lossy().into_owned(),
+        ..Default::default()
+    })
+    .await;
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        report.differences,
+        vec![Difference::Added("server.sock.txt".to_string())]
+    );
+    assert_eq!(report.special_files, vec!["server.sock"]);
+}
+
+#[cfg(unix)]
+#[cfg(test)]
+#[tokio::test]
+async fn should_not_read_the_special_files_behind_the_symlinks() {
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_fifos_{}", std::process::id()));
+    for side in ["dir_one", "dir_two"] {
+        std::fs::create_dir_all(dir.join(side)).unwrap();
+        let status = std::process::Command::new("mkfifo")
+            .arg(dir.join(side).join("events"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+        std::os::unix::fs::symlink("events", dir.join(side).join("current")).unwrap();
+    }
+
+    // reading the FIFO would block until something writes to it
+    let report = dir_diff_report(DirDiff {
+        dir: dir.join("dir_one").to_string_lossy().into_owned(),
+        dir_comp: dir.join("dir_two").to_string_lossy().into_owned(),
+        follow_symlinks: true,
+        timeout: Some(Duration::from_secs(5)),
+        ..Default::default()
+    })
+    .await;
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.differences, vec![]);
+    assert_eq!(report.special_files, vec!["current", "events"]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_the_content_types_of_the_differences_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/logo.png", &b"\x89PNG\r\n\x1a\n\x01"[..])
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/logo.png", &b"\x89PNG\r\n\x1a\n\0"[..])
+        .file("dir_two/hello.txt", "Hello vlang")
+        .build();
+    let report = dir_diff_report_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            detect_content_type: true,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        report.content_types.get("logo.png").map(String::as_str),
+        Some("image/png")
+    );
+    assert_eq!(
+        report.differences_of_type("text/plain"),
+        vec![&Difference::Modified("hello.txt".to_string())]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_ignore_the_trailing_newline_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world\n")
+        .file("dir_two/hello.txt", "Hello world")
+        .build();
+    let normalization = normalize::Normalization {
+        ignore_trailing_newline: true,
+        ..Default::default()
+    };
+    let diff = dir_diff_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            normalization: normalization.clone(),
+            ..Default::default()
+        },
+    )
+    .await;
+    let file_diff = file_diff_with(
+        &fs,
+        &fs,
+        FileDiff {
+            file: "dir_one/hello.txt".to_string(),
+            file_comp: "dir_two/hello.txt".to_string(),
+            normalization,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert!(!diff);
+    assert!(!file_diff);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_the_metrics_of_the_comparison_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .file("dir_two/vlang/hello.v", "fn main() {}")
+        .file("dir_two/only.txt", "Only in two")
+        .build();
+    let report = dir_diff_report_with(
+        &fs,
+        &fs,
+        DirDiff {
+            dir: "dir_one".to_string(),
+            dir_comp: "dir_two".to_string(),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(report.metrics.entries_scanned, 7);
+    assert_eq!(report.metrics.files_read, 4);
+    assert_eq!(report.metrics.bytes_read, report.stats.bytes_compared);
+    assert_eq!(report.metrics.bytes_read, 46);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_not_read_again_the_cached_files_with_fs() {
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    let now = SystemTime::now();
+    let fs = vfs::mem::MemFs::builder()
+        .file_modified("dir_one/hello.txt", "Hello world", now)
+        .file_modified("dir_one/vlang/hello.v", "fn main() {}", now)
+        .file_modified("dir_two/hello.txt", "Hello world", now)
+        .file_modified("dir_two/vlang/hello.v", "fn main() {}", now)
+        .build();
+    let hash_cache = Arc::new(cache::HashCache::new());
+    let options = DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        hash_cache: Some(hash_cache.clone()),
+        ..Default::default()
+    };
+
+    let first = dir_diff_report_with(&fs, &fs, options.clone()).await;
+    let second = dir_diff_report_with(&fs, &fs, options.clone()).await;
+    assert!(first.is_equal() && second.is_equal());
+    assert_eq!(first.stats.bytes_compared, 46);
+    assert_eq!(second.stats.bytes_compared, 0);
+    assert_eq!(hash_cache.len(), 4);
+
+    // the modified file is read again because its size changed
+    let fs = vfs::mem::MemFs::builder()
+        .file_modified("dir_one/hello.txt", "Hello world", now)
+        .file_modified("dir_one/vlang/hello.v", "fn main() {}", now)
+        .file_modified("dir_two/hello.txt", "Hello vlang!", now)
+        .file_modified("dir_two/vlang/hello.v", "fn main() {}", now)
+        .build();
+    let third = dir_diff_report_with(&fs, &fs, options.clone()).await;
+    assert_eq!(
+        third.differences,
+        vec![Difference::Modified("hello.txt".to_string())]
+    );
+    assert_eq!(third.stats.bytes_compared, 23);
+    assert!(dir_diff_with(&fs, &fs, options).await);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_group_the_directories_which_agree_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("server_one/index.html", "v2")
+        .file("server_one/app.js", "app")
+        .file("server_two/index.html", "v2")
+        .file("server_two/app.js", "app")
+        .file("server_three/index.html", "v1")
+        .file("server_three/app.js", "app")
+        .file("server_four/index.html", "v2")
+        .dir("server_four/app.js")
+        .file("server_five/index.html", "v2")
+        .build();
+    let differences = multi_dir_diff_with(
+        &fs,
+        vec![
+            "server_one".to_string(),
+            "server_two".to_string(),
+            "server_three".to_string(),
+            "server_four".to_string(),
+            "server_five".to_string(),
+        ],
+        DirDiff::default(),
+    )
+    .await;
+
+    assert_eq!(
+        differences,
+        vec![
+            multi::MultiDifference {
+                path: "app.js".to_string(),
+                groups: vec![vec![0, 1, 2], vec![3]],
+                missing: vec![4],
+            },
+            multi::MultiDifference {
+                path: "index.html".to_string(),
+                groups: vec![vec![0, 1, 3, 4], vec![2]],
+                missing: vec![],
+            },
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_many_pairs_of_files_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/hello.v", "fn main() {}")
+        .file("dir_two/hello.txt", "Hello world")
+        .file("dir_two/hello.v", "fn main() { println('hello') }")
+        .build();
+    let pair = |name: &str| FileDiff {
+        file: format!("dir_one/{}", name),
+        file_comp: format!("dir_two/{}", name),
+        ..Default::default()
+    };
+    let files = vec![pair("hello.txt"), pair("hello.v"), pair("missing.txt")];
+    let diffs = files_diff_with(&fs, &fs, files.clone(), 2).await;
+
+    assert_eq!(
+        diffs[..2]
+            .iter()
+            .map(|diff| *diff.as_ref().unwrap())
+            .collect::<Vec<_>>(),
+        vec![false, true]
+    );
+    assert_eq!(diffs[2].as_ref().unwrap_err().path(), "dir_one/missing.txt");
+    assert_eq!(files_diff_with(&fs, &fs, files, 0).await.len(), 3);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_an_error_if_a_file_cant_be_read_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .build();
+    let diff = |file_comp: &str| FileDiff {
+        file: "dir_one/hello.txt".to_string(),
+        file_comp: file_comp.to_string(),
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        try_file_diff_with(&fs, &fs, diff("dir_two/missing.txt")).await,
+        Err(DiffError::NotFound { path }) if path == "dir_two/missing.txt"
+    ));
+    assert!(!try_file_diff_with(&fs, &fs, diff("dir_one/hello.txt"))
+        .await
+        .unwrap());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_binary_files_with_fs() {
+    let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let mut content_comp = content.clone();
+    content_comp[150_000] ^= 0xff;
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/image.png", content.clone())
+        .file("dir_two/image.png", content_comp)
+        .file("dir_two/copy.png", content.clone())
+        .file("dir_two/short.png", &content[..FILE_BUFFER_SIZE])
+        .build();
+    let diff = |file_comp: &str| FileDiff {
+        file: "dir_one/image.png".to_string(),
+        file_comp: file_comp.to_string(),
+        ..Default::default()
+    };
+
+    assert!(file_diff_with(&fs, &fs, diff("dir_two/image.png")).await);
+    assert!(!file_diff_with(&fs, &fs, diff("dir_two/copy.png")).await);
+    assert!(file_diff_with(&fs, &fs, diff("dir_two/short.png")).await);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_the_changed_lines_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.v", "fn main() {\n\tprintln('hello')\n}\n")
+        .file(
+            "dir_two/hello.v",
+            "// hello\nfn main() {\n\tprintln('vlang')\n\tprintln('world')\n}\n",
+        )
+        .file("dir_one/image.png", vec![0x89, b'P', b'N', b'G', 0])
+        .file("dir_two/image.png", vec![0x89, b'P', b'N', b'G', 1])
+        .build();
+    let diff = |file: &str| FileDiff {
+        file: format!("dir_one/{}", file),
+        file_comp: format!("dir_two/{}", file),
+        ..Default::default()
+    };
+
+    let result = file_diff_report_with(&fs, &fs, diff("hello.v"))
+        .await
+        .unwrap();
+    assert_eq!((result.additions, result.deletions), (3, 1));
+    assert_eq!(
+        result.changes,
+        vec![
+            lines::LineChange {
+                start: 0,
+                len: 0,
+                start_comp: 0,
+                len_comp: 1
+            },
+            lines::LineChange {
+                start: 1,
+                len: 1,
+                start_comp: 2,
+                len_comp: 2
+            },
+        ]
+    );
+
+    let result = file_diff_report_with(&fs, &fs, diff("image.png"))
+        .await
+        .unwrap();
+    assert!(result.different && result.binary && result.changes.is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_files_by_blocks_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/hello.txt", "Hello world")
+        .file("dir_two/vlang.txt", "Hello vlang")
+        .file("dir_two/long.txt", "Hello world!")
+        .build();
+    let files = |file_comp: &str| FileDiff {
+        file: "dir_one/hello.txt".to_string(),
+        file_comp: file_comp.to_string(),
+        ..Default::default()
+    };
+
+    for buffer_size in [0, 1, 3, 11, 4096] {
+        assert!(
+            file_equal_streaming_with(&fs, &fs, files("dir_two/hello.txt"), buffer_size)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !file_equal_streaming_with(&fs, &fs, files("dir_two/vlang.txt"), buffer_size)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !file_equal_streaming_with(&fs, &fs, files("dir_two/long.txt"), buffer_size)
+                .await
+                .unwrap()
+        );
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_abort_after_the_timeout_with_fs() {
+    use async_trait::async_trait;
+    use vfs::{VfsEntry, VfsMetadata};
+
+    /// Source which never answers, like a dead network mount
+    struct DeadMount;
+
+    #[async_trait]
+    impl DiffFs for DeadMount {
+        async fn read_dir(&self, _path: &str) -> std::io::Result<Vec<VfsEntry>> {
+            futures::future::pending().await
+        }
+
+        async fn metadata(&self, _path: &str) -> std::io::Result<VfsMetadata> {
+            futures::future::pending().await
+        }
+
+        async fn read(&self, _path: &str) -> std::io::Result<Vec<u8>> {
+            futures::future::pending().await
+        }
+    }
+
+    let timeout = Some(Duration::from_millis(20));
+    let diff = try_dir_diff_with(
+        &LocalFs,
+        &DeadMount,
+        DirDiff {
+            dir: "./mocks/dir_one".to_string(),
+            dir_comp: "/mnt/nfs".to_string(),
+            timeout,
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(matches!(diff, Err(DiffError::Timeout { path, .. }) if path == "./mocks/dir_one"));
+
+    let diff = try_file_diff_with(
+        &LocalFs,
+        &DeadMount,
+        FileDiff {
+            file: "./mocks/dir_one/hello.txt".to_string(),
+            file_comp: "/mnt/nfs/hello.txt".to_string(),
+            timeout,
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(matches!(diff, Err(DiffError::Timeout { .. })));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_what_differs_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .file("dir_three/hello.txt", "Hello world")
+        .file("dir_three/hello.v", "fn main() {}")
+        .build();
+    let outcome = |dir_comp: &str| {
+        dir_diff_outcome_with(
+            &fs,
+            &fs,
+            DirDiff {
+                dir: "dir_one".to_string(),
+                dir_comp: dir_comp.to_string(),
+                ..Default::default()
+            },
+        )
+    };
+
+    assert_eq!(outcome("dir_one").await, DirDiffOutcome::Equal);
+    assert_eq!(outcome("dir_two").await, DirDiffOutcome::ContentDiffers);
+    assert_eq!(outcome("dir_three").await, DirDiffOutcome::StructureDiffers);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_any_readers() {
+    let mut reader = rt::reader(b"Hello world".to_vec());
+    let mut reader_comp = rt::reader(b"Hello".to_vec());
+
+    assert!(
+        !readers_equal("one", &mut reader, "two", &mut reader_comp, 4)
+            .await
+            .unwrap()
+    );
+}
+
+/// Source which denies the access to the entries named `secret`
+#[cfg(test)]
+struct Forbidden(vfs::mem::MemFs);
+
+#[cfg(test)]
+impl Forbidden {
+    fn new() -> Self {
+        Forbidden(
+            vfs::mem::MemFs::builder()
+                .file("dir_one/hello.txt", "Hello world")
+                .file("dir_one/secret.txt", "Hello world")
+                .file("dir_one/secret/hello.txt", "Hello world")
+                .file("dir_two/hello.txt", "Hello world")
+                .file("dir_two/secret.txt", "Hello vlang")
+                .file("dir_two/secret/hello.txt", "Hello vlang")
+                .build(),
+        )
+    }
+
+    fn check(path: &str) -> std::io::Result<()> {
+        if path.contains("secret") {
+            return Err(std::io::ErrorKind::PermissionDenied.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl DiffFs for Forbidden {
+    async fn read_dir(&self, path: &str) -> std::io::Result<Vec<vfs::VfsEntry>> {
+        Forbidden::check(path)?;
+        self.0.read_dir(path).await
+    }
+
+    async fn metadata(&self, path: &str) -> std::io::Result<vfs::VfsMetadata> {
+        self.0.metadata(path).await
+    }
+
+    async fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+        Forbidden::check(path)?;
+        self.0.read(path).await
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_the_unreadable_entries_with_fs() {
+    let fs = Forbidden::new();
+    let options = DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        ..Default::default()
+    };
+
+    let report = dir_diff_report_with(&fs, &fs, options.clone()).await;
+    assert!(report.is_equal());
+    assert_eq!(report.unreadable, vec!["secret", "secret.txt"]);
+    assert!(!dir_diff_with(&fs, &fs, options.clone()).await);
+
+    let options = DirDiff {
+        unreadable_as_modified: true,
+        ..options
+    };
+    let report = dir_diff_report_with(&fs, &fs, options.clone()).await;
+    assert_eq!(
+        report.differences,
+        vec![
+            Difference::Modified("secret".to_string()),
+            Difference::Modified("secret.txt".to_string())
+        ]
+    );
+    assert!(dir_diff_with(&fs, &fs, options).await);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_collect_the_errors_or_fail_fast_with_fs() {
+    let fs = Forbidden::new();
+    let options = DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        ..Default::default()
+    };
+
+    let report = dir_diff_report_with(&fs, &fs, options.clone()).await;
+    assert_eq!(
+        report.errors,
+        vec![
+            DiffError::PermissionDenied {
+                path: "dir_one/secret".to_string()
+            },
+            DiffError::PermissionDenied {
+                path: "dir_two/secret".to_string()
+            },
+            DiffError::PermissionDenied {
+                path: "dir_one/secret.txt".to_string()
+            },
+            DiffError::PermissionDenied {
+                path: "dir_two/secret.txt".to_string()
+            },
+        ]
+    );
+
+    let options = DirDiff {
+        on_error: ErrorPolicy::FailFast,
+        ..options
+    };
+    let diff = try_dir_diff_with(&fs, &fs, options.clone()).await;
+    assert_eq!(
+        diff,
+        Err(DiffError::PermissionDenied {
+            path: "dir_one/secret".to_string()
+        })
+    );
+
+    let diff = try_dir_diff_with(
+        &fs,
+        &fs,
+        DirDiff {
+            excluding: Some(vec!["secret".to_string()]),
+            ..options
+        },
+    )
+    .await;
+    assert_eq!(
+        diff,
+        Err(DiffError::PermissionDenied {
+            path: "dir_one/secret.txt".to_string()
+        })
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_with_the_presets_with_fs() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world\n")
+        .file("dir_one/.cache/index", "1")
+        .file("dir_two/hello.txt", "Hello world")
+        .file("dir_two/.cache/index", "2")
+        .dir("dir_two/empty")
+        .build();
+    let report = |options: DirDiff| {
+        dir_diff_report_with(
+            &fs,
+            &fs,
+            DirDiff {
+                dir: "dir_one".to_string(),
+                dir_comp: "dir_two".to_string(),
+                ..options
+            },
+        )
+    };
+
+    assert!(report(DirDiff::lenient()).await.is_equal());
+    assert_eq!(
+        report(DirDiff::strict()).await.differences,
+        vec![
+            Difference::Modified(".cache/index".to_string()),
+            Difference::Added("empty".to_string()),
+            Difference::Modified("hello.txt".to_string()),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_a_missing_file_as_empty() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/empty.txt", "")
+        .file("dir_one/hello.txt", "Hello\r\nWorld  \r\n")
+        .file("dir_two/hello.txt", "hello\nworld\n")
+        .build();
+    let options = |file: &str, file_comp: &str| FileDiff {
+        file: file.to_string(),
+        file_comp: file_comp.to_string(),
+        normalization: normalize::Normalization {
+            normalize_eol: true,
+            trim_trailing_whitespace: true,
+            ignore_case: true,
+            ..Default::default()
+        },
+        missing_as_empty: true,
+        ..Default::default()
+    };
+
+    assert!(
+        !try_file_diff_with(&fs, &fs, options("dir_one/empty.txt", "dir_two/empty.txt"))
+            .await
+            .unwrap()
+    );
+    assert!(
+        !try_file_diff_with(&fs, &fs, options("dir_one/hello.txt", "dir_two/hello.txt"))
+            .await
+            .unwrap()
+    );
+    let added = file_diff_report_with(&fs, &fs, options("dir_one/hello.txt", "dir_two/new.txt"))
+        .await
+        .unwrap();
+    assert_eq!((added.additions, added.deletions), (0, 2));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_flag_only_the_truncated_trees() {
+    let fs = vfs::mem::MemFs::builder()
+        .file("dir_one/a.txt", "a")
+        .dir("dir_one/empty")
+        .file("dir_one/z.txt", "z")
+        .file("dir_two/a.txt", "a")
+        .file("dir_two/z.txt", "z")
+        .build();
+    let report = |max_entries: usize| {
+        dir_diff_report_with(
+            &fs,
+            &fs,
+            DirDiff {
+                dir: "dir_one".to_string(),
+                dir_comp: "dir_two".to_string(),
+                ignore_empty_dirs: true,
+                max_entries: Some(max_entries),
+                ..Default::default()
+            },
+        )
+    };
+
+    // `empty` is counted but pruned, so `z.txt` is left out of a tree of one entry
+    assert!(report(2).await.truncated);
+    assert!(!report(3).await.truncated);
+}
@@ -0,0 +1,342 @@
+//! Line by line differences of two texts with the algorithm of Myers, which finds the
+//! shortest edit script in O((N+M)D) where D is the number of lines added and removed
+use std::fmt;
+
+/// Consecutive lines removed from the first text and added in the second one, the
+/// indexes start at 0. A pure addition has `len` 0 and a pure deletion `len_comp` 0
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LineChange {
+    /// first line changed in the first text
+    pub start: usize,
+    /// number of lines removed from the first text
+    pub len: usize,
+    /// first line changed in the second text
+    pub start_comp: usize,
+    /// number of lines added in the second text
+    pub len_comp: usize,
+}
+
+/// Line of a hunk with its line break, the last line of a text may have none
+#[derive(Debug, PartialEq, Clone)]
+pub enum HunkLine {
+    /// line present in both texts
+    Context(String),
+    /// line only in the first text
+    Removed(String),
+    /// line only in the second text
+    Added(String),
+}
+
+/// Lines changed between two texts surrounded by some unchanged ones, like the hunks of
+/// `diff -u`. Displayed in the unified format with its `@@` header
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Hunk {
+    /// first line of the hunk in the first text, from 0
+    pub start: usize,
+    /// number of lines of the hunk in the first text
+    pub len: usize,
+    /// first line of the hunk in the second text, from 0
+    pub start_comp: usize,
+    /// number of lines of the hunk in the second text
+    pub len_comp: usize,
+    /// lines of the hunk in order
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// Header of the hunk, `@@ -1,4 +1,5 @@`. The ranges are numbered from 1, with only
+    /// the start if they have one line and with the line before them if they are empty
+    pub fn header(&self) -> String {
+        let range = |start: usize, len: usize| match len {
+            0 => format!("{},0", start),
+            1 => format!("{}", start + 1),
+            _ => format!("{},{}", start + 1, len),
+        };
+
+        format!(
+            "@@ -{} +{} @@",
+            range(self.start, self.len),
+            range(self.start_comp, self.len_comp)
+        )
+    }
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.header())?;
+        for line in &self.lines {
+            let (prefix, text) = match line {
+                HunkLine::Context(text) => (' ', text),
+                HunkLine::Removed(text) => ('-', text),
+                HunkLine::Added(text) => ('+', text),
+            };
+            write!(f, "{}{}", prefix, text)?;
+            if !text.ends_with('\n') {
+                write!(f, "\n\\ No newline at end of file\n")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(PartialEq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Shortest edit script to transform `lines` into `lines_comp`, in order
+fn edit_script<T: PartialEq>(lines: &[T], lines_comp: &[T]) -> Vec<Edit> {
+    let n = lines.len() as isize;
+    let m = lines_comp.len() as isize;
+    let max = n + m;
+    let offset = max as usize;
+    let index = |k: isize| (k + offset as isize) as usize;
+    let mut v = vec![0isize; 2 * offset + 2];
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && lines[x as usize] == lines_comp[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits: Vec<Edit> = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+
+    edits
+}
+
+/// Return true if the content is not text: it is not valid utf-8 or has a NUL byte in
+/// its first 8000 bytes, like git does
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::lines::is_binary;
+///
+/// assert!(!is_binary(b"Hello world"));
+/// assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0"));
+/// ```
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|byte| *byte == 0) || std::str::from_utf8(content).is_err()
+}
+
+/// Return the groups of lines changed between both texts, in order
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::lines::{diff_lines, LineChange};
+///
+/// let changes = diff_lines(&["fn main() {", "}"], &["fn main() {", "    println('hello')", "}"]);
+///
+/// assert_eq!(
+///     changes,
+///     vec![LineChange { start: 1, len: 0, start_comp: 1, len_comp: 1 }]
+/// );
+/// ```
+pub fn diff_lines<T: PartialEq>(lines: &[T], lines_comp: &[T]) -> Vec<LineChange> {
+    let mut changes: Vec<LineChange> = vec![];
+    let (mut line, mut line_comp) = (0, 0);
+    let mut current: Option<LineChange> = None;
+
+    for edit in edit_script(lines, lines_comp) {
+        if edit == Edit::Equal {
+            changes.extend(current.take());
+            line += 1;
+            line_comp += 1;
+            continue;
+        }
+        let change = current.get_or_insert(LineChange {
+            start: line,
+            len: 0,
+            start_comp: line_comp,
+            len_comp: 0,
+        });
+        if edit == Edit::Delete {
+            change.len += 1;
+            line += 1;
+        } else {
+            change.len_comp += 1;
+            line_comp += 1;
+        }
+    }
+    changes.extend(current);
+
+    changes
+}
+
+/// Group the lines changed between both texts in hunks with `context` unchanged lines
+/// around them, the changes closer than twice the context share the same hunk
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::lines::hunks;
+///
+/// let hunks = hunks("a\nb\nc\nd\n", "a\nb\nx\nd\n", 1);
+///
+/// assert_eq!(hunks.len(), 1);
+/// assert_eq!(hunks[0].to_string(), "@@ -2,3 +2,3 @@\n b\n-c\n+x\n d\n");
+/// ```
+pub fn hunks(text: &str, text_comp: &str, context: usize) -> Vec<Hunk> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let lines_comp: Vec<&str> = text_comp.split_inclusive('\n').collect();
+
+    let mut groups: Vec<Vec<LineChange>> = vec![];
+    for change in diff_lines(&lines, &lines_comp) {
+        match groups.last_mut() {
+            Some(group)
+                if change.start - (group[group.len() - 1].start + group[group.len() - 1].len)
+                    <= 2 * context =>
+            {
+                group.push(change)
+            }
+            _ => groups.push(vec![change]),
+        }
+    }
+
+    let mut hunks: Vec<Hunk> = vec![];
+    for group in groups {
+        let (first, last) = (group[0], group[group.len() - 1]);
+        // the lines around the changes are unchanged, so there are as many in both texts
+        let start = first.start.saturating_sub(context);
+        let start_comp = first.start_comp - (first.start - start);
+        let end = (last.start + last.len + context).min(lines.len());
+        let end_comp = last.start_comp + last.len_comp + (end - last.start - last.len);
+
+        let mut hunk_lines: Vec<HunkLine> = vec![];
+        let mut line = start;
+        for change in &group {
+            let context_lines = &lines[line..change.start];
+            let removed = &lines[change.start..change.start + change.len];
+            let added = &lines_comp[change.start_comp..change.start_comp + change.len_comp];
+            hunk_lines.extend(
+                context_lines
+                    .iter()
+                    .map(|l| HunkLine::Context(l.to_string())),
+            );
+            hunk_lines.extend(removed.iter().map(|l| HunkLine::Removed(l.to_string())));
+            hunk_lines.extend(added.iter().map(|l| HunkLine::Added(l.to_string())));
+            line = change.start + change.len;
+        }
+        hunk_lines.extend(
+            lines[line..end]
+                .iter()
+                .map(|l| HunkLine::Context(l.to_string())),
+        );
+
+        hunks.push(Hunk {
+            start,
+            len: end - start,
+            start_comp,
+            len_comp: end_comp - start_comp,
+            lines: hunk_lines,
+        });
+    }
+
+    hunks
+}
+
+#[test]
+fn should_find_the_changed_lines() {
+    let lines = vec!["a", "b", "c", "a", "b", "b", "a"];
+    let lines_comp = vec!["c", "b", "a", "b", "a", "c"];
+    let changes = diff_lines(&lines, &lines_comp);
+
+    let removed: usize = changes.iter().map(|change| change.len).sum();
+    let added: usize = changes.iter().map(|change| change.len_comp).sum();
+    assert_eq!((removed, added), (3, 2));
+
+    assert_eq!(diff_lines::<&str>(&[], &[]), vec![]);
+    assert_eq!(
+        diff_lines(&["a"], &[]),
+        vec![LineChange {
+            start: 0,
+            len: 1,
+            start_comp: 0,
+            len_comp: 0
+        }]
+    );
+    assert_eq!(
+        diff_lines(&["a", "b", "c"], &["a", "x", "c"]),
+        vec![LineChange {
+            start: 1,
+            len: 1,
+            start_comp: 1,
+            len_comp: 1
+        }]
+    );
+}
+
+#[test]
+fn should_group_the_changes_in_hunks() {
+    let text: String = (1..=20).map(|line| format!("{}\n", line)).collect();
+    let text_comp: String = (1..=20)
+        .map(|line| match line {
+            2 => "two\n".to_string(),
+            5 => String::new(),
+            17 => "seventeen\n".to_string(),
+            20 => "20".to_string(),
+            _ => format!("{}\n", line),
+        })
+        .collect();
+    let hunks = hunks(&text, &text_comp, 3);
+
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(
+        hunks[0].to_string(),
+        "@@ -1,8 +1,7 @@\n 1\n-2\n+two\n 3\n 4\n-5\n 6\n 7\n 8\n"
+    );
+    assert_eq!(
+        hunks[1].to_string(),
+        "@@ -14,7 +13,7 @@\n 14\n 15\n 16\n-17\n+seventeen\n 18\n 19\n-20\n+20\n\\ No newline at end of file\n"
+    );
+    assert_eq!(
+        super::lines::hunks("", "a\n", 3)[0].header(),
+        "@@ -0,0 +1 @@"
+    );
+}
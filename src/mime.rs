@@ -0,0 +1,100 @@
+//! Content type of the files detected from their first bytes
+use std::str;
+
+/// Number of bytes read from the start of a file to detect its content type
+pub const SNIFF_LEN: u64 = 512;
+
+/// Signatures at the start of the files, with the content type they identify
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"PK\x05\x06", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"BZh", "application/x-bzip2"),
+    (b"\xfd7zXZ\x00", "application/x-xz"),
+    (b"(\xb5/\xfd", "application/zstd"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"Rar!\x1a\x07", "application/vnd.rar"),
+    (b"\x7fELF", "application/x-elf"),
+    (b"MZ", "application/x-msdownload"),
+    (b"\x00asm", "application/wasm"),
+    (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"ID3", "audio/mpeg"),
+    (b"\x1aE\xdf\xa3", "video/webm"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+];
+
+/// Return the content type of a file from its first bytes, `text/plain` for the text
+/// without a known signature and `application/octet-stream` for the unknown binaries
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::mime::content_type;
+///
+/// assert_eq!(content_type(b"\x89PNG\r\n\x1a\n\0\0"), "image/png");
+/// assert_eq!(content_type(b"Hello world"), "text/plain");
+/// assert_eq!(content_type(b"\0\x01\x02"), "application/octet-stream");
+/// ```
+pub fn content_type(content: &[u8]) -> &'static str {
+    if let Some((_, content_type)) = MAGIC.iter().find(|(magic, _)| content.starts_with(magic)) {
+        return content_type;
+    }
+    if content.len() >= 12 && &content[..4] == b"RIFF" {
+        match &content[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            b"AVI " => return "video/x-msvideo",
+            _ => {}
+        }
+    }
+    if content.len() >= 12 && &content[4..8] == b"ftyp" {
+        return "video/mp4";
+    }
+    if !is_text(content) {
+        return "application/octet-stream";
+    }
+
+    let text = content.trim_ascii_start();
+    if text.starts_with(b"<?xml") {
+        "application/xml"
+    } else if text.len() >= 5 && text[..5].eq_ignore_ascii_case(b"<html")
+        || text.len() >= 14 && text[..14].eq_ignore_ascii_case(b"<!doctype html")
+    {
+        "text/html"
+    } else if text.starts_with(b"<svg") {
+        "image/svg+xml"
+    } else {
+        "text/plain"
+    }
+}
+
+/// Return true if the bytes are utf-8 without nul bytes, the content may be cut in the
+/// middle of a character at its end
+fn is_text(content: &[u8]) -> bool {
+    if content.contains(&0) {
+        return false;
+    }
+
+    match str::from_utf8(content) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
+
+#[test]
+fn should_detect_the_content_type_by_the_magic_bytes() {
+    assert_eq!(content_type(b"%PDF-1.7\n"), "application/pdf");
+    assert_eq!(content_type(b"RIFF\x24\0\0\0WEBPVP8 "), "image/webp");
+    assert_eq!(content_type(b"\n<!DOCTYPE html>\n<html>"), "text/html");
+    assert_eq!(content_type("añ".as_bytes().split_at(2).0), "text/plain");
+}
@@ -0,0 +1,152 @@
+//! Comparison of local files memory mapping them, without copying their content to the
+//! buffers of the async reads. The very large files are compared by chunks in parallel
+use crate::error::DiffError;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// Bytes of the chunks in which the very large files are split to compare them in parallel
+pub const CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Size from which the files are compared by chunks in parallel instead of at once
+pub const PARALLEL_THRESHOLD: u64 = 256 * 1024 * 1024;
+
+/// Return true if both local files have the same content, memory mapping them in a
+/// blocking task
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::mmap::equal;
+/// use std::path::Path;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let equal = equal(
+///         Path::new("./mocks/dir_one/vlang/purpose/purpose.txt"),
+///         Path::new("./mocks/dir_two/vlang/purpose/purpose.txt"),
+///     )
+///     .await
+///     .unwrap();
+///
+///     assert!(equal);
+/// }
+/// ```
+pub async fn equal(path: &Path, path_comp: &Path) -> Result<bool, DiffError> {
+    let file = path.to_string_lossy().into_owned();
+    let path = path.to_path_buf();
+    let path_comp = path_comp.to_path_buf();
+
+    crate::rt::spawn_blocking(move || equal_blocking(&path, &path_comp))
+        .await
+        .map_err(|error| DiffError::from_io(&file, error))?
+}
+
+fn equal_blocking(path: &Path, path_comp: &Path) -> Result<bool, DiffError> {
+    let file = File::open(path).map_err(|error| from_io(path, error))?;
+    let file_comp = File::open(path_comp).map_err(|error| from_io(path_comp, error))?;
+    let len = file.metadata().map_err(|error| from_io(path, error))?.len();
+    let len_comp = file_comp
+        .metadata()
+        .map_err(|error| from_io(path_comp, error))?
+        .len();
+    if len != len_comp {
+        return Ok(false);
+    }
+    // the empty files can't be mapped
+    if len == 0 {
+        return Ok(true);
+    }
+
+    // SAFETY: the maps are only read while they are alive, if other process truncates the
+    // files meanwhile the comparison may fail like any read of a file being modified
+    let map = unsafe { Mmap::map(&file) }.map_err(|error| from_io(path, error))?;
+    let map_comp = unsafe { Mmap::map(&file_comp) }.map_err(|error| from_io(path_comp, error))?;
+
+    if len < PARALLEL_THRESHOLD {
+        Ok(map[..] == map_comp[..])
+    } else {
+        Ok(equal_chunks(&map, &map_comp))
+    }
+}
+
+/// Compare the chunks of both contents in as many threads as the available cores, every
+/// thread takes the chunks at its own stride and stops at the first different chunk
+fn equal_chunks(content: &[u8], content_comp: &[u8]) -> bool {
+    let chunks: Vec<(&[u8], &[u8])> = content
+        .chunks(CHUNK_SIZE)
+        .zip(content_comp.chunks(CHUNK_SIZE))
+        .collect();
+    let workers = thread::available_parallelism()
+        .map(|workers| workers.get())
+        .unwrap_or(1)
+        .min(chunks.len());
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let chunks = &chunks;
+                scope.spawn(move || {
+                    chunks
+                        .iter()
+                        .skip(worker)
+                        .step_by(workers)
+                        .all(|(chunk, chunk_comp)| chunk == chunk_comp)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .all(|handle| handle.join().unwrap_or(false))
+    })
+}
+
+fn from_io(path: &Path, error: io::Error) -> DiffError {
+    DiffError::from_io(&path.to_string_lossy(), error)
+}
+
+/// Local paths of both files if both sources read them from the local filesystem
+pub(crate) fn local_paths<F: crate::vfs::DiffFs + ?Sized, C: crate::vfs::DiffFs + ?Sized>(
+    fs: &F,
+    path: &str,
+    fs_comp: &C,
+    path_comp: &str,
+) -> Option<(PathBuf, PathBuf)> {
+    Some((fs.local_path(path)?, fs_comp.local_path(path_comp)?))
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_mapped_files() {
+    let purpose = Path::new("./mocks/dir_one/vlang/purpose/purpose.txt");
+
+    assert!(equal(
+        purpose,
+        Path::new("./mocks/dir_two/vlang/purpose/purpose.txt")
+    )
+    .await
+    .unwrap());
+    assert!(!equal(
+        purpose,
+        Path::new("./mocks/dir_five/vlang/purpose/purpose.txt")
+    )
+    .await
+    .unwrap());
+    assert!(matches!(
+        equal(purpose, Path::new("./mocks/not_found.txt")).await,
+        Err(DiffError::NotFound { .. })
+    ));
+}
+
+#[test]
+fn should_compare_the_chunks_in_parallel() {
+    let content = vec![7u8; CHUNK_SIZE * 3 + 5];
+    let mut content_comp = content.clone();
+
+    assert!(equal_chunks(&content, &content_comp));
+    content_comp[CHUNK_SIZE * 2 + 1] = 0;
+    assert!(!equal_chunks(&content, &content_comp));
+}
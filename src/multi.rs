@@ -0,0 +1,92 @@
+//! Comparison of more than two directories at once, like the replicas of a fleet of servers
+use crate::diff::DirDiff;
+use crate::tree::Tree;
+use crate::vfs::{DiffFs, EntryKind};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Entry which is not equal in all the directories, the directories are identified by
+/// their index in the list compared
+#[derive(Debug, PartialEq, Clone)]
+pub struct MultiDifference {
+    /// path of the entry relative to the root of the directories
+    pub path: String,
+    /// directories which agree between them, every group has the same content, or are
+    /// all directories. The biggest group is the first one
+    pub groups: Vec<Vec<usize>>,
+    /// directories without the entry
+    pub missing: Vec<usize>,
+}
+
+/// What an entry is in one of the directories
+#[derive(PartialEq)]
+enum Variant {
+    Dir,
+    /// target of the symbolic link, None if it can't be read
+    Symlink(Option<String>),
+    /// special file or entry which can't be read, only compared by its kind
+    Special(EntryKind),
+    File(Vec<u8>, Vec<u8>),
+}
+
+/// Compare the trees entry by entry and return the entries which are missing in some of
+/// them or whose content diverges, sorted by path. The files are compared by their digest
+/// computed with `DirDiff::hasher` and by their content if the digests are equal
+pub(crate) async fn compare_many<F: DiffFs + ?Sized>(
+    fs: &F,
+    trees: &[Vec<Tree>],
+    options: &DirDiff,
+) -> Vec<MultiDifference> {
+    let indexes: Vec<BTreeMap<String, &Tree>> = trees
+        .iter()
+        .map(|tree| Tree::iter(tree).collect())
+        .collect();
+    let paths: BTreeSet<&String> = indexes.iter().flat_map(|index| index.keys()).collect();
+    let hasher = options.content_hasher();
+
+    let mut differences: Vec<MultiDifference> = vec![];
+    for path in paths {
+        let mut variants: Vec<(Variant, Vec<usize>)> = vec![];
+        let mut missing: Vec<usize> = vec![];
+        for (position, index) in indexes.iter().enumerate() {
+            let variant = match index.get(path) {
+                None => {
+                    missing.push(position);
+                    continue;
+                }
+                Some(entry) => match entry.kind {
+                    EntryKind::Dir => Variant::Dir,
+                    EntryKind::Symlink | EntryKind::Cycle => {
+                        Variant::Symlink(fs.read_link(&entry.path).await.ok())
+                    }
+                    kind if kind.is_special() || kind == EntryKind::Unreadable => {
+                        Variant::Special(kind)
+                    }
+                    _ => match fs.read(&entry.path).await {
+                        Ok(content) => {
+                            let content = options.normalization.apply(path, &content).into_owned();
+                            Variant::File(hasher.hash(&content), content)
+                        }
+                        Err(_) => Variant::Special(EntryKind::Unreadable),
+                    },
+                },
+            };
+            match variants.iter_mut().find(|(known, _)| *known == variant) {
+                Some((_, group)) => group.push(position),
+                None => variants.push((variant, vec![position])),
+            }
+        }
+
+        if variants.len() > 1 || !missing.is_empty() {
+            let mut groups: Vec<Vec<usize>> =
+                variants.into_iter().map(|(_, group)| group).collect();
+            groups.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+            differences.push(MultiDifference {
+                path: path.clone(),
+                groups,
+                missing,
+            });
+        }
+    }
+
+    differences
+}
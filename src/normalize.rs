@@ -0,0 +1,556 @@
+//! Transformations applied to the content of the files before comparing them, so the
+//! changes which don't matter are not reported as differences
+use crate::lines::is_binary;
+use std::borrow::Cow;
+
+/// Normalizations of the content of the files, all disabled by default. They are applied
+/// only to the text files, the binary ones detected by `lines::is_binary` are compared
+/// by their bytes
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::normalize::Normalization;
+///
+/// let normalization = Normalization {
+///     ignore_trailing_newline: true,
+///     ..Default::default()
+/// };
+///
+/// assert!(normalization.equal("hello.txt", b"Hello world\n", b"Hello world"));
+/// ```
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Normalization {
+    /// ignore the newline at the end of the file, `\n` or `\r\n`, if it has one
+    pub ignore_trailing_newline: bool,
+    /// compare the line endings `\r\n` and `\r` as `\n`
+    pub normalize_eol: bool,
+    /// ignore the spaces and tabs at the end of the lines
+    pub trim_trailing_whitespace: bool,
+    /// compare the text ignoring the case of the letters
+    pub ignore_case: bool,
+    /// strip the comments of the source files whose extension is known, `#` for
+    /// .py or .sh and `//` or `/* */` for .rs or .c, and the lines left empty by them
+    pub strip_comments: bool,
+    /// drop the lines matching any of these regular expressions, like timestamps or
+    /// `Generated on` headers
+    #[cfg(feature = "regex")]
+    pub ignore_line_patterns: Option<LinePatterns>,
+    /// compare the .json files as json values, ignoring the order of the keys and the
+    /// whitespace. The files which aren't valid json are compared by their bytes
+    #[cfg(feature = "json")]
+    pub structural_json: bool,
+    /// compare the .yaml and .yml files as yaml values, ignoring the order of the keys,
+    /// the indentation and the comments. The files which aren't valid yaml, or have keys
+    /// which aren't strings, are compared by their bytes
+    #[cfg(feature = "yaml")]
+    pub structural_yaml: bool,
+    /// compare the .toml files as toml values, ignoring the order of the keys, the
+    /// whitespace and the comments. The files which aren't valid toml are compared by
+    /// their bytes
+    #[cfg(feature = "toml")]
+    pub structural_toml: bool,
+}
+
+/// Regular expressions of `Normalization::ignore_line_patterns`, compiled once
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::normalize::{LinePatterns, Normalization};
+///
+/// let normalization = Normalization {
+///     ignore_line_patterns: Some(LinePatterns::new(&[r"^# Generated on "]).unwrap()),
+///     ..Default::default()
+/// };
+///
+/// assert!(normalization.equal("a.txt", b"# Generated on monday\nHello", b"Hello"));
+/// assert!(LinePatterns::new(&["(unclosed"]).is_err());
+/// ```
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct LinePatterns {
+    patterns: regex::bytes::RegexSet,
+}
+
+#[cfg(feature = "regex")]
+impl LinePatterns {
+    /// Compile the patterns, an error is returned if one is invalid
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, regex::Error> {
+        Ok(LinePatterns {
+            patterns: regex::bytes::RegexSet::new(patterns)?,
+        })
+    }
+
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.patterns.is_match(line)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl PartialEq for LinePatterns {
+    fn eq(&self, other: &Self) -> bool {
+        self.patterns.patterns() == other.patterns.patterns()
+    }
+}
+
+impl Normalization {
+    /// Return true if any normalization is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.ignore_trailing_newline
+            || self.normalize_eol
+            || self.trim_trailing_whitespace
+            || self.ignore_case
+            || self.strip_comments
+            || self.ignores_lines()
+            || self.compares_structure()
+    }
+
+    fn compares_structure(&self) -> bool {
+        #[allow(unused_mut)]
+        let mut enabled = false;
+        #[cfg(feature = "json")]
+        {
+            enabled |= self.structural_json;
+        }
+        #[cfg(feature = "yaml")]
+        {
+            enabled |= self.structural_yaml;
+        }
+        #[cfg(feature = "toml")]
+        {
+            enabled |= self.structural_toml;
+        }
+
+        enabled
+    }
+
+    /// Return the value of the content parsed by the format of the extension of the path,
+    /// if its structural comparison is enabled and the content is valid
+    #[cfg(any(feature = "json", feature = "yaml", feature = "toml"))]
+    fn structure(&self, path: &str, content: &[u8]) -> Option<serde_json::Value> {
+        let extension = path.rsplit_once('.').map(|(_, extension)| extension)?;
+
+        match extension {
+            #[cfg(feature = "json")]
+            "json" if self.structural_json => serde_json::from_slice(content).ok(),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" if self.structural_yaml => serde_yaml::from_slice(content).ok(),
+            #[cfg(feature = "toml")]
+            "toml" if self.structural_toml => std::str::from_utf8(content)
+                .ok()
+                .and_then(|content| toml::from_str(content).ok()),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    fn ignores_lines(&self) -> bool {
+        self.ignore_line_patterns.is_some()
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn ignores_lines(&self) -> bool {
+        false
+    }
+
+    /// Return the content of the file of the path with the normalizations applied, or
+    /// the same content if it is binary
+    pub fn apply<'a>(&self, path: &str, content: &'a [u8]) -> Cow<'a, [u8]> {
+        if !self.is_enabled() || is_binary(content) {
+            return Cow::Borrowed(content);
+        }
+        #[cfg(any(feature = "json", feature = "yaml", feature = "toml"))]
+        if let Some(content) = self.structure(path, content).and_then(canonical) {
+            return Cow::Owned(content);
+        }
+        let content = match CommentStyle::of(path).filter(|_| self.strip_comments) {
+            Some(style) => Cow::Owned(strip_comments(style, content)),
+            None => Cow::Borrowed(content),
+        };
+        #[cfg(feature = "regex")]
+        let content = match &self.ignore_line_patterns {
+            Some(patterns) => Cow::Owned(drop_lines(patterns, &content)),
+            None => content,
+        };
+
+        let content = match self.normalize_eol {
+            true => Cow::Owned(normalize_eol(&content)),
+            false => content,
+        };
+        let content = match self.trim_trailing_whitespace {
+            true => Cow::Owned(trim_trailing_whitespace(&content)),
+            false => content,
+        };
+        let content = match self.ignore_case {
+            true => Cow::Owned(
+                String::from_utf8_lossy(&content)
+                    .to_lowercase()
+                    .into_bytes(),
+            ),
+            false => content,
+        };
+
+        if !self.ignore_trailing_newline {
+            return content;
+        }
+
+        match content {
+            Cow::Borrowed(content) => Cow::Borrowed(trim_trailing_newline(content)),
+            Cow::Owned(content) => Cow::Owned(trim_trailing_newline(&content).to_vec()),
+        }
+    }
+
+    /// Return true if the contents of both files are equal once normalized
+    pub fn equal(&self, path: &str, content: &[u8], content_comp: &[u8]) -> bool {
+        if !self.is_enabled() {
+            return content == content_comp;
+        }
+
+        self.apply(path, content) == self.apply(path, content_comp)
+    }
+}
+
+/// Syntax of the comments of a source file
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum CommentStyle {
+    /// `#` until the end of the line
+    Hash,
+    /// `//` until the end of the line and `/* */` blocks
+    CLike,
+}
+
+impl CommentStyle {
+    fn of(path: &str) -> Option<CommentStyle> {
+        let extension = path.rsplit_once('.').map(|(_, extension)| extension)?;
+
+        match extension {
+            "py" | "sh" | "bash" | "zsh" | "rb" | "pl" | "toml" | "yaml" | "yml" | "r" => {
+                Some(CommentStyle::Hash)
+            }
+            "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "java" | "kt" | "go" | "js"
+            | "jsx" | "ts" | "tsx" | "swift" | "scala" | "v" => Some(CommentStyle::CLike),
+            _ => None,
+        }
+    }
+}
+
+/// Remove the comments out of the string literals, dropping the lines which only had
+/// comments and the whitespace left at the end of the lines with a trailing comment
+fn strip_comments(style: CommentStyle, content: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(content.len());
+    let mut line: Vec<u8> = Vec::new();
+    let mut commented = false;
+    let mut quote: Option<u8> = None;
+    let mut in_block = false;
+    let mut i = 0;
+
+    while i < content.len() {
+        let byte = content[i];
+        let next = content.get(i + 1).copied();
+
+        if byte == b'\n' {
+            if commented {
+                let len = line.trim_ascii_end().len();
+                line.truncate(len);
+            }
+            if !(commented && line.trim_ascii().is_empty()) {
+                stripped.append(&mut line);
+                stripped.push(b'\n');
+            }
+            line.clear();
+            commented = in_block;
+            i += 1;
+            continue;
+        }
+
+        if in_block {
+            if byte == b'*' && next == Some(b'/') {
+                in_block = false;
+                i += 2;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+
+        if let Some(open) = quote {
+            line.push(byte);
+            if byte == b'\\' {
+                if let Some(next) = next.filter(|next| *next != b'\n') {
+                    line.push(next);
+                    i += 1;
+                }
+            } else if byte == open {
+                quote = None;
+            }
+            i += 1;
+            continue;
+        }
+
+        match (style, byte, next) {
+            (CommentStyle::Hash, b'#', _) | (CommentStyle::CLike, b'/', Some(b'/')) => {
+                commented = true;
+                while i < content.len() && content[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            (CommentStyle::CLike, b'/', Some(b'*')) => {
+                commented = true;
+                in_block = true;
+                i += 2;
+            }
+            (CommentStyle::Hash, b'"' | b'\'', _) | (CommentStyle::CLike, b'"', _) => {
+                quote = Some(byte);
+                line.push(byte);
+                i += 1;
+            }
+            _ => {
+                line.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    if commented {
+        let len = line.trim_ascii_end().len();
+        line.truncate(len);
+    }
+    stripped.append(&mut line);
+
+    stripped
+}
+
+/// Remove the lines matching any of the patterns, without their line ending
+#[cfg(feature = "regex")]
+fn drop_lines(patterns: &LinePatterns, content: &[u8]) -> Vec<u8> {
+    content
+        .split_inclusive(|byte| *byte == b'\n')
+        .filter(|line| {
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            !patterns.is_match(line.strip_suffix(b"\r").unwrap_or(line))
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Serialize the value as json with its keys sorted and without whitespace
+#[cfg(any(feature = "json", feature = "yaml", feature = "toml"))]
+fn canonical(value: serde_json::Value) -> Option<Vec<u8>> {
+    fn sort_keys(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<(String, serde_json::Value)> = map.into_iter().collect();
+                entries.sort_by(|(key, _), (key_comp, _)| key.cmp(key_comp));
+                serde_json::Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key, sort_keys(value)))
+                        .collect(),
+                )
+            }
+            serde_json::Value::Array(values) => {
+                serde_json::Value::Array(values.into_iter().map(sort_keys).collect())
+            }
+            value => value,
+        }
+    }
+
+    serde_json::to_vec(&sort_keys(value)).ok()
+}
+
+/// Replace the `\r\n` and the `\r` alone by `\n`
+fn normalize_eol(content: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(content.len());
+    let mut bytes = content.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'\r' {
+            bytes.next_if_eq(&&b'\n');
+            normalized.push(b'\n');
+        } else {
+            normalized.push(byte);
+        }
+    }
+
+    normalized
+}
+
+/// Remove the spaces and tabs before the end of every line, keeping its line ending
+fn trim_trailing_whitespace(content: &[u8]) -> Vec<u8> {
+    let mut trimmed = Vec::with_capacity(content.len());
+    for line in content.split_inclusive(|byte| *byte == b'\n') {
+        let (text, ending): (&[u8], &[u8]) = match line.strip_suffix(b"\r\n") {
+            Some(text) => (text, b"\r\n"),
+            None => match line.strip_suffix(b"\n") {
+                Some(text) => (text, b"\n"),
+                None => (line, b""),
+            },
+        };
+        let len = text.len()
+            - text
+                .iter()
+                .rev()
+                .take_while(|byte| matches!(byte, b' ' | b'\t'))
+                .count();
+        trimmed.extend_from_slice(&text[..len]);
+        trimmed.extend_from_slice(ending);
+    }
+
+    trimmed
+}
+
+fn trim_trailing_newline(content: &[u8]) -> &[u8] {
+    match content.strip_suffix(b"\n") {
+        Some(content) => content.strip_suffix(b"\r").unwrap_or(content),
+        None => content,
+    }
+}
+
+#[test]
+fn should_normalize_the_line_endings_the_whitespace_and_the_case() {
+    let normalization = Normalization {
+        normalize_eol: true,
+        trim_trailing_whitespace: true,
+        ignore_case: true,
+        ..Default::default()
+    };
+
+    assert!(normalization.equal("hello.txt", b"Hello \r\nWorld\t\r", b"hello\nworld\n"));
+    assert!(!normalization.equal("hello.txt", b"Hello\nworld", b"Hello world"));
+    assert_eq!(trim_trailing_whitespace(b"a \r\nb\t\n c "), b"a\r\nb\n c");
+}
+
+#[test]
+fn should_ignore_only_one_trailing_newline() {
+    let normalization = Normalization {
+        ignore_trailing_newline: true,
+        ..Default::default()
+    };
+
+    assert!(normalization.equal("hello.txt", b"Hello world\r\n", b"Hello world"));
+    assert!(!normalization.equal("hello.txt", b"Hello world\n\n", b"Hello world"));
+    assert!(!Normalization::default().equal("hello.txt", b"Hello world\n", b"Hello world"));
+    assert!(!normalization.equal("hello.bin", b"\0\x89\n", b"\0\x89"));
+}
+
+#[test]
+fn should_strip_the_comments_by_extension() {
+    let normalization = Normalization {
+        strip_comments: true,
+        ..Default::default()
+    };
+
+    assert!(normalization.equal(
+        "hello.py",
+        b"# greet\nprint('#1')  # inline\n",
+        b"print('#1')\n"
+    ));
+    assert!(normalization.equal(
+        "hello.rs",
+        b"/* license\n * MIT */\nfn main() { // entry\n    println!(\"//\");\n}\n",
+        b"fn main() {\n    println!(\"//\");\n}\n"
+    ));
+    assert!(!normalization.equal("hello.rs", b"let a = 1; // one", b"let a = 2;"));
+    assert!(!normalization.equal("hello.txt", b"# title\nHello", b"Hello"));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn should_drop_the_lines_matching_the_patterns() {
+    let normalization = Normalization {
+        ignore_line_patterns: Some(
+            LinePatterns::new(&[r"^// Generated on ", r"build-\d+$"]).unwrap(),
+        ),
+        ..Default::default()
+    };
+
+    assert!(normalization.equal(
+        "version.rs",
+        b"// Generated on 2020-05-01\nconst VERSION: &str = \"1.0\";\n// build-12\r\n",
+        b"// Generated on 2021-01-09\nconst VERSION: &str = \"1.0\";\n// build-345\r\n"
+    ));
+    assert!(!normalization.equal(
+        "version.rs",
+        b"// Generated on 2020-05-01\nconst VERSION: &str = \"1.0\";\n",
+        b"// Generated on 2021-01-09\nconst VERSION: &str = \"2.0\";\n"
+    ));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn should_compare_the_json_files_by_value() {
+    let normalization = Normalization {
+        structural_json: true,
+        ..Default::default()
+    };
+
+    assert!(normalization.equal(
+        "package.json",
+        br#"{"name": "spielrs", "deps": {"b": 2, "a": [1, 2]}}"#,
+        b"{\n  \"deps\": {\"a\": [1,2], \"b\": 2},\n  \"name\": \"spielrs\"\n}\n"
+    ));
+    assert!(!normalization.equal("package.json", br#"{"a": [1, 2]}"#, br#"{"a": [2, 1]}"#));
+    assert!(!normalization.equal("package.txt", br#"{"a":1,"b":2}"#, br#"{"b":2,"a":1}"#));
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn should_compare_the_yaml_files_by_value() {
+    let normalization = Normalization {
+        structural_yaml: true,
+        ..Default::default()
+    };
+
+    assert!(normalization.equal(
+        "config.yml",
+        b"server:\n  port: 80\n  hosts: [a, b]\nname: spielrs\n",
+        b"# config\nname: spielrs\nserver:\n    hosts:\n      - a\n      - b\n    port: 80\n"
+    ));
+    assert!(!normalization.equal("config.yaml", b"port: 80\n", b"port: \"80\"\n"));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn should_compare_the_toml_files_by_value() {
+    let normalization = Normalization {
+        structural_toml: true,
+        ..Default::default()
+    };
+
+    assert!(normalization.equal(
+        "Cargo.toml",
+        b"[package]\nname = \"spielrs\"\nedition = \"2018\"\n",
+        b"# manifest\n[package]\nedition=\"2018\"\n  name   = \"spielrs\"\n"
+    ));
+    assert!(!normalization.equal("Cargo.toml", b"a = 1\n", b"a = 1.0\n"));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_the_binary_files_differing_in_the_normalized_bytes() {
+    use crate::diff::DirDiff;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/logo.png", &b"\x89PNG\r\n\x1a\n\0\0IHDR \r\n"[..])
+        .file("dir_one/notes.txt", "Hello world\r\n")
+        .file("dir_two/logo.png", &b"\x89PNG\n\x1a\n\0\0IHDR\n"[..])
+        .file("dir_two/notes.txt", "hello world\n")
+        .build();
+    let options = |file: &str| DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        excluding: Some(vec![file.to_string()]),
+        normalization: Normalization {
+            ignore_trailing_newline: true,
+            normalize_eol: true,
+            trim_trailing_whitespace: true,
+            ignore_case: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    assert!(!crate::dir_diff_with(&fs, &fs, options("logo.png")).await);
+    assert!(crate::dir_diff_with(&fs, &fs, options("notes.txt")).await);
+}
@@ -0,0 +1,130 @@
+//! Patterns of paths with the anchoring of gitignore, used by `DirDiff::excluding_patterns`
+
+/// Glob sets of `DirDiff::excluding_globs`
+#[cfg(feature = "globset")]
+pub use globset;
+
+/// Regular expression sets of `DirDiff::excluding_regex`
+#[cfg(feature = "regex")]
+pub use regex;
+
+/// Pattern matched against the path of an entry relative to the root of the tree, like
+/// the lines of a `.gitignore`:
+///
+/// - `*` matches anything but `/` and `?` any character but `/`
+/// - `**` matches any number of directories, `**/build` is `build` at any depth and
+///   `logs/**` everything inside of `logs`
+/// - a pattern with a `/` at the start or in the middle is anchored to the root, `/build`
+///   only matches the `build` of the root and `docs/*.md` only the ones of `docs`
+/// - a pattern without it matches at any depth, `build` is `build` in any directory
+/// - a `/` at the end matches only directories, `build/` doesn't match a file `build`
+///
+/// The negations with `!` and the character classes are not supported
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::pattern::PathPattern;
+///
+/// let root_only = PathPattern::new("/build");
+/// assert!(root_only.matches("build", true));
+/// assert!(!root_only.matches("vlang/build", true));
+///
+/// let dirs = PathPattern::new("build/");
+/// assert!(dirs.matches("vlang/build", true));
+/// assert!(!dirs.matches("vlang/build", false));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct PathPattern {
+    segments: Vec<String>,
+    dir_only: bool,
+}
+
+impl PathPattern {
+    /// Parse the pattern
+    pub fn new(pattern: &str) -> Self {
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        let anchored = pattern.contains('/');
+        let mut segments: Vec<String> = if anchored {
+            vec![]
+        } else {
+            vec!["**".to_string()]
+        };
+        segments.extend(
+            pattern
+                .trim_start_matches('/')
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(String::from),
+        );
+
+        PathPattern { segments, dir_only }
+    }
+
+    /// Return true if the path relative to the root, separated by `/`, matches
+    pub fn matches(&self, rel_path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let path: Vec<&str> = rel_path.split('/').collect();
+
+        matches_segments(&self.segments, &path)
+    }
+}
+
+fn matches_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        // at the end it matches the content, not the directory itself
+        Some((first, rest)) if first == "**" && rest.is_empty() => !path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skipped| matches_segments(rest, &path[skipped..]))
+        }
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path)) => {
+                let first: Vec<char> = first.chars().collect();
+                let segment: Vec<char> = segment.chars().collect();
+                matches_wildcard(&first, &segment) && matches_segments(rest, path)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match a segment with `*` and `?`
+fn matches_wildcard(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', rest)), _) => {
+            (0..=name.len()).any(|skipped| matches_wildcard(rest, &name[skipped..]))
+        }
+        (Some(('?', rest)), Some((_, name))) => matches_wildcard(rest, name),
+        (Some((pattern_char, rest)), Some((name_char, name))) if pattern_char == name_char => {
+            matches_wildcard(rest, name)
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn should_match_like_gitignore() {
+    let anywhere = PathPattern::new("build");
+    assert!(anywhere.matches("build", false));
+    assert!(anywhere.matches("a/b/build", true));
+    assert_eq!(anywhere, PathPattern::new("**/build"));
+
+    let anchored = PathPattern::new("docs/*.md");
+    assert!(anchored.matches("docs/README.md", false));
+    assert!(!anchored.matches("vlang/docs/README.md", false));
+    assert!(!anchored.matches("docs/api/README.md", false));
+
+    let content = PathPattern::new("logs/**");
+    assert!(content.matches("logs/2021/a.log", false));
+    assert!(!content.matches("logs", true));
+
+    let nested = PathPattern::new("a/**/b?.txt");
+    assert!(nested.matches("a/b1.txt", false));
+    assert!(nested.matches("a/x/y/b2.txt", false));
+    assert!(!nested.matches("a/b10.txt", false));
+}
@@ -0,0 +1,124 @@
+//! Ascii tree renderer
+use super::{DiffReport, Difference};
+use std::collections::BTreeMap;
+
+/// Entry of the merged tree, the directories containing differences have children
+#[derive(Default)]
+struct Node<'a> {
+    /// marker of the difference and a note after the name, `None` if the entry is only
+    /// the parent of differences
+    mark: Option<(char, String)>,
+    children: BTreeMap<&'a str, Node<'a>>,
+}
+
+fn mark(difference: &Difference) -> (char, String) {
+    match difference {
+        Difference::Added(_) => ('+', String::new()),
+        Difference::Removed(_) => ('-', String::new()),
+        Difference::Modified(_) => ('~', String::new()),
+        Difference::Renamed { from, .. } => ('+', format!(" (renamed from {})", from)),
+        Difference::Relinked(_) => ('~', " (relinked)".to_string()),
+    }
+}
+
+fn render_node(name: &str, node: &Node, prefix: &str, last: bool, lines: &mut String) {
+    let (branch, indent) = if last {
+        ("`-- ", "    ")
+    } else {
+        ("|-- ", "|   ")
+    };
+    let (marker, note) = match &node.mark {
+        Some((marker, note)) => (format!("{} ", marker), note.as_str()),
+        None => (String::new(), ""),
+    };
+    let slash = if node.children.is_empty() { "" } else { "/" };
+    lines.push_str(&format!(
+        "{}{}{}{}{}{}\n",
+        prefix, branch, marker, name, slash, note
+    ));
+
+    let prefix = format!("{}{}", prefix, indent);
+    for (index, (child_name, child)) in node.children.iter().enumerate() {
+        let last = index + 1 == node.children.len();
+        render_node(child_name, child, &prefix, last, lines);
+    }
+}
+
+/// Render the report as a tree of the entries which differ and of their parents, like
+/// `tree` with the ascii charset. The entries are marked with `+` if they are only in
+/// `dir_comp`, `-` if they are only in `dir` and `~` if they were modified
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::report::{to_ascii_tree, DiffReport, Difference};
+///
+/// let report = DiffReport {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_four".to_string(),
+///     differences: vec![
+///         Difference::Added("hello.js".to_string()),
+///         Difference::Modified("vlang/hello.v".to_string()),
+///         Difference::Removed("vlang/purpose".to_string()),
+///     ],
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     to_ascii_tree(&report),
+///     "./mocks/dir_one -> ./mocks/dir_four\n\
+///      |-- + hello.js\n\
+///      `-- vlang/\n    \
+///          |-- ~ hello.v\n    \
+///          `-- - purpose\n"
+/// );
+/// ```
+pub fn to_ascii_tree(report: &DiffReport) -> String {
+    let mut root = Node::default();
+    for difference in &report.differences {
+        let mut node = &mut root;
+        for segment in difference.path().split(['/', '\\']) {
+            node = node.children.entry(segment).or_default();
+        }
+        node.mark = Some(mark(difference));
+    }
+
+    let mut lines = format!("{} -> {}\n", report.dir, report.dir_comp);
+    if report.is_equal() {
+        lines.push_str("`-- no differences\n");
+    }
+    for (index, (name, node)) in root.children.iter().enumerate() {
+        let last = index + 1 == root.children.len();
+        render_node(name, node, "", last, &mut lines);
+    }
+
+    lines
+}
+
+#[test]
+fn should_render_the_tree_of_differences() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![
+            Difference::Modified("a/b/c.txt".to_string()),
+            Difference::Renamed {
+                from: "a/old.txt".to_string(),
+                to: "a/new.txt".to_string(),
+                similarity: 1.0,
+            },
+            Difference::Relinked("z.bin".to_string()),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        to_ascii_tree(&report),
+        "dir_one -> dir_two\n\
+         |-- a/\n\
+         |   |-- b/\n\
+         |   |   `-- ~ c.txt\n\
+         |   `-- + new.txt (renamed from a/old.txt)\n\
+         `-- ~ z.bin (relinked)\n"
+    );
+}
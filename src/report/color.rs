@@ -0,0 +1,81 @@
+//! Colored terminal renderer
+use super::{DiffReport, Difference};
+use std::env;
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Return false if the `NO_COLOR` environment variable is set and not empty,
+/// see [no-color.org](https://no-color.org)
+pub fn color_enabled() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Render the report with the same lines than `Display`, the added entries in green,
+/// the removed in red, the modified in yellow, the renamed in blue and the relinked in cyan
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::report::{to_colored_text, DiffReport, Difference};
+///
+/// let report = DiffReport {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_three".to_string(),
+///     differences: vec![Difference::Added("hello.js".to_string())],
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     to_colored_text(&report),
+///     "\x1b[32mOnly in ./mocks/dir_three: hello.js\x1b[0m\n"
+/// );
+/// ```
+pub fn to_colored_text(report: &DiffReport) -> String {
+    let mut text = String::new();
+
+    for difference in &report.differences {
+        let color = match difference {
+            Difference::Added(_) => GREEN,
+            Difference::Removed(_) => RED,
+            Difference::Modified(_) => YELLOW,
+            Difference::Renamed { .. } => BLUE,
+            Difference::Relinked(_) => CYAN,
+        };
+        text.push_str(&format!("{}{}{}\n", color, report.line(difference), RESET));
+    }
+
+    text
+}
+
+/// Render the report colored unless `NO_COLOR` is set, in that case it is the same than `Display`
+pub fn to_terminal(report: &DiffReport) -> String {
+    if color_enabled() {
+        to_colored_text(report)
+    } else {
+        report.to_string()
+    }
+}
+
+#[test]
+fn should_color_by_kind() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_four".to_string(),
+        differences: vec![
+            Difference::Modified("vlang/hello.v".to_string()),
+            Difference::Removed("vlang/purpose".to_string()),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        to_colored_text(&report),
+        "\x1b[33mFiles dir_one/vlang/hello.v and dir_four/vlang/hello.v differ\x1b[0m\n\
+         \x1b[31mOnly in dir_one/vlang: purpose\x1b[0m\n"
+    );
+}
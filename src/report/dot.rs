@@ -0,0 +1,162 @@
+//! Graphviz DOT renderer
+use super::{DiffReport, Difference};
+use std::collections::BTreeMap;
+
+/// Entry of the tree of differences, the directories containing differences have children
+#[derive(Default)]
+struct Node<'a> {
+    kind: Option<&'static str>,
+    children: BTreeMap<&'a str, Node<'a>>,
+}
+
+fn kind(difference: &Difference) -> &'static str {
+    match difference {
+        Difference::Added(_) => "added",
+        Difference::Removed(_) => "removed",
+        Difference::Modified(_) => "modified",
+        Difference::Renamed { .. } => "renamed",
+        Difference::Relinked(_) => "relinked",
+    }
+}
+
+/// Color of the kind of difference, the same than in the html page
+fn color(kind: &str) -> &'static str {
+    match kind {
+        "added" => "#22863a",
+        "removed" => "#cb2431",
+        "modified" => "#b08800",
+        "renamed" => "#0366d6",
+        _ => "#1b7c83",
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn render_node(id: &str, name: &str, node: &Node, dot: &mut String) {
+    let label = if node.children.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/", name)
+    };
+    match node.kind {
+        Some(kind) => dot.push_str(&format!(
+            "  {} [label={}, tooltip={}, color=\"{color}\", fontcolor=\"{color}\", penwidth=2];\n",
+            quote(id),
+            quote(&label),
+            quote(kind),
+            color = color(kind)
+        )),
+        None => dot.push_str(&format!(
+            "  {} [label={}, shape=folder];\n",
+            quote(id),
+            quote(&label)
+        )),
+    }
+    render_children(id, node, dot);
+}
+
+fn render_children(id: &str, node: &Node, dot: &mut String) {
+    for (child_name, child) in &node.children {
+        let child_id = format!("{}/{}", id, child_name);
+        dot.push_str(&format!("  {} -> {};\n", quote(id), quote(&child_id)));
+        render_node(&child_id, child_name, child, dot);
+    }
+}
+
+impl DiffReport {
+    /// Render the report as a Graphviz DOT graph of the entries which differ and of their
+    /// parents, colored by the kind of difference like the html page. The renamed files
+    /// are also shown in their old path, linked to the new one with a dashed edge
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::report::{DiffReport, Difference};
+    ///
+    /// let report = DiffReport {
+    ///     dir: "./mocks/dir_one".to_string(),
+    ///     dir_comp: "./mocks/dir_four".to_string(),
+    ///     differences: vec![Difference::Modified("vlang/hello.v".to_string())],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// // render it with `dot -Tsvg diff.dot -o diff.svg`
+    /// std::fs::write(std::env::temp_dir().join("diff.dot"), report.to_dot()).unwrap();
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut root = Node::default();
+        let mut renames: Vec<(&str, &str)> = vec![];
+        for difference in &self.differences {
+            if let Difference::Renamed { from, to, .. } = difference {
+                let mut node = &mut root;
+                for segment in from.split(['/', '\\']) {
+                    node = node.children.entry(segment).or_default();
+                }
+                node.kind.get_or_insert("renamed");
+                renames.push((from, to));
+            }
+            let mut node = &mut root;
+            for segment in difference.path().split(['/', '\\']) {
+                node = node.children.entry(segment).or_default();
+            }
+            node.kind = Some(kind(difference));
+        }
+
+        let mut dot = String::from(
+            "digraph diff {\n  rankdir=LR;\n  node [shape=box, fontname=\"sans-serif\"];\n",
+        );
+        // the root is `.` so the ids of the entries are their paths prefixed with `./`
+        dot.push_str(&format!(
+            "  \".\" [label={}, shape=folder];\n",
+            quote(&format!("{} -> {}", self.dir, self.dir_comp))
+        ));
+        render_children(".", &root, &mut dot);
+        for (from, to) in renames {
+            let id = |path: &str| quote(&format!("./{}", path.replace('\\', "/")));
+            dot.push_str(&format!(
+                "  {} -> {} [style=dashed, color=\"{}\", label=\"renamed\"];\n",
+                id(from),
+                id(to),
+                color("renamed")
+            ));
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+#[test]
+fn should_render_the_dot_graph() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_\"two\"".to_string(),
+        differences: vec![
+            Difference::Added("hello.js".to_string()),
+            Difference::Renamed {
+                from: "vlang/old.v".to_string(),
+                to: "vlang/new.v".to_string(),
+                similarity: 1.0,
+            },
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        report.to_dot(),
+        "digraph diff {\n  rankdir=LR;\n  node [shape=box, fontname=\"sans-serif\"];\n  \
+         \".\" [label=\"dir_one -> dir_\\\"two\\\"\", shape=folder];\n  \
+         \".\" -> \"./hello.js\";\n  \
+         \"./hello.js\" [label=\"hello.js\", tooltip=\"added\", color=\"#22863a\", fontcolor=\"#22863a\", penwidth=2];\n  \
+         \".\" -> \"./vlang\";\n  \
+         \"./vlang\" [label=\"vlang/\", shape=folder];\n  \
+         \"./vlang\" -> \"./vlang/new.v\";\n  \
+         \"./vlang/new.v\" [label=\"new.v\", tooltip=\"renamed\", color=\"#0366d6\", fontcolor=\"#0366d6\", penwidth=2];\n  \
+         \"./vlang\" -> \"./vlang/old.v\";\n  \
+         \"./vlang/old.v\" [label=\"old.v\", tooltip=\"renamed\", color=\"#0366d6\", fontcolor=\"#0366d6\", penwidth=2];\n  \
+         \"./vlang/old.v\" -> \"./vlang/new.v\" [style=dashed, color=\"#0366d6\", label=\"renamed\"];\n\
+         }\n"
+    );
+}
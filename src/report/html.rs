@@ -0,0 +1,234 @@
+//! Standalone html renderer
+use super::{DiffReport, Difference};
+use crate::lines::{Hunk, HunkLine};
+use std::collections::BTreeMap;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+ul{list-style:none;padding-left:1.2em}\
+summary{cursor:pointer}\
+.added{color:#22863a}.removed{color:#cb2431}.modified{color:#b08800}.renamed{color:#0366d6}.relinked{color:#1b7c83}\
+.kind{font-size:.8em;margin-left:.5em}\
+.trees{display:flex;gap:2em}.trees section{flex:1;min-width:0}\
+table.diff{border-collapse:collapse;font-family:monospace;width:100%;margin-bottom:1em}\
+.diff td{padding:0 .5em;white-space:pre-wrap}.diff .number{color:#6a737d;text-align:right;width:1%}\
+.diff .hunk td{color:#6a737d;background:#f1f8ff}.diff .add{background:#e6ffed}.diff .del{background:#ffeef0}";
+
+/// Entry of the tree of differences, the directories containing differences have children
+#[derive(Default)]
+struct Node<'a> {
+    kind: Option<&'static str>,
+    children: BTreeMap<&'a str, Node<'a>>,
+}
+
+fn kind(difference: &Difference) -> &'static str {
+    match difference {
+        Difference::Added(_) => "added",
+        Difference::Removed(_) => "removed",
+        Difference::Modified(_) => "modified",
+        Difference::Renamed { .. } => "renamed",
+        Difference::Relinked(_) => "relinked",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_node(name: &str, node: &Node, html: &mut String) {
+    let class = node.kind.unwrap_or("changed");
+    let label = match node.kind {
+        Some(kind) => format!(r#"<span class="kind">{}</span>"#, kind),
+        None => String::new(),
+    };
+
+    if node.children.is_empty() {
+        html.push_str(&format!(
+            r#"<li class="{}">{}{}</li>"#,
+            class,
+            escape(name),
+            label
+        ));
+    } else {
+        html.push_str(&format!(
+            r#"<li><details open><summary class="{}">{}/{}</summary><ul>"#,
+            class,
+            escape(name),
+            label
+        ));
+        for (child_name, child) in &node.children {
+            render_node(child_name, child, html);
+        }
+        html.push_str("</ul></details></li>");
+    }
+}
+
+/// Tree of the entries of one side which differ, with `path` the path of the difference
+/// on that side if it has one
+fn side_tree<'a>(report: &'a DiffReport, path: fn(&'a Difference) -> Option<&'a str>) -> Node<'a> {
+    let mut root = Node::default();
+    for difference in &report.differences {
+        if let Some(path) = path(difference) {
+            let mut node = &mut root;
+            for segment in path.split(['/', '\\']) {
+                node = node.children.entry(segment).or_default();
+            }
+            node.kind = Some(kind(difference));
+        }
+    }
+
+    root
+}
+
+fn render_side(title: &str, root: &Node, html: &mut String) {
+    html.push_str(&format!("<section><h2>{}</h2>", escape(title)));
+    if root.children.is_empty() {
+        html.push_str("<p>No differences</p>");
+    } else {
+        html.push_str("<ul>");
+        for (name, node) in &root.children {
+            render_node(name, node, html);
+        }
+        html.push_str("</ul>");
+    }
+    html.push_str("</section>");
+}
+
+/// Table of the lines of the hunks, numbered in both files
+fn render_hunks(path: &str, hunks: &[Hunk], html: &mut String) {
+    html.push_str(&format!(
+        r#"<details open><summary>{}</summary><table class="diff">"#,
+        escape(path)
+    ));
+    for hunk in hunks {
+        html.push_str(&format!(
+            r#"<tr class="hunk"><td class="number"></td><td class="number"></td><td>{}</td></tr>"#,
+            escape(&hunk.header())
+        ));
+        let (mut line, mut line_comp) = (hunk.start + 1, hunk.start_comp + 1);
+        for hunk_line in &hunk.lines {
+            let (class, number, number_comp, prefix, text) = match hunk_line {
+                HunkLine::Context(text) => ("context", Some(line), Some(line_comp), ' ', text),
+                HunkLine::Removed(text) => ("del", Some(line), None, '-', text),
+                HunkLine::Added(text) => ("add", None, Some(line_comp), '+', text),
+            };
+            line += number.is_some() as usize;
+            line_comp += number_comp.is_some() as usize;
+            let cell = |number: Option<usize>| number.map(|n| n.to_string()).unwrap_or_default();
+            html.push_str(&format!(
+                r#"<tr class="{}"><td class="number">{}</td><td class="number">{}</td><td>{}{}</td></tr>"#,
+                class,
+                cell(number),
+                cell(number_comp),
+                prefix,
+                escape(text.trim_end_matches(['\r', '\n']))
+            ));
+        }
+    }
+    html.push_str("</table></details>");
+}
+
+/// Render the report as a standalone html page. The entries which differ are shown
+/// side by side in the trees of both directories, inside of collapsible directories,
+/// followed by the lines changed in the files compared with `DirDiff::report_text_diffs`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::report::{to_html, DiffReport, Difference};
+///
+/// let report = DiffReport {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_four".to_string(),
+///     differences: vec![Difference::Modified("vlang/purpose/purpose.txt".to_string())],
+///     ..Default::default()
+/// };
+///
+/// std::fs::write(std::env::temp_dir().join("report.html"), to_html(&report)).unwrap();
+/// ```
+pub fn to_html(report: &DiffReport) -> String {
+    let mut body = String::new();
+    if report.is_equal() {
+        body.push_str("<p>No differences</p>");
+    } else {
+        let tree = side_tree(report, |difference| match difference {
+            Difference::Added(_) => None,
+            Difference::Renamed { from, .. } => Some(from),
+            difference => Some(difference.path()),
+        });
+        let tree_comp = side_tree(report, |difference| match difference {
+            Difference::Removed(_) => None,
+            difference => Some(difference.path()),
+        });
+        body.push_str(r#"<div class="trees">"#);
+        render_side(&report.dir, &tree, &mut body);
+        render_side(&report.dir_comp, &tree_comp, &mut body);
+        body.push_str("</div>");
+    }
+    if !report.text_diffs.is_empty() {
+        body.push_str("<h2>Changes</h2>");
+        for (path, hunks) in &report.text_diffs {
+            render_hunks(path, hunks, &mut body);
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <style>{style}</style></head><body><h1>{title}</h1>\
+         <p>{count} difference(s)</p>{body}</body></html>\n",
+        title = escape(&format!("{} vs {}", report.dir, report.dir_comp)),
+        style = STYLE,
+        count = report.differences.len(),
+        body = body
+    )
+}
+
+#[test]
+fn should_render_nested_differences() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_<four>".to_string(),
+        differences: vec![
+            Difference::Added("hello.js".to_string()),
+            Difference::Modified("vlang/hello.v".to_string()),
+            Difference::Removed("vlang/purpose".to_string()),
+        ],
+        ..Default::default()
+    };
+    let html = to_html(&report);
+
+    assert!(html.contains("<title>dir_one vs dir_&lt;four&gt;</title>"));
+    assert!(html.contains(r#"<li class="added">hello.js<span class="kind">added</span></li>"#));
+    assert!(html.contains(
+        r#"<li><details open><summary class="changed">vlang/</summary><ul><li class="modified">hello.v<span class="kind">modified</span></li><li class="removed">purpose<span class="kind">removed</span></li></ul></details></li>"#
+    ));
+}
+
+#[test]
+fn should_render_the_lines_changed() {
+    let mut report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![Difference::Modified("hello.v".to_string())],
+        ..Default::default()
+    };
+    report.text_diffs.insert(
+        "hello.v".to_string(),
+        crate::lines::hunks(
+            "fn main() {\n}\n",
+            "fn main() {\n\tprintln('<hello>')\n}\n",
+            3,
+        ),
+    );
+    let html = to_html(&report);
+
+    assert!(html.contains(
+        r#"<div class="trees"><section><h2>dir_one</h2><ul><li class="modified">hello.v"#
+    ));
+    assert!(html.contains(r#"<tr class="hunk"><td class="number"></td><td class="number"></td><td>@@ -1,2 +1,3 @@</td></tr>"#));
+    assert!(html.contains(
+        r#"<tr class="add"><td class="number"></td><td class="number">2</td><td>+	println('&lt;hello&gt;')</td></tr><tr class="context"><td class="number">2</td><td class="number">3</td><td> }</td></tr>"#
+    ));
+}
@@ -0,0 +1,251 @@
+//! Json renderer
+use super::{DiffReport, DiffStats, Difference};
+use crate::diff::FileDiff;
+use crate::tree::TreeStats;
+use crate::vfs;
+
+/// Escape a string as a json string literal
+pub(crate) fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+fn tree_stats_json(stats: &TreeStats) -> String {
+    format!(
+        r#"{{"files":{},"dirs":{},"bytes":{}}}"#,
+        stats.files, stats.dirs, stats.bytes
+    )
+}
+
+fn stats_json(stats: &DiffStats) -> String {
+    format!(
+        r#"{{"tree":{},"tree_comp":{},"bytes_compared":{},"added":{},"removed":{},"modified":{},"renamed":{},"relinked":{}}}"#,
+        tree_stats_json(&stats.tree),
+        tree_stats_json(&stats.tree_comp),
+        stats.bytes_compared,
+        stats.added,
+        stats.removed,
+        stats.modified,
+        stats.renamed,
+        stats.relinked
+    )
+}
+
+/// Render the report as a json object
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::report::{to_json, DiffReport, Difference};
+///
+/// let report = DiffReport {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_three".to_string(),
+///     differences: vec![Difference::Added("hello.js".to_string())],
+///     ..Default::default()
+/// };
+///
+/// assert!(to_json(&report).starts_with(
+///     r#"{"dir":"./mocks/dir_one","dir_comp":"./mocks/dir_three","equal":false,"differences":[{"kind":"added","path":"hello.js"}],"stats":{"#
+/// ));
+/// ```
+pub fn to_json(report: &DiffReport) -> String {
+    let differences: Vec<String> = report
+        .differences
+        .iter()
+        .map(|difference| {
+            let path = vfs::path::normalize_separators(difference.path());
+            let kind = match difference {
+                Difference::Added(_) => "added",
+                Difference::Removed(_) => "removed",
+                Difference::Modified(_) => "modified",
+                Difference::Relinked(_) => "relinked",
+                Difference::Renamed {
+                    from, similarity, ..
+                } => {
+                    return format!(
+                        r#"{{"kind":"renamed","path":{},"from":{},"similarity":{}}}"#,
+                        json_string(&path),
+                        json_string(from),
+                        similarity
+                    )
+                }
+            };
+            match report.changes.get(difference.path()) {
+                Some(change) => format!(
+                    r#"{{"kind":"{}","path":{},"changed_bytes":{},"len_comp":{}}}"#,
+                    kind,
+                    json_string(&path),
+                    change.changed_bytes,
+                    change.len_comp
+                ),
+                None => format!(r#"{{"kind":"{}","path":{}}}"#, kind, json_string(&path)),
+            }
+        })
+        .collect();
+
+    let digests: Vec<String> = report
+        .digests
+        .iter()
+        .map(|(path, digest)| {
+            format!(
+                r#"{}:{{"digest":"{}","digest_comp":"{}"}}"#,
+                json_string(path),
+                digest.digest,
+                digest.digest_comp
+            )
+        })
+        .collect();
+    let capabilities: Vec<String> = report
+        .capabilities
+        .iter()
+        .map(|(path, change)| {
+            format!(
+                r#"{}:{{"capabilities":{},"capabilities_comp":{}}}"#,
+                json_string(path),
+                json_string(&change.capabilities.to_string()),
+                json_string(&change.capabilities_comp.to_string())
+            )
+        })
+        .collect();
+    let streams: Vec<String> = report
+        .streams
+        .iter()
+        .map(|(path, names)| {
+            let names: Vec<String> = names.iter().map(|name| json_string(name)).collect();
+            format!("{}:[{}]", json_string(path), names.join(","))
+        })
+        .collect();
+    let content_types: Vec<String> = report
+        .content_types
+        .iter()
+        .map(|(path, content_type)| format!("{}:{}", json_string(path), json_string(content_type)))
+        .collect();
+
+    let special_files: Vec<String> = report
+        .special_files
+        .iter()
+        .map(|path| json_string(path))
+        .collect();
+    let unreadable: Vec<String> = report
+        .unreadable
+        .iter()
+        .map(|path| json_string(path))
+        .collect();
+    let errors: Vec<String> = report
+        .errors
+        .iter()
+        .map(|error| {
+            format!(
+                r#"{{"path":{},"error":{}}}"#,
+                json_string(error.path()),
+                json_string(&error.to_string())
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{"dir":{},"dir_comp":{},"equal":{},"differences":[{}],"stats":{}{}{}{}{}{}{}{}{}}}"#,
+        json_string(&report.dir),
+        json_string(&report.dir_comp),
+        report.is_equal(),
+        differences.join(","),
+        stats_json(&report.stats),
+        if digests.is_empty() {
+            String::new()
+        } else {
+            format!(r#","digests":{{{}}}"#, digests.join(","))
+        },
+        if capabilities.is_empty() {
+            String::new()
+        } else {
+            format!(r#","capabilities":{{{}}}"#, capabilities.join(","))
+        },
+        if streams.is_empty() {
+            String::new()
+        } else {
+            format!(r#","streams":{{{}}}"#, streams.join(","))
+        },
+        if content_types.is_empty() {
+            String::new()
+        } else {
+            format!(r#","content_types":{{{}}}"#, content_types.join(","))
+        },
+        if special_files.is_empty() {
+            String::new()
+        } else {
+            format!(r#","special_files":[{}]"#, special_files.join(","))
+        },
+        if unreadable.is_empty() {
+            String::new()
+        } else {
+            format!(r#","unreadable":[{}]"#, unreadable.join(","))
+        },
+        if errors.is_empty() {
+            String::new()
+        } else {
+            format!(r#","errors":[{}]"#, errors.join(","))
+        },
+        if report.truncated {
+            r#","truncated":true"#
+        } else {
+            ""
+        }
+    )
+}
+
+/// Render the result of a file comparison as a json object
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{diff::FileDiff, report::file_to_json};
+///
+/// let files = FileDiff {
+///     file: "./mocks/dir_one/hello.txt".to_string(),
+///     file_comp: "./mocks/dir_two/hello.txt".to_string(),
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     file_to_json(&files, true),
+///     r#"{"file":"./mocks/dir_one/hello.txt","file_comp":"./mocks/dir_two/hello.txt","equal":true}"#
+/// );
+/// ```
+pub fn file_to_json(file_diff: &FileDiff, equal: bool) -> String {
+    format!(
+        r#"{{"file":{},"file_comp":{},"equal":{}}}"#,
+        json_string(&file_diff.file),
+        json_string(&file_diff.file_comp),
+        equal
+    )
+}
+
+#[test]
+fn should_escape_json_strings() {
+    let report = DiffReport {
+        dir: "C:\\dir \"one\"".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        to_json(&report),
+        r#"{"dir":"C:\\dir \"one\"","dir_comp":"dir_two","equal":true,"differences":[],"stats":{"tree":{"files":0,"dirs":0,"bytes":0},"tree_comp":{"files":0,"dirs":0,"bytes":0},"bytes_compared":0,"added":0,"removed":0,"modified":0,"renamed":0,"relinked":0}}"#
+    );
+}
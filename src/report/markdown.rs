@@ -0,0 +1,211 @@
+//! Markdown renderer
+use super::{DiffReport, Difference};
+use crate::lines::HunkLine;
+
+/// Code span of the text, escaping the pipes so it can be in a table cell
+fn code(text: &str) -> String {
+    let text = text.replace('|', "\\|");
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// Fence longer than any run of backticks of the content
+fn fence(content: &str) -> String {
+    let mut fence = "```".to_string();
+    while content.contains(&fence) {
+        fence.push('`');
+    }
+
+    fence
+}
+
+fn table(title: &str, header: &[&str], rows: &[Vec<String>], markdown: &mut String) {
+    if rows.is_empty() {
+        return;
+    }
+    markdown.push_str(&format!("\n### {}\n\n", title));
+    markdown.push_str(&format!("| {} |\n", header.join(" | ")));
+    markdown.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+    for row in rows {
+        markdown.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+}
+
+/// Render the report as Markdown, to post it in a pull request or a chat. The differences
+/// are in a table by kind and the lines changed in the files compared with
+/// `DirDiff::report_text_diffs` are in collapsible sections
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::report::{to_markdown, DiffReport, Difference};
+///
+/// let report = DiffReport {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_three".to_string(),
+///     differences: vec![Difference::Added("hello.js".to_string())],
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     to_markdown(&report),
+///     "## Differences between `./mocks/dir_one` and `./mocks/dir_three`\n\n\
+///      1 difference: 1 added, 0 removed, 0 modified, 0 renamed\n\n### Added\n\n\
+///      | Path |\n\
+///      | --- |\n\
+///      | `hello.js` |\n"
+/// );
+/// ```
+pub fn to_markdown(report: &DiffReport) -> String {
+    if report.is_equal() {
+        return format!(
+            "No differences between {} and {}\n",
+            code(&report.dir),
+            code(&report.dir_comp)
+        );
+    }
+
+    let paths = |kind: fn(&Difference) -> bool| -> Vec<Vec<String>> {
+        report
+            .differences
+            .iter()
+            .filter(|difference| kind(difference))
+            .map(|difference| vec![code(difference.path())])
+            .collect()
+    };
+    let added = paths(|d| matches!(d, Difference::Added(_)));
+    let removed = paths(|d| matches!(d, Difference::Removed(_)));
+    let relinked = paths(|d| matches!(d, Difference::Relinked(_)));
+    let modified: Vec<Vec<String>> = report
+        .differences
+        .iter()
+        .filter_map(|difference| match difference {
+            Difference::Modified(path) => {
+                let lines = report.text_diffs.get(path).map(|hunks| {
+                    let lines = hunks.iter().flat_map(|hunk| &hunk.lines);
+                    let additions = lines
+                        .clone()
+                        .filter(|line| matches!(line, HunkLine::Added(_)))
+                        .count();
+                    let deletions = lines
+                        .filter(|line| matches!(line, HunkLine::Removed(_)))
+                        .count();
+                    format!("+{} -{}", additions, deletions)
+                });
+                Some(vec![code(path), lines.unwrap_or_default()])
+            }
+            _ => None,
+        })
+        .collect();
+    let renamed: Vec<Vec<String>> = report
+        .differences
+        .iter()
+        .filter_map(|difference| match difference {
+            Difference::Renamed {
+                from,
+                to,
+                similarity,
+            } => Some(vec![
+                code(from),
+                code(to),
+                format!("{:.0}%", similarity * 100.0),
+            ]),
+            _ => None,
+        })
+        .collect();
+
+    let total = report.differences.len();
+    let mut markdown = format!(
+        "## Differences between {} and {}\n\n{} difference{}: {} added, {} removed, {} modified, {} renamed{}\n",
+        code(&report.dir),
+        code(&report.dir_comp),
+        total,
+        if total == 1 { "" } else { "s" },
+        added.len(),
+        removed.len(),
+        modified.len(),
+        renamed.len(),
+        if relinked.is_empty() {
+            String::new()
+        } else {
+            format!(", {} relinked", relinked.len())
+        },
+    );
+    table("Added", &["Path"], &added, &mut markdown);
+    table("Removed", &["Path"], &removed, &mut markdown);
+    if report.text_diffs.is_empty() {
+        let modified: Vec<Vec<String>> =
+            modified.into_iter().map(|row| row[..1].to_vec()).collect();
+        table("Modified", &["Path"], &modified, &mut markdown);
+    } else {
+        table("Modified", &["Path", "Lines"], &modified, &mut markdown);
+    }
+    table(
+        "Renamed",
+        &["From", "To", "Similarity"],
+        &renamed,
+        &mut markdown,
+    );
+    table("Relinked", &["Path"], &relinked, &mut markdown);
+
+    if !report.text_diffs.is_empty() {
+        markdown.push_str("\n### Changes\n");
+        for (path, hunks) in &report.text_diffs {
+            let diff: String = hunks.iter().map(|hunk| hunk.to_string()).collect();
+            let fence = fence(&diff);
+            markdown.push_str(&format!(
+                "\n<details><summary>{}</summary>\n\n{}diff\n{}{}\n\n</details>\n",
+                path.replace('&', "&amp;").replace('<', "&lt;"),
+                fence,
+                diff,
+                fence
+            ));
+        }
+    }
+
+    markdown
+}
+
+#[test]
+fn should_render_the_tables_and_the_changes() {
+    let mut report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![
+            Difference::Modified("a|b.txt".to_string()),
+            Difference::Modified("logo.png".to_string()),
+            Difference::Renamed {
+                from: "old.md".to_string(),
+                to: "new.md".to_string(),
+                similarity: 0.9,
+            },
+        ],
+        ..Default::default()
+    };
+    report.text_diffs.insert(
+        "a|b.txt".to_string(),
+        crate::lines::hunks("a\nb\n", "a\nc\n", 3),
+    );
+
+    assert_eq!(
+        to_markdown(&report),
+        "## Differences between `dir_one` and `dir_two`\n\n\
+         3 differences: 0 added, 0 removed, 2 modified, 1 renamed\n\n\
+         ### Modified\n\n\
+         | Path | Lines |\n\
+         | --- | --- |\n\
+         | `a\\|b.txt` | +1 -1 |\n\
+         | `logo.png` |  |\n\n\
+         ### Renamed\n\n\
+         | From | To | Similarity |\n\
+         | --- | --- | --- |\n\
+         | `old.md` | `new.md` | 90% |\n\n\
+         ### Changes\n\n\
+         <details><summary>a|b.txt</summary>\n\n\
+         ```diff\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n```\n\n\
+         </details>\n"
+    );
+}
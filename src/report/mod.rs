@@ -0,0 +1,1650 @@
+//! Detailed result of a directory comparison and the renderers to present it
+//! as plain text, colored text, a unified diff, an ascii tree, json, Markdown, a Graphviz
+//! graph or a standalone html page
+use crate::cache::Stamp;
+use crate::caps::FileCapabilities;
+use crate::chunk::ChunkDiff;
+use crate::diff::{CompareMode, DirDiff, ErrorPolicy};
+use crate::error::DiffError;
+use crate::hash::{to_hex, ContentHasher};
+use crate::lines::{self, Hunk, LineChange};
+use crate::similarity::content_similarity;
+use crate::tree::{Tree, TreeStats};
+use crate::vfs::{self, DiffFs, EntryKind};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+mod ascii;
+mod color;
+mod dot;
+mod html;
+mod json;
+mod markdown;
+mod text;
+mod unified;
+
+pub use ascii::to_ascii_tree;
+pub use color::{color_enabled, to_colored_text, to_terminal};
+pub use html::to_html;
+pub use json::{file_to_json, to_json};
+pub use markdown::to_markdown;
+pub use text::to_text;
+pub use unified::to_unified;
+
+/// Difference found between `dir` and `dir_comp`, the paths are relative to the root of both
+#[derive(Debug, PartialEq, Clone)]
+pub enum Difference {
+    /// entry only present in `dir_comp`
+    Added(String),
+    /// entry only present in `dir`
+    Removed(String),
+    /// file present in both with different content, or a file in one and a directory in the other
+    Modified(String),
+    /// file only present in `dir` as `from` and only in `dir_comp` as `to`, with the same
+    /// content or a similar one
+    Renamed {
+        /// path in `dir`
+        from: String,
+        /// path in `dir_comp`
+        to: String,
+        /// similarity of both contents, 1.0 if are equal
+        similarity: f64,
+    },
+    /// file present in both with the same content but hard linked to different files,
+    /// only reported with `DirDiff::report_hardlinks`
+    Relinked(String),
+}
+
+impl Difference {
+    /// Relative path of the entry, the new path if it was renamed
+    pub fn path(&self) -> &str {
+        match self {
+            Difference::Added(path)
+            | Difference::Removed(path)
+            | Difference::Modified(path)
+            | Difference::Relinked(path) => path,
+            Difference::Renamed { to, .. } => to,
+        }
+    }
+}
+
+/// Numbers of a directory comparison
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct DiffStats {
+    /// totals of `dir`
+    pub tree: TreeStats,
+    /// totals of `dir_comp`
+    pub tree_comp: TreeStats,
+    /// bytes read from both sides to compare the files present in both
+    pub bytes_compared: u64,
+    /// number of `Difference::Added`
+    pub added: usize,
+    /// number of `Difference::Removed`
+    pub removed: usize,
+    /// number of `Difference::Modified`
+    pub modified: usize,
+    /// number of `Difference::Renamed`
+    pub renamed: usize,
+    /// number of `Difference::Relinked`
+    pub relinked: usize,
+}
+
+/// Work done by a directory comparison and the time spent in each phase
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct DiffMetrics {
+    /// entries of both trees, files and directories
+    pub entries_scanned: usize,
+    /// files read from both sides to compare their content
+    pub files_read: usize,
+    /// bytes read from both sides, the same as `DiffStats::bytes_compared`
+    pub bytes_read: u64,
+    /// time spent listing both directories to build the trees
+    pub build_duration: Duration,
+    /// time spent comparing the trees
+    pub compare_duration: Duration,
+}
+
+/// Result of comparing two directories entry by entry
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DiffReport {
+    /// directory compared
+    pub dir: String,
+    /// comparation directory
+    pub dir_comp: String,
+    /// differences sorted by path
+    pub differences: Vec<Difference>,
+    /// numbers of the comparison
+    pub stats: DiffStats,
+    /// changes of the modified files by path, only if compared with `DirDiff::chunking`
+    pub changes: BTreeMap<String, ChunkDiff>,
+    /// digests of the files present in both directories by path, only if compared
+    /// with `DirDiff::report_digests`
+    pub digests: BTreeMap<String, FileDigest>,
+    /// capabilities of both sides of the files whose capabilities differ by path, only if
+    /// compared with `DirDiff::compare_capabilities`
+    pub capabilities: BTreeMap<String, CapabilitiesChange>,
+    /// names of the alternate data streams which differ, sorted, by path of the file. Only
+    /// if compared with `DirDiff::compare_streams`
+    pub streams: BTreeMap<String, Vec<String>>,
+    /// hunks of the modified text files by path, only if compared with
+    /// `DirDiff::report_text_diffs`. The binary files are not included
+    pub text_diffs: BTreeMap<String, Vec<Hunk>>,
+    /// content types of the differences by path, from `dir_comp` or from `dir` if the
+    /// entry was removed, only if compared with `DirDiff::detect_content_type`
+    pub content_types: BTreeMap<String, String>,
+    /// paths of the FIFOs, sockets, devices and other special files of both directories,
+    /// sorted. Their content is not compared, only their kind
+    pub special_files: Vec<String>,
+    /// paths of the directories which couldn't be listed and of the files which couldn't
+    /// be read in any of both directories, sorted. They are differences only with
+    /// `DirDiff::unreadable_as_modified`
+    pub unreadable: Vec<String>,
+    /// errors of the entries which couldn't be read with `ErrorPolicy::Collect`
+    pub errors: Vec<DiffError>,
+    /// true if any of the trees reached `DirDiff::max_entries`, then the differences
+    /// of the entries not listed are missing
+    pub truncated: bool,
+    /// work done and time spent by the comparison
+    pub metrics: DiffMetrics,
+}
+
+/// Linux capabilities of both sides of a file, see `DirDiff::compare_capabilities`
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct CapabilitiesChange {
+    /// capabilities of the file in `dir`
+    pub capabilities: FileCapabilities,
+    /// capabilities of the file in `dir_comp`
+    pub capabilities_comp: FileCapabilities,
+}
+
+/// Detailed result of comparing two files, returned by `file_diff_report`
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct FileDiffResult {
+    /// true if the bytes of both files are different
+    pub different: bool,
+    /// true if any of the files is not text, then the lines are not compared
+    pub binary: bool,
+    /// number of lines only in the comparation file
+    pub additions: usize,
+    /// number of lines only in the first file
+    pub deletions: usize,
+    /// groups of lines changed, in order
+    pub changes: Vec<LineChange>,
+}
+
+/// Hexadecimal digests of both sides of a file, computed with `DirDiff::hasher`
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct FileDigest {
+    /// digest of the file in `dir`
+    pub digest: String,
+    /// digest of the file in `dir_comp`
+    pub digest_comp: String,
+}
+
+impl DiffReport {
+    /// Return true if no differences were found
+    pub fn is_equal(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Paths of the entries only present in `dir`, the `Difference::Removed`. The renamed
+    /// files are not included
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::report::{DiffReport, Difference};
+    ///
+    /// let report = DiffReport {
+    ///     differences: vec![
+    ///         Difference::Added("hello.js".to_string()),
+    ///         Difference::Removed("hello.py".to_string()),
+    ///     ],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(report.only_in_dir(), vec!["hello.py"]);
+    /// assert_eq!(report.only_in_dir_comp(), vec!["hello.js"]);
+    /// ```
+    pub fn only_in_dir(&self) -> Vec<&str> {
+        self.differences
+            .iter()
+            .filter_map(|difference| match difference {
+                Difference::Removed(path) => Some(path.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Paths of the entries only present in `dir_comp`, the `Difference::Added`. The renamed
+    /// files are not included
+    pub fn only_in_dir_comp(&self) -> Vec<&str> {
+        self.differences
+            .iter()
+            .filter_map(|difference| match difference {
+                Difference::Added(path) => Some(path.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Differences whose file has the content type, or any content type of its family
+    /// if it ends with `/`, like `image/`. Empty if the content types weren't detected
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::report::{DiffReport, Difference};
+    ///
+    /// let report = DiffReport {
+    ///     differences: vec![
+    ///         Difference::Added("logo.png".to_string()),
+    ///         Difference::Modified("readme.txt".to_string()),
+    ///     ],
+    ///     content_types: vec![
+    ///         ("logo.png".to_string(), "image/png".to_string()),
+    ///         ("readme.txt".to_string(), "text/plain".to_string()),
+    ///     ]
+    ///     .into_iter()
+    ///     .collect(),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(
+    ///     report.differences_of_type("image/"),
+    ///     vec![&Difference::Added("logo.png".to_string())]
+    /// );
+    /// ```
+    pub fn differences_of_type(&self, content_type: &str) -> Vec<&Difference> {
+        self.differences
+            .iter()
+            .filter(
+                |difference| match self.content_types.get(difference.path()) {
+                    Some(found) if content_type.ends_with('/') => found.starts_with(content_type),
+                    Some(found) => found == content_type,
+                    None => false,
+                },
+            )
+            .collect()
+    }
+
+    /// Line of `diff -rq` describing the difference
+    fn line(&self, difference: &Difference) -> String {
+        match difference {
+            Difference::Added(path) => {
+                let (parent, name) = split(&self.dir_comp, path);
+                format!("Only in {}: {}", parent, name)
+            }
+            Difference::Removed(path) => {
+                let (parent, name) = split(&self.dir, path);
+                format!("Only in {}: {}", parent, name)
+            }
+            Difference::Modified(path) => format!(
+                "Files {} and {} differ",
+                join(&self.dir, path),
+                join(&self.dir_comp, path)
+            ),
+            Difference::Renamed {
+                from,
+                to,
+                similarity,
+            } if *similarity < 1.0 => format!(
+                "File {} renamed to {} and modified, {:.0}% similar",
+                join(&self.dir, from),
+                join(&self.dir_comp, to),
+                similarity * 100.0
+            ),
+            Difference::Renamed { from, to, .. } => format!(
+                "File {} renamed to {}",
+                join(&self.dir, from),
+                join(&self.dir_comp, to)
+            ),
+            Difference::Relinked(path) => format!(
+                "Files {} and {} are hard linked to different files",
+                join(&self.dir, path),
+                join(&self.dir_comp, path)
+            ),
+        }
+    }
+}
+
+/// Path of the entry in the directory, the `\` of the paths of the trees taken in Windows
+/// are shown as separators
+fn join(dir: &str, path: &str) -> String {
+    vfs::path::join(dir, &vfs::path::normalize_separators(path))
+}
+
+/// Directory containing the entry and name of the entry
+fn split(dir: &str, path: &str) -> (String, String) {
+    let path = vfs::path::normalize_separators(path);
+    match path.rsplit_once('/') {
+        Some((parent, name)) => (join(dir, parent), name.to_string()),
+        None => (vfs::path::trim_separator(dir).to_string(), path.to_string()),
+    }
+}
+
+/// Same output than `diff -rq`
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for difference in &self.differences {
+            writeln!(f, "{}", self.line(difference))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Differences found comparing two trees
+#[derive(Debug, Default)]
+pub(crate) struct Comparison {
+    pub(crate) differences: Vec<Difference>,
+    /// bytes read from both sides
+    pub(crate) bytes_compared: u64,
+    pub(crate) changes: BTreeMap<String, ChunkDiff>,
+    pub(crate) digests: BTreeMap<String, FileDigest>,
+    pub(crate) capabilities: BTreeMap<String, CapabilitiesChange>,
+    pub(crate) streams: BTreeMap<String, Vec<String>>,
+    pub(crate) text_diffs: BTreeMap<String, Vec<Hunk>>,
+    /// files read from both sides
+    pub(crate) files_read: usize,
+    /// entries which couldn't be read in any of both sides
+    pub(crate) unreadable: Vec<String>,
+    /// errors reading them, only the first one with `ErrorPolicy::FailFast`
+    pub(crate) errors: Vec<DiffError>,
+}
+
+/// Compare two trees entry by entry reading the content of the files present in both.
+/// The removed and added files are renamed if their content is equal, or similar enough
+/// with `DirDiff::similarity_threshold`, and the modified files are compared by chunks
+/// with `DirDiff::chunking`
+pub(crate) async fn compare_trees<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    tree: &[Tree],
+    fs_comp: &C,
+    tree_comp: &[Tree],
+    options: &DirDiff,
+) -> Comparison {
+    if options.mode == CompareMode::Content {
+        return compare_contents(fs, tree, fs_comp, tree_comp, options).await;
+    }
+
+    let index: BTreeMap<String, &Tree> = Tree::iter(tree).collect();
+    let index_comp: BTreeMap<String, &Tree> = Tree::iter(tree_comp).collect();
+
+    let mut paths: Vec<&String> = index.keys().chain(index_comp.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut differences: Vec<Difference> = vec![];
+    let mut bytes_compared: u64 = 0;
+    let mut changes: BTreeMap<String, ChunkDiff> = BTreeMap::new();
+    let mut digests: BTreeMap<String, FileDigest> = BTreeMap::new();
+    let mut capabilities: BTreeMap<String, CapabilitiesChange> = BTreeMap::new();
+    let mut streams: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut files_read: usize = 0;
+    let mut unreadable: Vec<String> = vec![];
+    let mut errors: Vec<DiffError> = vec![];
+    // digests of the files hard linked to others by device and inode, with `hardlinks`
+    let mut linked: HashMap<(u64, u64), Vec<u8>> = HashMap::new();
+    let mut linked_comp: HashMap<(u64, u64), Vec<u8>> = HashMap::new();
+    let links = hard_links(&index);
+    let links_comp = hard_links(&index_comp);
+    for path in paths {
+        // the content of a directory only present in one side is not listed
+        if differences.iter().any(|difference| match difference {
+            Difference::Added(parent) | Difference::Removed(parent) => {
+                path.starts_with(&format!("{}/", parent))
+            }
+            _ => false,
+        }) || unreadable
+            .iter()
+            .any(|parent| path.starts_with(&format!("{}/", parent)))
+        {
+            continue;
+        }
+        if let Some(hook) = &options.on_entry_visited {
+            hook.call(path);
+        }
+
+        match (index.get(path), index_comp.get(path)) {
+            (Some(_), None) => differences.push(Difference::Removed(path.clone())),
+            (None, Some(_)) => differences.push(Difference::Added(path.clone())),
+            (Some(entry), Some(entry_comp)) => match (&entry.subdir, &entry_comp.subdir) {
+                // the content of a directory which can't be listed is unknown
+                _ if entry.kind == EntryKind::Unreadable
+                    || entry_comp.kind == EntryKind::Unreadable =>
+                {
+                    unreadable.push(path.clone());
+                    if options.unreadable_as_modified {
+                        differences.push(Difference::Modified(path.clone()));
+                    }
+                }
+                _ if entry.kind != entry_comp.kind => {
+                    differences.push(Difference::Modified(path.clone()))
+                }
+                (None, None) if options.mode == CompareMode::Structure => {}
+                (None, None) if matches!(entry.kind, EntryKind::Symlink | EntryKind::Cycle) => {
+                    // the links are equal if they point to the same target, not followed
+                    let target = fs.read_link(&entry.path).await.ok();
+                    let target_comp = fs_comp.read_link(&entry_comp.path).await.ok();
+                    debug_event!(path = %path, ?target, ?target_comp, "symbolic link compared");
+                    if target.is_none() || target != target_comp {
+                        differences.push(Difference::Modified(path.clone()));
+                    }
+                }
+                // the content of the FIFOs, sockets and devices is never read
+                (None, None) if entry.kind.is_special() => {}
+                (None, None) => {
+                    // the capabilities and the streams modify the file even with the same content
+                    let mut attributes_changed = false;
+                    if options.compare_capabilities {
+                        if let Some(change) =
+                            changed_capabilities(fs, entry, fs_comp, entry_comp).await
+                        {
+                            debug_event!(path = %path, "capabilities changed");
+                            capabilities.insert(path.clone(), change);
+                            attributes_changed = true;
+                        }
+                    }
+                    if options.compare_streams {
+                        if let Some(names) = changed_streams(fs, entry, fs_comp, entry_comp).await {
+                            debug_event!(path = %path, ?names, "alternate data streams changed");
+                            streams.insert(path.clone(), names);
+                            attributes_changed = true;
+                        }
+                    }
+                    // the hard links are read once per inode and compared by their topology
+                    // the hard links are read once per inode and compared by their topology
+                    if options.chunking.is_none()
+                        && options.comparator.is_none()
+                        && !options.report_digests
+                        && !options.normalization.is_enabled()
+                        && !options.report_hardlinks
+                        && !options.hardlinks
+                    {
+                        if let Some((equal, bytes_read)) =
+                            fast_equal(fs, entry, fs_comp, entry_comp, options).await
+                        {
+                            bytes_compared += bytes_read;
+                            files_read += 2;
+                            let equal = equal
+                                && (!options.compare_mtime
+                                    || same_mtime(
+                                        fs,
+                                        entry,
+                                        fs_comp,
+                                        entry_comp,
+                                        options.mtime_tolerance,
+                                    )
+                                    .await);
+                            debug_event!(path = %path, equal, bytes_read, "file compared without reading it");
+                            if let Some(hook) = &options.on_file_compared {
+                                hook.call(path, equal && !attributes_changed);
+                            }
+                            if !equal || attributes_changed {
+                                differences.push(Difference::Modified(path.clone()));
+                            }
+                            continue;
+                        }
+                    }
+
+                    // the files with the same digest are equal without a comparator
+                    let by_digest =
+                        options.comparator.is_none() && !options.normalization.is_enabled();
+                    let hash_cache = options.hash_cache.as_deref().filter(|_| by_digest);
+                    let inode = entry.inode.filter(|_| options.hardlinks && by_digest);
+                    let inode_comp = entry_comp.inode.filter(|_| options.hardlinks && by_digest);
+                    let (stamp, stamp_comp) = match hash_cache {
+                        Some(_) => (
+                            Stamp::of(fs, entry).await,
+                            Stamp::of(fs_comp, entry_comp).await,
+                        ),
+                        None => (None, None),
+                    };
+                    let cached = hash_cache
+                        .and_then(|hash_cache| {
+                            Some((
+                                hash_cache.get(&entry.path, stamp.as_ref()?)?,
+                                hash_cache.get(&entry_comp.path, stamp_comp.as_ref()?)?,
+                            ))
+                        })
+                        .or_else(|| {
+                            Some((
+                                linked.get(&inode?)?.clone(),
+                                linked_comp.get(&inode_comp?)?.clone(),
+                            ))
+                        });
+
+                    let equal = match cached {
+                        Some((digest, digest_comp)) if digest == digest_comp => {
+                            if options.report_digests {
+                                digests.insert(
+                                    path.clone(),
+                                    FileDigest {
+                                        digest: to_hex(&digest),
+                                        digest_comp: to_hex(&digest_comp),
+                                    },
+                                );
+                            }
+                            true
+                        }
+                        _ => {
+                            let content = read(fs, entry, &mut files_read).await;
+                            let content_comp = read(fs_comp, entry_comp, &mut files_read).await;
+                            let (content, content_comp) =
+                                match (content, content_comp) {
+                                    (Ok(content), Ok(content_comp)) => (content, content_comp),
+                                    (content, content_comp) => {
+                                        debug_event!(path = %path, "file not readable");
+                                        errors.extend(
+                                            content.err().map(|error| {
+                                                DiffError::from_io(&entry.path, error)
+                                            }),
+                                        );
+                                        errors.extend(content_comp.err().map(|error| {
+                                            DiffError::from_io(&entry_comp.path, error)
+                                        }));
+                                        if options.on_error == ErrorPolicy::FailFast {
+                                            break;
+                                        }
+                                        unreadable.push(path.clone());
+                                        if options.unreadable_as_modified {
+                                            differences.push(Difference::Modified(path.clone()));
+                                        }
+                                        continue;
+                                    }
+                                };
+                            bytes_compared += (content.len() + content_comp.len()) as u64;
+                            if options.report_digests
+                                || hash_cache.is_some()
+                                || inode.is_some()
+                                || inode_comp.is_some()
+                            {
+                                let hasher = options.content_hasher();
+                                let digest = hasher.hash(&content);
+                                let digest_comp = hasher.hash(&content_comp);
+                                if options.report_digests {
+                                    digests.insert(
+                                        path.clone(),
+                                        FileDigest {
+                                            digest: to_hex(&digest),
+                                            digest_comp: to_hex(&digest_comp),
+                                        },
+                                    );
+                                }
+                                if let Some(inode) = inode {
+                                    linked.insert(inode, digest.clone());
+                                }
+                                if let Some(inode_comp) = inode_comp {
+                                    linked_comp.insert(inode_comp, digest_comp.clone());
+                                }
+                                if let Some(hash_cache) = hash_cache {
+                                    if let Some(stamp) = stamp {
+                                        hash_cache.insert(&entry.path, stamp, digest);
+                                    }
+                                    if let Some(stamp_comp) = stamp_comp {
+                                        hash_cache.insert(
+                                            &entry_comp.path,
+                                            stamp_comp,
+                                            digest_comp,
+                                        );
+                                    }
+                                }
+                            }
+                            let equal = match &options.comparator {
+                                Some(comparator) => comparator
+                                    .equal(path, &content, &content_comp)
+                                    .await
+                                    .unwrap_or(false),
+                                None => options.normalization.equal(path, &content, &content_comp),
+                            };
+                            if !equal {
+                                if let Some(chunker) = options.chunking {
+                                    changes.insert(
+                                        path.clone(),
+                                        chunker.diff(&content, &content_comp),
+                                    );
+                                }
+                            }
+                            equal
+                        }
+                    };
+                    debug_event!(path = %path, equal, "file compared");
+                    let modified = !equal
+                        || attributes_changed
+                        || (options.compare_mtime
+                            && !same_mtime(
+                                fs,
+                                entry,
+                                fs_comp,
+                                entry_comp,
+                                options.mtime_tolerance,
+                            )
+                            .await);
+                    if let Some(hook) = &options.on_file_compared {
+                        hook.call(path, !modified);
+                    }
+                    if modified {
+                        differences.push(Difference::Modified(path.clone()));
+                    } else if options.report_hardlinks
+                        && linked_to(&links, path, entry)
+                            != linked_to(&links_comp, path, entry_comp)
+                    {
+                        differences.push(Difference::Relinked(path.clone()));
+                    }
+                }
+                (Some(_), Some(_)) => {}
+                _ => differences.push(Difference::Modified(path.clone())),
+            },
+            (None, None) => {}
+        }
+    }
+
+    let (bytes_renamed, files_renamed) = if options.mode == CompareMode::Structure {
+        (0, 0)
+    } else {
+        detect_renames(
+            fs,
+            &index,
+            fs_comp,
+            &index_comp,
+            options.similarity_threshold,
+            options.content_hasher(),
+            &mut differences,
+        )
+        .await
+    };
+
+    let mut text_diffs: BTreeMap<String, Vec<Hunk>> = BTreeMap::new();
+    if options.report_text_diffs {
+        for difference in &differences {
+            let path = match difference {
+                Difference::Modified(path) => path,
+                _ => continue,
+            };
+            if let (Some(entry), Some(entry_comp)) = (index.get(path), index_comp.get(path)) {
+                if let Some(hunks) = text_diff(fs, entry, fs_comp, entry_comp).await {
+                    text_diffs.insert(path.clone(), hunks);
+                }
+            }
+        }
+    }
+
+    Comparison {
+        differences,
+        bytes_compared: bytes_compared + bytes_renamed,
+        changes,
+        digests,
+        capabilities,
+        streams,
+        text_diffs,
+        files_read: files_read + files_renamed,
+        unreadable,
+        errors,
+    }
+}
+
+/// Read the content of the file from the source, counted in `files_read`
+async fn read<F: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &Tree,
+    files_read: &mut usize,
+) -> io::Result<Vec<u8>> {
+    let content = fs.read(&entry.path).await?;
+    *files_read += 1;
+
+    Ok(content)
+}
+
+/// Compare the multisets of contents of both trees, the files whose content is not in the
+/// other tree, or is there less times, are reported as removed or added
+async fn compare_contents<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    tree: &[Tree],
+    fs_comp: &C,
+    tree_comp: &[Tree],
+    options: &DirDiff,
+) -> Comparison {
+    let hasher = options.content_hasher();
+    let mut bytes_compared: u64 = 0;
+    let mut files_read: usize = 0;
+    let mut unreadable: Vec<String> = vec![];
+    let mut errors: Vec<DiffError> = vec![];
+    let mut contents: HashMap<(Vec<u8>, u64), Vec<String>> = HashMap::new();
+    for (path, entry) in Tree::iter(tree).filter(|(_, entry)| entry.kind == EntryKind::File) {
+        let content = match fs.read(&entry.path).await {
+            Ok(content) => content,
+            Err(error) => {
+                errors.push(DiffError::from_io(&entry.path, error));
+                if options.on_error == ErrorPolicy::FailFast {
+                    return Comparison {
+                        errors,
+                        ..Default::default()
+                    };
+                }
+                unreadable.push(path);
+                continue;
+            }
+        };
+        bytes_compared += content.len() as u64;
+        files_read += 1;
+        let content = options.normalization.apply(&path, &content);
+        contents
+            .entry((hasher.hash(&content), content.len() as u64))
+            .or_default()
+            .push(path);
+    }
+
+    let mut differences: Vec<Difference> = vec![];
+    for (path, entry) in Tree::iter(tree_comp).filter(|(_, entry)| entry.kind == EntryKind::File) {
+        let content = match fs_comp.read(&entry.path).await {
+            Ok(content) => content,
+            Err(error) => {
+                errors.push(DiffError::from_io(&entry.path, error));
+                if options.on_error == ErrorPolicy::FailFast {
+                    return Comparison {
+                        errors,
+                        ..Default::default()
+                    };
+                }
+                unreadable.push(path);
+                continue;
+            }
+        };
+        bytes_compared += content.len() as u64;
+        files_read += 1;
+        let content = options.normalization.apply(&path, &content);
+        match contents.get_mut(&(hasher.hash(&content), content.len() as u64)) {
+            Some(paths) if !paths.is_empty() => {
+                paths.remove(0);
+            }
+            _ => differences.push(Difference::Added(path)),
+        }
+    }
+    differences.extend(contents.into_values().flatten().map(Difference::Removed));
+    differences.sort_by(|a, b| a.path().cmp(b.path()));
+
+    Comparison {
+        differences,
+        bytes_compared,
+        files_read,
+        unreadable,
+        errors,
+        ..Default::default()
+    }
+}
+
+/// Return false if the modification times of both files differ more than the tolerance,
+/// true if any of the sources doesn't know it
+async fn same_mtime<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &Tree,
+    fs_comp: &C,
+    entry_comp: &Tree,
+    tolerance: Duration,
+) -> bool {
+    let modified = fs
+        .metadata(&entry.path)
+        .await
+        .ok()
+        .and_then(|metadata| metadata.modified);
+    let modified_comp = fs_comp
+        .metadata(&entry_comp.path)
+        .await
+        .ok()
+        .and_then(|metadata| metadata.modified);
+
+    match (modified, modified_comp) {
+        (Some(modified), Some(modified_comp)) => {
+            let difference = modified
+                .duration_since(modified_comp)
+                .or_else(|_| modified_comp.duration_since(modified))
+                .unwrap_or_default();
+            difference <= tolerance
+        }
+        _ => true,
+    }
+}
+
+/// Capabilities of both files if they differ, `None` if they are the same or any of the
+/// sources doesn't know them
+async fn changed_capabilities<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &Tree,
+    fs_comp: &C,
+    entry_comp: &Tree,
+) -> Option<CapabilitiesChange> {
+    let capabilities = fs.capabilities(&entry.path).await.ok()??;
+    let capabilities_comp = fs_comp.capabilities(&entry_comp.path).await.ok()??;
+
+    if capabilities == capabilities_comp {
+        None
+    } else {
+        Some(CapabilitiesChange {
+            capabilities,
+            capabilities_comp,
+        })
+    }
+}
+
+/// Lines changed between both files with the context of `diff -u`, `None` if any of
+/// them is not a text file or can't be read
+async fn text_diff<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &Tree,
+    fs_comp: &C,
+    entry_comp: &Tree,
+) -> Option<Vec<Hunk>> {
+    if entry.kind != EntryKind::File || entry_comp.kind != EntryKind::File {
+        return None;
+    }
+    let content = fs.read(&entry.path).await.ok()?;
+    let content_comp = fs_comp.read(&entry_comp.path).await.ok()?;
+    if lines::is_binary(&content) || lines::is_binary(&content_comp) {
+        return None;
+    }
+
+    Some(lines::hunks(
+        &String::from_utf8_lossy(&content),
+        &String::from_utf8_lossy(&content_comp),
+        3,
+    ))
+}
+
+/// Names of the alternate data streams which are only in one of both files or whose
+/// content differs, `None` if there is none or any of the sources doesn't know them
+async fn changed_streams<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &Tree,
+    fs_comp: &C,
+    entry_comp: &Tree,
+) -> Option<Vec<String>> {
+    let streams = fs.alternate_streams(&entry.path).await.ok()??;
+    let streams_comp = fs_comp.alternate_streams(&entry_comp.path).await.ok()??;
+
+    let mut names: Vec<String> = streams
+        .keys()
+        .chain(streams_comp.keys())
+        .filter(|name| streams.get(*name) != streams_comp.get(*name))
+        .cloned()
+        .collect();
+    names.sort();
+    names.dedup();
+
+    if names.is_empty() {
+        None
+    } else {
+        Some(names)
+    }
+}
+
+/// Compare the files without reading their whole content through the source, the
+/// sparse files by their allocated regions and, with the `mmap` feature, the local files
+/// memory mapped. `None` if none of them applies
+async fn fast_equal<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &Tree,
+    fs_comp: &C,
+    entry_comp: &Tree,
+    options: &DirDiff,
+) -> Option<(bool, u64)> {
+    if options.sparse {
+        if let Some(compared) = sparse_equal(fs, entry, fs_comp, entry_comp).await {
+            return Some(compared);
+        }
+    }
+    // the hash cache is filled only by the files read
+    #[cfg(feature = "mmap")]
+    if options.hash_cache.is_none() {
+        let (path, path_comp) =
+            crate::mmap::local_paths(fs, &entry.path, fs_comp, &entry_comp.path)?;
+        let equal = crate::mmap::equal(&path, &path_comp).await.ok()?;
+        return Some((equal, entry.size + entry_comp.size));
+    }
+
+    None
+}
+
+/// Bytes read at once from the allocated regions of sparse files
+const SPARSE_READ_SIZE: u64 = 1024 * 1024;
+
+/// Compare two sparse files reading only the regions allocated in any of both, outside of
+/// them both are zeros. Return if are equal and the bytes read, or `None` if any of the
+/// sources doesn't know the extents of its file or can't read it
+async fn sparse_equal<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &Tree,
+    fs_comp: &C,
+    entry_comp: &Tree,
+) -> Option<(bool, u64)> {
+    if entry.size != entry_comp.size {
+        return Some((false, 0));
+    }
+    let extents = fs.data_extents(&entry.path).await.ok()??;
+    let extents_comp = fs_comp.data_extents(&entry_comp.path).await.ok()??;
+
+    // union of the regions of both files
+    let mut regions: Vec<(u64, u64)> = extents.into_iter().chain(extents_comp).collect();
+    regions.sort();
+    let mut merged: Vec<(u64, u64)> = vec![];
+    for (offset, len) in regions {
+        match merged.last_mut() {
+            Some((start, merged_len)) if offset <= *start + *merged_len => {
+                *merged_len = (*merged_len).max(offset + len - *start);
+            }
+            _ => merged.push((offset, len)),
+        }
+    }
+
+    let mut bytes_read: u64 = 0;
+    for (offset, len) in merged {
+        let mut position = offset;
+        while position < offset + len {
+            let size = SPARSE_READ_SIZE.min(offset + len - position);
+            let content = fs.read_range(&entry.path, position, size).await.ok()?;
+            let content_comp = fs_comp
+                .read_range(&entry_comp.path, position, size)
+                .await
+                .ok()?;
+            bytes_read += (content.len() + content_comp.len()) as u64;
+            if content != content_comp {
+                return Some((false, bytes_read));
+            }
+            position += size;
+        }
+    }
+
+    Some((true, bytes_read))
+}
+
+/// Paths of the files by inode
+fn hard_links(index: &BTreeMap<String, &Tree>) -> HashMap<(u64, u64), Vec<String>> {
+    let mut links: HashMap<(u64, u64), Vec<String>> = HashMap::new();
+    for (path, entry) in index {
+        if let Some(inode) = entry.inode {
+            links.entry(inode).or_default().push(path.clone());
+        }
+    }
+
+    links
+}
+
+/// Other paths of the tree linked to the same inode than the file
+fn linked_to<'a>(
+    links: &'a HashMap<(u64, u64), Vec<String>>,
+    path: &str,
+    entry: &Tree,
+) -> Vec<&'a String> {
+    entry
+        .inode
+        .and_then(|inode| links.get(&inode))
+        .map(|paths| paths.iter().filter(|linked| *linked != path).collect())
+        .unwrap_or_default()
+}
+
+/// Files of the index which are the entry or are inside of it
+fn files_in<'a>(
+    index: &'a BTreeMap<String, &'a Tree>,
+    path: &'a str,
+) -> impl Iterator<Item = (&'a String, &'a Tree)> + 'a {
+    index
+        .iter()
+        .filter(move |(file_path, entry)| {
+            entry.kind == EntryKind::File
+                && (*file_path == path || file_path.starts_with(&format!("{}/", path)))
+        })
+        .map(|(file_path, entry)| (file_path, *entry))
+}
+
+/// Replace the removed and added files with the same content by `Difference::Renamed`,
+/// the files inside of removed and added directories are matched too but the directories
+/// are still reported. Without `similarity_threshold` only the files with the size of a
+/// removed file are read, otherwise the files which are not equal are renamed to the most
+/// similar file over the threshold. Return the number of bytes and files read
+async fn detect_renames<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+    fs: &F,
+    index: &BTreeMap<String, &Tree>,
+    fs_comp: &C,
+    index_comp: &BTreeMap<String, &Tree>,
+    similarity_threshold: Option<f64>,
+    hasher: &dyn ContentHasher,
+    differences: &mut Vec<Difference>,
+) -> (u64, usize) {
+    let mut removed: Vec<(&String, &Tree)> = vec![];
+    let mut added: Vec<(&String, &Tree)> = vec![];
+    for difference in differences.iter() {
+        match difference {
+            Difference::Removed(path) => removed.extend(files_in(index, path)),
+            Difference::Added(path) => added.extend(files_in(index_comp, path)),
+            _ => {}
+        }
+    }
+    let sizes: Vec<u64> = removed.iter().map(|(_, entry)| entry.size).collect();
+
+    let mut bytes_read: u64 = 0;
+    let mut files_read: usize = 0;
+    let mut contents: Vec<(String, Vec<u8>, Vec<u8>)> = vec![];
+    for (path, entry) in added {
+        if similarity_threshold.is_some() || sizes.contains(&entry.size) {
+            let content = match fs_comp.read(&entry.path).await {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            bytes_read += content.len() as u64;
+            files_read += 1;
+            let hash = hasher.hash(&content);
+            contents.push((path.clone(), content, hash));
+        }
+    }
+    if contents.is_empty() || removed.is_empty() {
+        return (bytes_read, files_read);
+    }
+
+    let mut renames: Vec<Difference> = vec![];
+    let mut not_equal: Vec<(String, Vec<u8>)> = vec![];
+    for (path, entry) in removed {
+        let content = match fs.read(&entry.path).await {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        bytes_read += content.len() as u64;
+        files_read += 1;
+        let hash = hasher.hash(&content);
+        match contents
+            .iter()
+            .position(|(_, content_comp, hash_comp)| *hash_comp == hash && *content_comp == content)
+        {
+            Some(position) => renames.push(Difference::Renamed {
+                from: path.clone(),
+                to: contents.remove(position).0,
+                similarity: 1.0,
+            }),
+            None => not_equal.push((path.clone(), content)),
+        }
+    }
+
+    if let Some(threshold) = similarity_threshold {
+        for (path, content) in not_equal {
+            let best = contents
+                .iter()
+                .enumerate()
+                .map(|(position, (_, content_comp, _))| {
+                    (position, content_similarity(&content, content_comp))
+                })
+                .filter(|(_, similarity)| *similarity >= threshold)
+                .fold(None, |best: Option<(usize, f64)>, candidate| match best {
+                    Some(best) if best.1 >= candidate.1 => Some(best),
+                    _ => Some(candidate),
+                });
+            if let Some((position, similarity)) = best {
+                renames.push(Difference::Renamed {
+                    from: path,
+                    to: contents.remove(position).0,
+                    similarity,
+                });
+            }
+        }
+    }
+
+    differences.retain(|difference| match difference {
+        Difference::Removed(path) => !renames
+            .iter()
+            .any(|rename| matches!(rename, Difference::Renamed { from, .. } if from == path)),
+        Difference::Added(path) => !renames.iter().any(|rename| rename.path() == path),
+        _ => true,
+    });
+    differences.extend(renames);
+    differences.sort_by(|a, b| a.path().cmp(b.path()));
+
+    (bytes_read, files_read)
+}
+
+#[test]
+fn should_display_report_like_diff() {
+    let report = DiffReport {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: "./mocks/dir_four/".to_string(),
+        differences: vec![
+            Difference::Added("hello.js".to_string()),
+            Difference::Modified("vlang/hello.v".to_string()),
+            Difference::Removed("vlang/purpose".to_string()),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        report.to_string(),
+        "Only in ./mocks/dir_four: hello.js\n\
+         Files ./mocks/dir_one/vlang/hello.v and ./mocks/dir_four/vlang/hello.v differ\n\
+         Only in ./mocks/dir_one/vlang: purpose\n"
+    );
+}
+
+#[test]
+fn should_display_the_windows_separators_of_the_paths() {
+    let report = DiffReport {
+        dir: "./mocks/dir_one".to_string(),
+        dir_comp: r"C:\mocks\dir_four".to_string(),
+        differences: vec![
+            Difference::Added(r"vlang\hello.js".to_string()),
+            Difference::Modified(r"vlang\hello.v".to_string()),
+            Difference::Removed(r"vlang\purpose".to_string()),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        report.to_string(),
+        "Only in C:\\mocks\\dir_four\\vlang: hello.js\n\
+         Files ./mocks/dir_one/vlang/hello.v and C:\\mocks\\dir_four\\vlang\\hello.v differ\n\
+         Only in ./mocks/dir_one/vlang: purpose\n"
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_detect_renamed_files() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/old.txt", "Old")
+        .file("dir_two/greetings/hello.txt", "Hello world")
+        .file("dir_two/new.txt", "New")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &DirDiff::default()).await;
+    assert_eq!(
+        comparison.differences,
+        vec![
+            Difference::Added("greetings".to_string()),
+            Difference::Renamed {
+                from: "hello.txt".to_string(),
+                to: "greetings/hello.txt".to_string(),
+                similarity: 1.0,
+            },
+            Difference::Added("new.txt".to_string()),
+            Difference::Removed("old.txt".to_string()),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_detect_renamed_and_modified_files() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file(
+            "dir_one/main.v",
+            "fn main() {\n\tprintln('hello')\n\tprintln('world')\n}",
+        )
+        .file(
+            "dir_two/src/main.v",
+            "fn main() {\n\tprintln('hello')\n\tprintln('vlang')\n}",
+        )
+        .file("dir_two/other.txt", "Other")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let options = DirDiff::default();
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+    assert_eq!(comparison.differences.len(), 3);
+
+    let options = DirDiff {
+        similarity_threshold: Some(0.7),
+        ..Default::default()
+    };
+    let differences = compare_trees(&fs, &tree, &fs, &tree_comp, &options)
+        .await
+        .differences;
+    assert_eq!(
+        differences,
+        vec![
+            Difference::Added("other.txt".to_string()),
+            Difference::Added("src".to_string()),
+            Difference::Renamed {
+                from: "main.v".to_string(),
+                to: "src/main.v".to_string(),
+                similarity: 0.75,
+            },
+        ]
+    );
+
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences,
+        ..Default::default()
+    };
+    assert!(report.to_string().ends_with(
+        "File dir_one/main.v renamed to dir_two/src/main.v and modified, 75% similar\n"
+    ));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_different_hard_links() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .hard_link("dir_one/link.txt", "dir_one/hello.txt")
+        .file("dir_two/hello.txt", "Hello world")
+        .file("dir_two/link.txt", "Hello world")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let linked = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .hard_link("dir_one/link.txt", "dir_one/hello.txt")
+        .file("dir_two/hello.txt", "Hello world")
+        .hard_link("dir_two/link.txt", "dir_two/hello.txt")
+        .build();
+    let options = DirDiff {
+        hardlinks: true,
+        ..Default::default()
+    };
+    let comparison = compare_trees(
+        &linked,
+        &Tree::build_tree_with(&linked, "dir_one".to_string(), None, false).await,
+        &linked,
+        &Tree::build_tree_with(&linked, "dir_two".to_string(), None, false).await,
+        &options,
+    )
+    .await;
+    assert!(comparison.differences.is_empty());
+    // the links are equal by the digests of the files they link to, without reading them
+    assert_eq!(comparison.files_read, 2);
+    assert_eq!(comparison.bytes_compared, 22);
+
+    let options = DirDiff {
+        report_hardlinks: true,
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+    assert_eq!(
+        comparison.differences,
+        vec![
+            Difference::Relinked("hello.txt".to_string()),
+            Difference::Relinked("link.txt".to_string()),
+        ]
+    );
+
+    // the sparse files compared by their regions are relinked too
+    let sparse = crate::vfs::mem::MemFs::builder()
+        .sparse_file("dir_one/disk.img", 4096, vec![(0, b"boot".to_vec())])
+        .hard_link("dir_one/link.img", "dir_one/disk.img")
+        .sparse_file("dir_two/disk.img", 4096, vec![(0, b"boot".to_vec())])
+        .sparse_file("dir_two/link.img", 4096, vec![(0, b"boot".to_vec())])
+        .build();
+    let options = DirDiff {
+        report_hardlinks: true,
+        sparse: true,
+        ..Default::default()
+    };
+    let comparison = compare_trees(
+        &sparse,
+        &Tree::build_tree_with(&sparse, "dir_one".to_string(), None, false).await,
+        &sparse,
+        &Tree::build_tree_with(&sparse, "dir_two".to_string(), None, false).await,
+        &options,
+    )
+    .await;
+    assert_eq!(
+        comparison.differences,
+        vec![
+            Difference::Relinked("disk.img".to_string()),
+            Difference::Relinked("link.img".to_string()),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_only_the_data_of_sparse_files() {
+    use crate::tree::TreeBuilder;
+
+    let len = 4 * 1024 * 1024;
+    let fs = crate::vfs::mem::MemFs::builder()
+        .sparse_file("dir_one/disk.img", len, vec![(1024, b"boot".to_vec())])
+        .sparse_file("dir_two/disk.img", len, vec![(1024, b"boot".to_vec())])
+        .sparse_file("dir_one/db", len, vec![(0, b"page".to_vec())])
+        .sparse_file("dir_two/db", len, vec![(2048, b"page".to_vec())])
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let options = DirDiff {
+        sparse: true,
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+
+    assert_eq!(
+        comparison.differences,
+        vec![Difference::Modified("db".to_string())]
+    );
+    assert!(comparison.bytes_compared < 64);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_only_the_structure() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .file("dir_two/vlang/hello.v", "fn main() { println('hello') }")
+        .file("dir_two/hello.js", "Hello world")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let options = DirDiff {
+        mode: CompareMode::Structure,
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+
+    assert_eq!(
+        comparison.differences,
+        vec![Difference::Added("hello.js".to_string())]
+    );
+    assert_eq!(comparison.bytes_compared, 0);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_only_the_contents() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/copy.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_two/archive/greetings.txt", "Hello world")
+        .file("dir_two/main.v", "fn main() {}")
+        .file("dir_two/other.txt", "Other")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let options = DirDiff {
+        mode: CompareMode::Content,
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+
+    assert_eq!(
+        comparison.differences,
+        vec![
+            Difference::Removed("hello.txt".to_string()),
+            Difference::Added("other.txt".to_string()),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_mtimes_with_tolerance() {
+    use crate::tree::TreeBuilder;
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now();
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file_modified("dir_one/fat.txt", "Hello world", now)
+        .file_modified("dir_one/old.txt", "Hello world", now)
+        .file_modified(
+            "dir_two/fat.txt",
+            "Hello world",
+            now + Duration::from_secs(1),
+        )
+        .file_modified(
+            "dir_two/old.txt",
+            "Hello world",
+            now - Duration::from_secs(60),
+        )
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let options = DirDiff {
+        compare_mtime: true,
+        mtime_tolerance: Duration::from_secs(2),
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+
+    assert_eq!(
+        comparison.differences,
+        vec![Difference::Modified("old.txt".to_string())]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_differ_by_the_mtimes_with_compare_mtime() {
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now();
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file_modified("dir_one/old.txt", "Hello world", now)
+        .file_modified(
+            "dir_two/old.txt",
+            "Hello world",
+            now - Duration::from_secs(60),
+        )
+        .build();
+    let options = |compare_mtime| DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        compare_mtime,
+        mtime_tolerance: Duration::from_secs(2),
+        ..Default::default()
+    };
+
+    assert!(crate::dir_diff_with(&fs, &fs, options(true)).await);
+    assert!(!crate::dir_diff_with(&fs, &fs, options(false)).await);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_contents_with_the_hasher() {
+    use crate::tree::TreeBuilder;
+    use std::sync::Arc;
+
+    /// Digest which ignores the case of the content
+    struct CaseInsensitive;
+
+    impl ContentHasher for CaseInsensitive {
+        fn hash(&self, content: &[u8]) -> Vec<u8> {
+            content.to_ascii_lowercase()
+        }
+    }
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/greetings.txt", "HELLO WORLD")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let options = DirDiff {
+        mode: CompareMode::Content,
+        hasher: Some(Arc::new(CaseInsensitive)),
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+
+    assert!(comparison.differences.is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_capabilities() {
+    use crate::tree::TreeBuilder;
+
+    let net_bind_service = FileCapabilities {
+        permitted: 1 << 10,
+        effective: true,
+        ..Default::default()
+    };
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/server", "ELF")
+        .file("dir_one/client", "ELF")
+        .capabilities("dir_one/server", net_bind_service)
+        .capabilities("dir_one/client", net_bind_service)
+        .file("dir_two/server", "ELF")
+        .file("dir_two/client", "ELF")
+        .capabilities("dir_two/client", net_bind_service)
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &DirDiff::default()).await;
+    assert!(comparison.differences.is_empty());
+
+    let options = DirDiff {
+        compare_capabilities: true,
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+    assert_eq!(
+        comparison.differences,
+        vec![Difference::Modified("server".to_string())]
+    );
+    assert_eq!(
+        comparison.capabilities["server"].capabilities.to_string(),
+        "cap_net_bind_service=ep"
+    );
+    assert!(comparison.capabilities["server"]
+        .capabilities_comp
+        .is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_alternate_streams() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/setup.exe", "MZ")
+        .file("dir_one/readme.txt", "hello")
+        .stream(
+            "dir_one/setup.exe",
+            "Zone.Identifier",
+            "[ZoneTransfer]\nZoneId=3",
+        )
+        .stream("dir_one/readme.txt", "hidden", "payload")
+        .file("dir_two/setup.exe", "MZ")
+        .file("dir_two/readme.txt", "hello")
+        .stream(
+            "dir_two/setup.exe",
+            "Zone.Identifier",
+            "[ZoneTransfer]\nZoneId=3",
+        )
+        .stream("dir_two/readme.txt", "hidden", "other payload")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &DirDiff::default()).await;
+    assert!(comparison.differences.is_empty());
+
+    let options = DirDiff {
+        compare_streams: true,
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+    assert_eq!(
+        comparison.differences,
+        vec![Difference::Modified("readme.txt".to_string())]
+    );
+    assert_eq!(comparison.streams["readme.txt"], vec!["hidden".to_string()]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_report_the_lines_changed_in_the_text_files() {
+    use crate::tree::TreeBuilder;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.v", "fn main() {\n}\n")
+        .file("dir_one/logo.png", b"\x89PNG\0\0".to_vec())
+        .file("dir_two/hello.v", "fn main() {\n\tprintln('hello')\n}\n")
+        .file("dir_two/logo.png", b"\x89PNG\0\x01".to_vec())
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let options = DirDiff {
+        report_text_diffs: true,
+        ..Default::default()
+    };
+    let comparison = compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+
+    assert_eq!(comparison.differences.len(), 2);
+    assert_eq!(
+        comparison.text_diffs.keys().collect::<Vec<_>>(),
+        vec!["hello.v"]
+    );
+    assert_eq!(
+        comparison.text_diffs["hello.v"][0].to_string(),
+        "@@ -1,2 +1,3 @@\n fn main() {\n+\tprintln('hello')\n }\n"
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_call_the_hooks_while_comparing() {
+    use crate::diff::{CompareHook, EntryHook};
+    use crate::tree::TreeBuilder;
+    use std::sync::{Arc, Mutex};
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_one/old/a.txt", "a")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .file("dir_two/vlang/hello.v", "fn main() {}")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let tree_comp = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let visited = Arc::new(Mutex::new(vec![]));
+    let compared = Arc::new(Mutex::new(vec![]));
+    let options = DirDiff {
+        on_entry_visited: Some(EntryHook::new({
+            let visited = visited.clone();
+            move |path| visited.lock().unwrap().push(path.to_string())
+        })),
+        on_file_compared: Some(CompareHook::new({
+            let compared = compared.clone();
+            move |path, equal| compared.lock().unwrap().push((path.to_string(), equal))
+        })),
+        ..Default::default()
+    };
+    compare_trees(&fs, &tree, &fs, &tree_comp, &options).await;
+
+    // the content of the removed directory is not visited
+    assert_eq!(
+        *visited.lock().unwrap(),
+        vec!["hello.txt", "old", "vlang", "vlang/hello.v"]
+    );
+    assert_eq!(
+        *compared.lock().unwrap(),
+        vec![
+            ("hello.txt".to_string(), false),
+            ("vlang/hello.v".to_string(), true)
+        ]
+    );
+}
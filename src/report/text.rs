@@ -0,0 +1,122 @@
+//! Plain text renderer
+use super::{DiffReport, Difference};
+
+/// Render the report as the list of differences, with the same format than `diff -rq`,
+/// followed by a summary with the number of differences by kind. The modified files
+/// compared by chunks show how many bytes changed
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::report::{to_text, DiffReport, Difference};
+///
+/// let report = DiffReport {
+///     dir: "./mocks/dir_one".to_string(),
+///     dir_comp: "./mocks/dir_three".to_string(),
+///     differences: vec![Difference::Added("hello.js".to_string())],
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     to_text(&report),
+///     "Only in ./mocks/dir_three: hello.js\n1 difference: 1 added, 0 removed, 0 modified, 0 renamed\n"
+/// );
+/// ```
+pub fn to_text(report: &DiffReport) -> String {
+    if report.is_equal() {
+        return format!(
+            "No differences between {} and {}\n",
+            report.dir, report.dir_comp
+        );
+    }
+
+    let count =
+        |kind: fn(&Difference) -> bool| report.differences.iter().filter(|d| kind(d)).count();
+    let total = report.differences.len();
+    let mut lines = String::new();
+    for difference in &report.differences {
+        lines.push_str(&report.line(difference));
+        if let Some(change) = report.changes.get(difference.path()) {
+            lines.push_str(&format!(
+                ", {} of {} bytes changed",
+                change.changed_bytes, change.len_comp
+            ));
+        }
+        lines.push('\n');
+    }
+
+    let relinked = count(|d| matches!(d, Difference::Relinked(_)));
+
+    format!(
+        "{}{} difference{}: {} added, {} removed, {} modified, {} renamed{}\n",
+        lines,
+        total,
+        if total == 1 { "" } else { "s" },
+        count(|d| matches!(d, Difference::Added(_))),
+        count(|d| matches!(d, Difference::Removed(_))),
+        count(|d| matches!(d, Difference::Modified(_))),
+        count(|d| matches!(d, Difference::Renamed { .. })),
+        if relinked > 0 {
+            format!(", {} relinked", relinked)
+        } else {
+            String::new()
+        },
+    )
+}
+
+#[test]
+fn should_render_summary_of_differences() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_four".to_string(),
+        differences: vec![
+            Difference::Modified("vlang/hello.v".to_string()),
+            Difference::Removed("vlang/purpose".to_string()),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        to_text(&report),
+        "Files dir_one/vlang/hello.v and dir_four/vlang/hello.v differ\n\
+         Only in dir_one/vlang: purpose\n\
+         2 differences: 0 added, 1 removed, 1 modified, 0 renamed\n"
+    );
+}
+
+#[test]
+fn should_render_no_differences() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        to_text(&report),
+        "No differences between dir_one and dir_two\n"
+    );
+}
+
+#[test]
+fn should_render_changed_bytes() {
+    let mut report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![Difference::Modified("big.bin".to_string())],
+        ..Default::default()
+    };
+    report.changes.insert(
+        "big.bin".to_string(),
+        crate::chunk::ChunkDiff {
+            len_comp: 4096,
+            changed_bytes: 512,
+            ..Default::default()
+        },
+    );
+
+    assert!(to_text(&report).starts_with(
+        "Files dir_one/big.bin and dir_two/big.bin differ, 512 of 4096 bytes changed\n"
+    ));
+}
@@ -0,0 +1,96 @@
+//! Unified diff renderer, compatible with `diff -ru` and `patch`
+use super::{join, DiffReport, Difference};
+
+/// Render the report like `diff -ru`: the lines changed in the text files compared with
+/// `DirDiff::report_text_diffs` as unified diffs which `patch -p1` can apply, and the
+/// entries only in one of both directories as `Only in` lines. The renamed files are
+/// only in one directory each and the other modified files, binary or not compared by
+/// lines, are described like `diff -rq`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{lines::hunks, report::{to_unified, DiffReport, Difference}};
+///
+/// let mut report = DiffReport {
+///     dir: "dir_one".to_string(),
+///     dir_comp: "dir_two".to_string(),
+///     differences: vec![
+///         Difference::Modified("hello.v".to_string()),
+///         Difference::Added("hello.js".to_string()),
+///     ],
+///     ..Default::default()
+/// };
+/// report.text_diffs.insert("hello.v".to_string(), hunks("old\n", "new\n", 3));
+///
+/// assert_eq!(
+///     to_unified(&report),
+///     "Only in dir_two: hello.js\n\
+///      diff -ru dir_one/hello.v dir_two/hello.v\n\
+///      --- dir_one/hello.v\n\
+///      +++ dir_two/hello.v\n\
+///      @@ -1 +1 @@\n\
+///      -old\n\
+///      +new\n"
+/// );
+/// ```
+pub fn to_unified(report: &DiffReport) -> String {
+    // `diff -r` walks both directories in the order of the paths
+    let mut entries: Vec<(&str, String)> = vec![];
+    for difference in &report.differences {
+        match difference {
+            Difference::Modified(path) => match report.text_diffs.get(path) {
+                Some(hunks) if hunks.is_empty() => {}
+                Some(hunks) => {
+                    let (file, file_comp) = (join(&report.dir, path), join(&report.dir_comp, path));
+                    let mut diff = format!(
+                        "diff -ru {} {}\n--- {}\n+++ {}\n",
+                        file, file_comp, file, file_comp
+                    );
+                    for hunk in hunks {
+                        diff.push_str(&hunk.to_string());
+                    }
+                    entries.push((path, diff));
+                }
+                None => entries.push((path, format!("{}\n", report.line(difference)))),
+            },
+            Difference::Renamed { from, to, .. } => {
+                let removed = Difference::Removed(from.clone());
+                let added = Difference::Added(to.clone());
+                entries.push((from, format!("{}\n", report.line(&removed))));
+                entries.push((to, format!("{}\n", report.line(&added))));
+            }
+            // the content is the same, `diff` doesn't know the hard links
+            Difference::Relinked(_) => {}
+            _ => entries.push((difference.path(), format!("{}\n", report.line(difference)))),
+        }
+    }
+    entries.sort_by_key(|(path, _)| *path);
+
+    entries.into_iter().map(|(_, lines)| lines).collect()
+}
+
+#[test]
+fn should_render_the_renamed_files_in_order() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![
+            Difference::Modified("logo.png".to_string()),
+            Difference::Renamed {
+                from: "a/old.txt".to_string(),
+                to: "z/new.txt".to_string(),
+                similarity: 1.0,
+            },
+            Difference::Relinked("b.bin".to_string()),
+        ],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        to_unified(&report),
+        "Only in dir_one/a: old.txt\n\
+         Files dir_one/logo.png and dir_two/logo.png differ\n\
+         Only in dir_two/z: new.txt\n"
+    );
+}
@@ -0,0 +1,23 @@
+//! Async runtime through which the local files are read: [tokio](https://tokio.rs) with
+//! the default `tokio` feature, or [async-std](https://async.rs) or
+//! [smol](https://github.com/smol-rs/smol) with their features and without the default
+//! features. If more than one is enabled tokio is preferred, then async-std. The readers
+//! returned by `DiffFs::open` implement the `AsyncRead` of the runtime, the one of tokio
+//! or the one of the `futures` crate for the others
+#[cfg(not(any(feature = "tokio", feature = "async-std", feature = "smol")))]
+compile_error!("spielrs_diff needs the `tokio`, the `async-std` or the `smol` feature");
+
+#[cfg(feature = "tokio")]
+mod with_tokio;
+#[cfg(feature = "tokio")]
+pub use with_tokio::*;
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+mod with_async_std;
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+pub use with_async_std::*;
+
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+mod with_smol;
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+pub use with_smol::*;
@@ -0,0 +1,101 @@
+//! [smol](https://github.com/smol-rs/smol) runtime
+use crate::vfs::VfsReader;
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+pub use futures::io::{AsyncRead, AsyncReadExt};
+
+/// Reader of a content already in memory
+pub(crate) fn reader(content: Vec<u8>) -> VfsReader {
+    Box::new(futures::io::Cursor::new(content))
+}
+
+/// Run the future until its end or until the duration is elapsed, `None` in that case
+pub(crate) async fn timeout<T>(duration: Duration, future: impl Future<Output = T>) -> Option<T> {
+    smol::future::or(async { Some(future.await) }, async {
+        smol::Timer::after(duration).await;
+        None
+    })
+    .await
+}
+
+/// Wait for the duration without blocking the thread
+pub(crate) async fn sleep(duration: Duration) {
+    smol::Timer::after(duration).await;
+}
+
+/// Run the blocking function in the thread pool of smol, used by the `mmap` and `sparse`
+/// features
+#[allow(dead_code)]
+pub(crate) async fn spawn_blocking<T: Send + 'static>(
+    function: impl FnOnce() -> T + Send + 'static,
+) -> io::Result<T> {
+    Ok(smol::unblock(function).await)
+}
+
+/// Run the future to its end blocking the current thread
+pub(crate) fn block_on<T>(future: impl Future<Output = T>) -> T {
+    smol::block_on(future)
+}
+
+/// Operations of the local filesystem used by `LocalFs` and the hash cache
+pub(crate) mod fs {
+    use crate::vfs::VfsReader;
+    use futures::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+    use futures::TryStreamExt;
+    use smol::fs;
+    use std::fs::Metadata;
+    use std::io;
+
+    /// Name, path and metadata, without following the symbolic links, of the entries of
+    /// the directory
+    pub(crate) async fn read_dir(path: &str) -> io::Result<Vec<(String, String, Metadata)>> {
+        let mut entries = fs::read_dir(path).await?;
+        let mut list = vec![];
+
+        while let Some(entry) = entries.try_next().await? {
+            let metadata = fs::symlink_metadata(entry.path()).await?;
+            list.push((
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path().to_string_lossy().into_owned(),
+                metadata,
+            ));
+        }
+
+        Ok(list)
+    }
+
+    pub(crate) async fn metadata(path: &str) -> io::Result<Metadata> {
+        fs::metadata(path).await
+    }
+
+    pub(crate) async fn read(path: &str) -> io::Result<Vec<u8>> {
+        fs::read(path).await
+    }
+
+    pub(crate) async fn write(path: &str, content: &[u8]) -> io::Result<()> {
+        fs::write(path, content).await
+    }
+
+    pub(crate) async fn rename(from: &str, to: &str) -> io::Result<()> {
+        fs::rename(from, to).await
+    }
+
+    pub(crate) async fn open(path: &str) -> io::Result<VfsReader> {
+        Ok(Box::new(fs::File::open(path).await?))
+    }
+
+    pub(crate) async fn read_link(path: &str) -> io::Result<String> {
+        Ok(fs::read_link(path).await?.to_string_lossy().into_owned())
+    }
+
+    pub(crate) async fn read_range(path: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut file = fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        let mut content: Vec<u8> = vec![];
+        file.take(len).read_to_end(&mut content).await?;
+
+        Ok(content)
+    }
+}
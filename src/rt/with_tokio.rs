@@ -0,0 +1,191 @@
+//! [tokio](https://tokio.rs) runtime. In WASI tokio can't read files, so they are read
+//! through std blocking the only thread of the sandbox, from its preopened directories
+use crate::vfs::VfsReader;
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+pub use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Reader of a content already in memory
+pub(crate) fn reader(content: Vec<u8>) -> VfsReader {
+    Box::new(io::Cursor::new(content))
+}
+
+/// Run the future until its end or until the duration is elapsed, `None` in that case
+pub(crate) async fn timeout<T>(duration: Duration, future: impl Future<Output = T>) -> Option<T> {
+    tokio::time::timeout(duration, future).await.ok()
+}
+
+/// Wait for the duration without blocking the thread
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Run the blocking function in the threads of the runtime for the blocking tasks, used
+/// by the `mmap` and `sparse` features
+#[cfg(not(target_family = "wasm"))]
+#[allow(dead_code)]
+pub(crate) async fn spawn_blocking<T: Send + 'static>(
+    function: impl FnOnce() -> T + Send + 'static,
+) -> io::Result<T> {
+    tokio::task::spawn_blocking(function)
+        .await
+        .map_err(io::Error::other)
+}
+
+#[cfg(target_family = "wasm")]
+#[allow(dead_code)]
+pub(crate) async fn spawn_blocking<T: Send + 'static>(
+    function: impl FnOnce() -> T + Send + 'static,
+) -> io::Result<T> {
+    Ok(function())
+}
+
+/// Run the future to its end in a new current thread runtime, with the io and the
+/// timers enabled
+pub(crate) fn block_on<T>(future: impl Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("the runtime of the blocking comparison can't be created")
+        .block_on(future)
+}
+
+/// Operations of the local filesystem used by `LocalFs` and the hash cache
+#[cfg(not(target_family = "wasm"))]
+pub(crate) mod fs {
+    use crate::vfs::VfsReader;
+    use std::fs::Metadata;
+    use std::io;
+    use tokio::fs;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+    /// Name, path and metadata, without following the symbolic links, of the entries of
+    /// the directory
+    pub(crate) async fn read_dir(path: &str) -> io::Result<Vec<(String, String, Metadata)>> {
+        let mut entries = fs::read_dir(path).await?;
+        let mut list = vec![];
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = fs::symlink_metadata(entry.path()).await?;
+            list.push((
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path().to_string_lossy().into_owned(),
+                metadata,
+            ));
+        }
+
+        Ok(list)
+    }
+
+    pub(crate) async fn metadata(path: &str) -> io::Result<Metadata> {
+        fs::metadata(path).await
+    }
+
+    pub(crate) async fn read(path: &str) -> io::Result<Vec<u8>> {
+        fs::read(path).await
+    }
+
+    pub(crate) async fn write(path: &str, content: &[u8]) -> io::Result<()> {
+        fs::write(path, content).await
+    }
+
+    pub(crate) async fn rename(from: &str, to: &str) -> io::Result<()> {
+        fs::rename(from, to).await
+    }
+
+    pub(crate) async fn open(path: &str) -> io::Result<VfsReader> {
+        Ok(Box::new(fs::File::open(path).await?))
+    }
+
+    pub(crate) async fn read_link(path: &str) -> io::Result<String> {
+        Ok(fs::read_link(path).await?.to_string_lossy().into_owned())
+    }
+
+    pub(crate) async fn read_range(path: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut file = fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        let mut content: Vec<u8> = vec![];
+        file.take(len).read_to_end(&mut content).await?;
+
+        Ok(content)
+    }
+}
+
+#[cfg(target_family = "wasm")]
+pub(crate) mod fs {
+    use crate::vfs::VfsReader;
+    use std::fs::{self, File, Metadata};
+    use std::io::{self, Read, Seek, SeekFrom};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, ReadBuf};
+
+    /// Name, path and metadata, without following the symbolic links, of the entries of
+    /// the directory
+    pub(crate) async fn read_dir(path: &str) -> io::Result<Vec<(String, String, Metadata)>> {
+        let mut list = vec![];
+
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = fs::symlink_metadata(entry.path())?;
+            list.push((
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path().to_string_lossy().into_owned(),
+                metadata,
+            ));
+        }
+
+        Ok(list)
+    }
+
+    pub(crate) async fn metadata(path: &str) -> io::Result<Metadata> {
+        fs::metadata(path)
+    }
+
+    pub(crate) async fn read(path: &str) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    pub(crate) async fn write(path: &str, content: &[u8]) -> io::Result<()> {
+        fs::write(path, content)
+    }
+
+    pub(crate) async fn rename(from: &str, to: &str) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    pub(crate) async fn open(path: &str) -> io::Result<VfsReader> {
+        Ok(Box::new(BlockingReader(File::open(path)?)))
+    }
+
+    pub(crate) async fn read_link(path: &str) -> io::Result<String> {
+        Ok(fs::read_link(path)?.to_string_lossy().into_owned())
+    }
+
+    pub(crate) async fn read_range(path: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut content: Vec<u8> = vec![];
+        file.take(len).read_to_end(&mut content)?;
+
+        Ok(content)
+    }
+
+    /// File read through std, every poll reads without waiting for anything else
+    struct BlockingReader(File);
+
+    impl AsyncRead for BlockingReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let read = self.0.read(buf.initialize_unfilled())?;
+            buf.advance(read);
+
+            Poll::Ready(Ok(()))
+        }
+    }
+}
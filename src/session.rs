@@ -0,0 +1,406 @@
+//! Repeated comparisons of the same directories, like the polling loop of a watcher
+use crate::cache::HashCache;
+use crate::diff::DirDiff;
+use crate::report::{DiffReport, Difference};
+use crate::tree::{FsEvent, Tree};
+use crate::vfs::{DiffFs, LocalFs};
+use crate::{report_trees, rt, try_build_trees};
+use futures::stream::{self, Stream, StreamExt};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Differences which changed since the previous run of a `DiffSession`
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct SessionDelta {
+    /// differences found now which were not found in the previous run
+    pub appeared: Vec<Difference>,
+    /// differences found in the previous run which are not found now
+    pub resolved: Vec<Difference>,
+    /// report of the current run
+    pub report: DiffReport,
+}
+
+impl SessionDelta {
+    /// Return true if the differences are the same than in the previous run
+    pub fn is_unchanged(&self) -> bool {
+        self.appeared.is_empty() && self.resolved.is_empty()
+    }
+}
+
+/// Compare the same directories again and again remembering the trees and the digests of
+/// the previous run, only the files whose size or modification time changed are read again.
+/// Every run returns what changed in the differences since the previous one
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::{diff::DirDiff, session::DiffSession};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut session = DiffSession::new(DirDiff {
+///         dir: "./mocks/dir_one".to_string(),
+///         dir_comp: "./mocks/dir_five".to_string(),
+///         ..Default::default()
+///     });
+///
+///     let first = session.run().await;
+///     assert_eq!(first.appeared, first.report.differences);
+///
+///     // nothing was modified between both runs
+///     assert!(session.run().await.is_unchanged());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DiffSession {
+    options: DirDiff,
+    trees: Option<(Vec<Tree>, Vec<Tree>)>,
+    differences: Vec<Difference>,
+}
+
+impl DiffSession {
+    /// Create a session comparing the directories of the options, a `HashCache` is
+    /// created if the options don't have one
+    pub fn new(mut options: DirDiff) -> Self {
+        options
+            .hash_cache
+            .get_or_insert_with(|| Arc::new(HashCache::new()));
+
+        DiffSession {
+            options,
+            trees: None,
+            differences: vec![],
+        }
+    }
+
+    /// Compare the local directories again
+    pub async fn run(&mut self) -> SessionDelta {
+        self.run_with(&LocalFs, &LocalFs).await
+    }
+
+    /// Compare again the directories read from any `DiffFs`
+    pub async fn run_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+        &mut self,
+        fs: &F,
+        fs_comp: &C,
+    ) -> SessionDelta {
+        let started = Instant::now();
+        let (tree, tree_comp, errors, truncated) = try_build_trees(fs, fs_comp, &self.options)
+            .await
+            .unwrap_or_else(|error| panic!("{}", error));
+        let build_duration = started.elapsed();
+        let mut report = report_trees(fs, &tree, fs_comp, &tree_comp, errors, &self.options)
+            .await
+            .unwrap_or_else(|error| panic!("{}", error));
+        report.metrics.build_duration = build_duration;
+        report.truncated = truncated;
+
+        let appeared: Vec<Difference> = report
+            .differences
+            .iter()
+            .filter(|difference| !self.differences.contains(difference))
+            .cloned()
+            .collect();
+        let resolved: Vec<Difference> = self
+            .differences
+            .iter()
+            .filter(|difference| !report.differences.contains(difference))
+            .cloned()
+            .collect();
+        self.differences = report.differences.clone();
+        self.trees = Some((tree, tree_comp));
+
+        SessionDelta {
+            appeared,
+            resolved,
+            report,
+        }
+    }
+
+    /// Compare the local directories again after every burst of events, see `watch_with`
+    pub fn watch<'a, S: Stream<Item = FsEvent> + Unpin + 'a>(
+        &'a mut self,
+        events: S,
+        debounce: Duration,
+    ) -> impl Stream<Item = SessionDelta> + 'a {
+        self.watch_with(&LocalFs, &LocalFs, events, debounce)
+    }
+
+    /// Compare the directories again after every burst of events of a watcher, like the
+    /// ones of [notify](https://docs.rs/notify) converted to `FsEvent`. The events are
+    /// coalesced until none is received during `debounce`, so the save of an editor or a
+    /// sync writing many files is compared once. The stream ends with the events
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::{diff::DirDiff, session::DiffSession, tree::FsEvent, vfs::LocalFs};
+    /// use futures::{channel::mpsc, StreamExt};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut session = DiffSession::new(DirDiff {
+    ///         dir: "./mocks/dir_one".to_string(),
+    ///         dir_comp: "./mocks/dir_five".to_string(),
+    ///         ..Default::default()
+    ///     });
+    ///     let (sender, receiver) = mpsc::unbounded();
+    ///     // sent by the watcher of the directories
+    ///     for _ in 0..3 {
+    ///         sender.unbounded_send(FsEvent::Modified("./mocks/dir_five/hello.txt".to_string())).unwrap();
+    ///     }
+    ///     drop(sender);
+    ///
+    ///     let deltas = session.watch_with(&LocalFs, &LocalFs, receiver, Duration::from_millis(50));
+    ///     assert_eq!(deltas.count().await, 1);
+    /// }
+    /// ```
+    pub fn watch_with<'a, F, C, S>(
+        &'a mut self,
+        fs: &'a F,
+        fs_comp: &'a C,
+        events: S,
+        debounce: Duration,
+    ) -> impl Stream<Item = SessionDelta> + 'a
+    where
+        F: DiffFs + ?Sized,
+        C: DiffFs + ?Sized,
+        S: Stream<Item = FsEvent> + Unpin + 'a,
+    {
+        stream::unfold((self, events), move |(session, mut events)| async move {
+            next_burst(&mut events, debounce).await?;
+            let delta = session.run_with(fs, fs_comp).await;
+
+            Some((delta, (session, events)))
+        })
+    }
+
+    /// Compare the local directories every `interval`, see `poll_with`
+    pub fn poll(&mut self, interval: Duration) -> impl Stream<Item = SessionDelta> + '_ {
+        self.poll_with(&LocalFs, &LocalFs, interval)
+    }
+
+    /// Compare the directories every `interval` for the sources without events of
+    /// changes, like NFS or the volumes of some containers. Only the files whose size or
+    /// modification time changed are read again. The first run is returned, then only
+    /// the runs whose differences changed, like the ones of `watch_with`. The stream
+    /// never ends
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::{diff::DirDiff, session::DiffSession};
+    /// use futures::StreamExt;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut session = DiffSession::new(DirDiff {
+    ///         dir: "./mocks/dir_one".to_string(),
+    ///         dir_comp: "./mocks/dir_five".to_string(),
+    ///         ..Default::default()
+    ///     });
+    ///
+    ///     let mut deltas = Box::pin(session.poll(Duration::from_secs(5)));
+    ///     let first = deltas.next().await.unwrap();
+    ///     assert_eq!(first.appeared, first.report.differences);
+    /// }
+    /// ```
+    pub fn poll_with<'a, F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+        &'a mut self,
+        fs: &'a F,
+        fs_comp: &'a C,
+        interval: Duration,
+    ) -> impl Stream<Item = SessionDelta> + 'a {
+        stream::unfold((self, true), move |(session, first)| async move {
+            if first {
+                let delta = session.run_with(fs, fs_comp).await;
+                return Some((delta, (session, false)));
+            }
+            loop {
+                rt::sleep(interval).await;
+                let delta = session.run_with(fs, fs_comp).await;
+                if !delta.is_unchanged() {
+                    return Some((delta, (session, false)));
+                }
+            }
+        })
+    }
+
+    /// Trees of both directories built in the last run, `None` before the first one
+    pub fn trees(&self) -> Option<(&[Tree], &[Tree])> {
+        self.trees
+            .as_ref()
+            .map(|(tree, tree_comp)| (tree.as_slice(), tree_comp.as_slice()))
+    }
+
+    /// Forget the previous run, the next one reads all the files again
+    pub fn reset(&mut self) {
+        if let Some(hash_cache) = &self.options.hash_cache {
+            hash_cache.clear();
+        }
+        self.trees = None;
+        self.differences.clear();
+    }
+}
+
+/// Wait for the next event and return it with the ones received until none is received
+/// during `debounce`, without repeating the same event. `None` when the events end
+/// before the next one
+pub async fn next_burst<S: Stream<Item = FsEvent> + Unpin>(
+    events: &mut S,
+    debounce: Duration,
+) -> Option<Vec<FsEvent>> {
+    let mut burst = vec![events.next().await?];
+    while let Some(Some(event)) = rt::timeout(debounce, events.next()).await {
+        if !burst.contains(&event) {
+            burst.push(event);
+        }
+    }
+
+    Some(burst)
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_the_changes_since_the_previous_run() {
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now();
+    let later = now + Duration::from_secs(10);
+    let mut session = DiffSession::new(DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        ..Default::default()
+    });
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file_modified("dir_one/hello.txt", "Hello world", now)
+        .file_modified("dir_one/vlang/hello.v", "fn main() {}", now)
+        .file_modified("dir_two/hello.txt", "Hello vlang", now)
+        .file_modified("dir_two/vlang/hello.v", "fn main() {}", now)
+        .build();
+    let first = session.run_with(&fs, &fs).await;
+    assert_eq!(
+        first.appeared,
+        vec![Difference::Modified("hello.txt".to_string())]
+    );
+    assert_eq!(first.report.stats.bytes_compared, 46);
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file_modified("dir_one/hello.txt", "Hello world", now)
+        .file_modified("dir_one/vlang/hello.v", "fn main() {}", now)
+        .file_modified("dir_two/hello.txt", "Hello world", later)
+        .file_modified("dir_two/vlang/hello.v", "fn main() {}", now)
+        .file_modified("dir_two/new.txt", "new", now)
+        .build();
+    let second = session.run_with(&fs, &fs).await;
+    assert_eq!(
+        second.appeared,
+        vec![Difference::Added("new.txt".to_string())]
+    );
+    assert_eq!(
+        second.resolved,
+        vec![Difference::Modified("hello.txt".to_string())]
+    );
+    // only hello.txt changed in dir_two since the previous run
+    assert_eq!(second.report.stats.bytes_compared, 22);
+    assert_eq!(Tree::stats(session.trees().unwrap().1).files, 3);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_coalesce_the_bursts_of_events() {
+    use futures::channel::mpsc;
+
+    let (sender, receiver) = mpsc::unbounded();
+    let mut session = DiffSession::new(DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        ..Default::default()
+    });
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/hello.txt", "Hello vlang")
+        .build();
+    let events = async {
+        let modified = FsEvent::Modified("dir_two/hello.txt".to_string());
+        for _ in 0..5 {
+            sender.unbounded_send(modified.clone()).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        sender.unbounded_send(modified).unwrap();
+        sender
+            .unbounded_send(FsEvent::Created("dir_two/new.txt".to_string()))
+            .unwrap();
+        drop(sender);
+    };
+
+    let (deltas, _) = futures::join!(
+        session
+            .watch_with(&fs, &fs, receiver, Duration::from_millis(50))
+            .collect::<Vec<SessionDelta>>(),
+        events
+    );
+
+    // one comparison by burst, the second one found nothing new
+    assert_eq!(deltas.len(), 2);
+    assert_eq!(
+        deltas[0].appeared,
+        vec![Difference::Modified("hello.txt".to_string())]
+    );
+    assert!(deltas[1].is_unchanged());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_poll_until_the_differences_change() {
+    use crate::vfs::mem::MemFs;
+    use std::sync::RwLock;
+
+    // source whose files are replaced while it is polled
+    struct Swapped(RwLock<MemFs>);
+
+    #[async_trait::async_trait]
+    impl DiffFs for Swapped {
+        async fn read_dir(&self, path: &str) -> std::io::Result<Vec<crate::vfs::VfsEntry>> {
+            let fs = self.0.read().unwrap().clone();
+            fs.read_dir(path).await
+        }
+
+        async fn metadata(&self, path: &str) -> std::io::Result<crate::vfs::VfsMetadata> {
+            let fs = self.0.read().unwrap().clone();
+            fs.metadata(path).await
+        }
+
+        async fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+            let fs = self.0.read().unwrap().clone();
+            fs.read(path).await
+        }
+    }
+
+    let fs = Swapped(RwLock::new(
+        MemFs::builder()
+            .file("dir_one/hello.txt", "Hello world")
+            .file("dir_two/hello.txt", "Hello world")
+            .build(),
+    ));
+    let mut session = DiffSession::new(DirDiff {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        ..Default::default()
+    });
+    let mut deltas = Box::pin(session.poll_with(&fs, &fs, Duration::from_millis(10)));
+
+    assert!(deltas.next().await.unwrap().report.is_equal());
+    *fs.0.write().unwrap() = MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_two/hello.txt", "Hello world")
+        .file("dir_two/new.txt", "new")
+        .build();
+    assert_eq!(
+        deltas.next().await.unwrap().appeared,
+        vec![Difference::Added("new.txt".to_string())]
+    );
+}
@@ -0,0 +1,74 @@
+//! Ratio of similarity between the contents of two files
+use crate::hash::content_hash;
+use std::collections::HashMap;
+use std::str;
+
+/// Size of the chunks compared when a content is not text
+const CHUNK_SIZE: usize = 64;
+
+/// Hashes of the lines, or of chunks of `CHUNK_SIZE` bytes if any content is not utf-8
+fn segments<'a>(content: &'a [u8], text: bool) -> Box<dyn Iterator<Item = u64> + 'a> {
+    if text {
+        Box::new(
+            content
+                .split(|byte| *byte == b'\n')
+                .map(|line| content_hash(line.strip_suffix(b"\r").unwrap_or(line))),
+        )
+    } else {
+        Box::new(content.chunks(CHUNK_SIZE).map(content_hash))
+    }
+}
+
+/// Return how similar are two contents from 0.0 to 1.0, comparing them line by line
+/// if both are text, or by chunks of bytes otherwise. It is the number of lines or
+/// chunks present in both, in any order, divided by the average of lines or chunks
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::similarity::content_similarity;
+///
+/// assert_eq!(content_similarity(b"a\nb\nc\nd", b"a\nb\nc\ne"), 0.75);
+/// assert_eq!(content_similarity(b"a\nb", b"a\nb"), 1.0);
+/// ```
+pub fn content_similarity(content: &[u8], content_comp: &[u8]) -> f64 {
+    if content == content_comp {
+        return 1.0;
+    }
+
+    let text = str::from_utf8(content).is_ok() && str::from_utf8(content_comp).is_ok();
+    let mut counts: HashMap<u64, usize> = HashMap::new();
+    let mut total: usize = 0;
+    for segment in segments(content, text) {
+        *counts.entry(segment).or_default() += 1;
+        total += 1;
+    }
+
+    let mut common: usize = 0;
+    for segment in segments(content_comp, text) {
+        total += 1;
+        if let Some(count) = counts.get_mut(&segment) {
+            if *count > 0 {
+                *count -= 1;
+                common += 1;
+            }
+        }
+    }
+
+    if total == 0 {
+        1.0
+    } else {
+        (common * 2) as f64 / total as f64
+    }
+}
+
+#[test]
+fn should_compare_binary_contents_by_chunks() {
+    let content: Vec<u8> = (0..=255u8).cycle().take(CHUNK_SIZE * 4).collect();
+    let mut content_comp = content.clone();
+    content_comp[0] = 0xff;
+    content_comp[1] = 0xfe;
+
+    assert_eq!(content_similarity(&content, &content_comp), 0.75);
+    assert_eq!(content_similarity(&content, b""), 0.0);
+}
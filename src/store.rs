@@ -0,0 +1,340 @@
+//! Digests and metadata of the files of a tree kept in a SQLite database through
+//! [rusqlite](https://crates.io/crates/rusqlite), to know what changed since the last audit
+//! of trees too large to keep a snapshot of them in memory
+use crate::error::DiffError;
+use crate::hash::ContentHasher;
+use crate::report::Difference;
+use crate::vfs::{DiffFs, EntryKind};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::io;
+use std::time::UNIX_EPOCH;
+
+pub use rusqlite;
+
+/// Tables of the store, the entries are by root and path and keep the id of the last
+/// audit which found them
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS audits (
+    id INTEGER PRIMARY KEY,
+    root TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS entries (
+    root TEXT NOT NULL,
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    modified_secs INTEGER,
+    modified_nanos INTEGER,
+    digest BLOB,
+    audit INTEGER NOT NULL,
+    PRIMARY KEY (root, path)
+);
+";
+
+/// Entry recorded by an audit
+struct Recorded {
+    kind: String,
+    size: u64,
+    modified: Option<(i64, i64)>,
+    digest: Option<Vec<u8>>,
+}
+
+/// Store of the kind, size, modification time and digest of every entry of the audited
+/// trees. Each audit walks the tree, only reads the files whose size or modification time
+/// changed since the previous audit of the same root and returns the differences with it,
+/// the entries of the previous audit are `dir` and the current ones are `dir_comp`.
+///
+/// The database is written while the tree is walked, only the entries of a directory are
+/// in memory. The digests are computed with the hasher of the audit, use the same stable
+/// hasher in all of them, like `Blake3Hasher`, `DefaultContentHasher` is not stable
+/// between builds
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use spielrs_diff::{hash::Blake3Hasher, store::ChecksumStore, vfs::LocalFs};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut store = ChecksumStore::open("audits.sqlite3").unwrap();
+///
+///     for difference in store.audit(&LocalFs, "/srv/data", &Blake3Hasher).await.unwrap() {
+///         println!("{:?}", difference);
+///     }
+/// }
+/// ```
+pub struct ChecksumStore {
+    connection: Connection,
+}
+
+impl ChecksumStore {
+    /// Open the database at the path, creating it if it doesn't exist
+    pub fn open(path: &str) -> io::Result<Self> {
+        ChecksumStore::from_connection(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Store in memory, lost when it is dropped
+    pub fn open_in_memory() -> io::Result<Self> {
+        ChecksumStore::from_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    /// Use an already opened connection, the tables are created if they don't exist
+    pub fn from_connection(connection: Connection) -> io::Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+
+        Ok(ChecksumStore { connection })
+    }
+
+    /// Number of entries recorded by the last audit of the root
+    pub fn len(&self, root: &str) -> io::Result<usize> {
+        self.connection
+            .query_row(
+                "SELECT COUNT(*) FROM entries WHERE root = ?1",
+                params![root],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as usize)
+            .map_err(sqlite_error)
+    }
+
+    /// Digest recorded by the last audit of the root for the file, relative to the root
+    pub fn digest(&self, root: &str, path: &str) -> io::Result<Option<Vec<u8>>> {
+        self.connection
+            .query_row(
+                "SELECT digest FROM entries WHERE root = ?1 AND path = ?2",
+                params![root, path],
+                |row| row.get::<_, Option<Vec<u8>>>(0),
+            )
+            .optional()
+            .map(Option::flatten)
+            .map_err(sqlite_error)
+    }
+
+    /// Walk the tree of `root` in the source, record its entries and return the entries
+    /// added, removed and modified since the previous audit of the root, ordered by path.
+    /// All the entries are added in the first audit. The symbolic links are not followed,
+    /// they are modified when their target changes. Nothing is recorded if an entry can't
+    /// be read, the audit is a transaction rolled back on the first error
+    pub async fn audit<F: DiffFs + ?Sized>(
+        &mut self,
+        fs: &F,
+        root: &str,
+        hasher: &dyn ContentHasher,
+    ) -> Result<Vec<Difference>, DiffError> {
+        let error = |error: rusqlite::Error| DiffError::from_io(root, sqlite_error(error));
+
+        self.connection.execute_batch("BEGIN").map_err(error)?;
+        match self.record(fs, root, hasher).await {
+            Ok(differences) => {
+                self.connection.execute_batch("COMMIT").map_err(error)?;
+                Ok(differences)
+            }
+            Err(failure) => {
+                self.connection.execute_batch("ROLLBACK").map_err(error)?;
+                Err(failure)
+            }
+        }
+    }
+
+    // `&mut self` keeps the future `Send`, the connection is not `Sync`
+    async fn record<F: DiffFs + ?Sized>(
+        &mut self,
+        fs: &F,
+        root: &str,
+        hasher: &dyn ContentHasher,
+    ) -> Result<Vec<Difference>, DiffError> {
+        let failed = |path: &str, error| DiffError::from_io(path, sqlite_error(error));
+
+        self.connection
+            .execute("INSERT INTO audits (root) VALUES (?1)", params![root])
+            .map_err(|error| failed(root, error))?;
+        let audit = self.connection.last_insert_rowid();
+
+        let mut differences: Vec<Difference> = vec![];
+        let mut dirs: Vec<String> = vec![root.to_string()];
+        while let Some(dir) = dirs.pop() {
+            let entries = fs
+                .read_dir(&dir)
+                .await
+                .map_err(|error| DiffError::from_io(&dir, error))?;
+            for entry in entries {
+                let relative = entry
+                    .path
+                    .strip_prefix(root)
+                    .unwrap_or(&entry.path)
+                    .trim_start_matches(['/', '\\'])
+                    .to_string();
+                let previous = self
+                    .recorded(root, &relative)
+                    .map_err(|error| failed(&entry.path, error))?;
+
+                let (modified, digest) = match entry.kind {
+                    EntryKind::File => {
+                        let metadata = fs
+                            .metadata(&entry.path)
+                            .await
+                            .map_err(|error| DiffError::from_io(&entry.path, error))?;
+                        let modified = metadata.modified.and_then(|modified| {
+                            let since = modified.duration_since(UNIX_EPOCH).ok()?;
+                            Some((since.as_secs() as i64, since.subsec_nanos() as i64))
+                        });
+                        // the file didn't change if its stamp is the recorded one
+                        let digest = match &previous {
+                            Some(previous)
+                                if modified.is_some()
+                                    && previous.kind == kind_name(entry.kind)
+                                    && previous.size == metadata.len
+                                    && previous.modified == modified =>
+                            {
+                                previous.digest.clone()
+                            }
+                            _ => {
+                                let content = fs
+                                    .read(&entry.path)
+                                    .await
+                                    .map_err(|error| DiffError::from_io(&entry.path, error))?;
+                                Some(hasher.hash(&content))
+                            }
+                        };
+                        (modified, digest)
+                    }
+                    EntryKind::Symlink => {
+                        let target = fs
+                            .read_link(&entry.path)
+                            .await
+                            .map_err(|error| DiffError::from_io(&entry.path, error))?;
+                        (None, Some(hasher.hash(target.as_bytes())))
+                    }
+                    EntryKind::Dir => {
+                        dirs.push(entry.path.clone());
+                        (None, None)
+                    }
+                    _ => (None, None),
+                };
+
+                match &previous {
+                    None => differences.push(Difference::Added(relative.clone())),
+                    Some(previous)
+                        if previous.kind != kind_name(entry.kind) || previous.digest != digest =>
+                    {
+                        differences.push(Difference::Modified(relative.clone()))
+                    }
+                    Some(_) => {}
+                }
+                self.connection
+                    .execute(
+                        "INSERT OR REPLACE INTO entries
+                            (root, path, kind, size, modified_secs, modified_nanos, digest, audit)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            root,
+                            relative,
+                            kind_name(entry.kind),
+                            entry.len as i64,
+                            modified.map(|(secs, _)| secs),
+                            modified.map(|(_, nanos)| nanos),
+                            digest,
+                            audit
+                        ],
+                    )
+                    .map_err(|error| failed(&entry.path, error))?;
+            }
+        }
+
+        // the entries which weren't found by this audit were removed
+        {
+            let mut removed = self
+                .connection
+                .prepare("SELECT path FROM entries WHERE root = ?1 AND audit != ?2")
+                .map_err(|error| failed(root, error))?;
+            let paths = removed
+                .query_map(params![root, audit], |row| row.get::<_, String>(0))
+                .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+                .map_err(|error| failed(root, error))?;
+            differences.extend(paths.into_iter().map(Difference::Removed));
+        }
+        self.connection
+            .execute(
+                "DELETE FROM entries WHERE root = ?1 AND audit != ?2",
+                params![root, audit],
+            )
+            .map_err(|error| failed(root, error))?;
+        differences.sort_by(|a, b| a.path().cmp(b.path()));
+
+        Ok(differences)
+    }
+
+    fn recorded(&self, root: &str, path: &str) -> rusqlite::Result<Option<Recorded>> {
+        self.connection
+            .query_row(
+                "SELECT kind, size, modified_secs, modified_nanos, digest
+                 FROM entries WHERE root = ?1 AND path = ?2",
+                params![root, path],
+                |row| {
+                    let secs: Option<i64> = row.get(2)?;
+                    let nanos: Option<i64> = row.get(3)?;
+                    Ok(Recorded {
+                        kind: row.get(0)?,
+                        size: row.get::<_, i64>(1)? as u64,
+                        modified: secs.zip(nanos),
+                        digest: row.get(4)?,
+                    })
+                },
+            )
+            .optional()
+    }
+}
+
+/// Name of the kind of the entries in the database
+fn kind_name(kind: EntryKind) -> String {
+    format!("{:?}", kind).to_lowercase()
+}
+
+fn sqlite_error(error: rusqlite::Error) -> io::Error {
+    io::Error::other(error)
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_what_changed_since_the_last_audit() {
+    use crate::hash::DefaultContentHasher;
+    use crate::vfs::mem::MemFs;
+    use std::time::{Duration, SystemTime};
+
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let mut store = ChecksumStore::open_in_memory().unwrap();
+    let before = MemFs::builder()
+        .file_modified("data/a.txt", "a", modified)
+        .file_modified("data/logs/b.txt", "b", modified)
+        .file_modified("data/c.txt", "c", modified)
+        .build();
+    let after = MemFs::builder()
+        .file_modified("data/a.txt", "a", modified)
+        .file_modified("data/logs/b.txt", "B", modified + Duration::from_secs(1))
+        .symlink("data/current", "a.txt")
+        .build();
+
+    let first = store
+        .audit(&before, "data", &DefaultContentHasher)
+        .await
+        .unwrap();
+    let second = store
+        .audit(&after, "data", &DefaultContentHasher)
+        .await
+        .unwrap();
+
+    assert_eq!(first.len(), 4);
+    assert_eq!(
+        second,
+        vec![
+            Difference::Removed("c.txt".to_string()),
+            Difference::Added("current".to_string()),
+            Difference::Modified("logs/b.txt".to_string()),
+        ]
+    );
+    assert_eq!(store.len("data").unwrap(), 4);
+    assert_eq!(
+        store.digest("data", "logs/b.txt").unwrap(),
+        Some(DefaultContentHasher.hash(b"B"))
+    );
+}
@@ -0,0 +1,147 @@
+//! Plan of the operations which would make `dir_comp` equal to `dir`, like `rsync --dry-run`,
+//! so they can be reviewed before being applied
+use crate::report::{DiffReport, Difference};
+use std::fmt;
+
+/// Operation on `dir_comp`, the paths are relative to the root of both directories
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyncOp {
+    /// copy the entry from `dir`, the whole directory if it is one, replacing the
+    /// entry of `dir_comp` if exists
+    Copy(String),
+    /// delete the entry of `dir_comp`, the whole directory if it is one
+    Delete(String),
+}
+
+impl SyncOp {
+    /// Relative path of the entry
+    pub fn path(&self) -> &str {
+        match self {
+            SyncOp::Copy(path) | SyncOp::Delete(path) => path,
+        }
+    }
+}
+
+/// Operations which would be performed to sync `dir_comp` with `dir`, in order
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct SyncPlan {
+    /// directory copied from
+    pub dir: String,
+    /// directory updated
+    pub dir_comp: String,
+    /// operations sorted by path
+    pub ops: Vec<SyncOp>,
+}
+
+impl SyncPlan {
+    /// Return true if both directories are already in sync
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Entries which would be copied
+    pub fn copies(&self) -> Vec<&str> {
+        self.ops
+            .iter()
+            .filter(|op| matches!(op, SyncOp::Copy(_)))
+            .map(SyncOp::path)
+            .collect()
+    }
+
+    /// Entries which would be deleted
+    pub fn deletes(&self) -> Vec<&str> {
+        self.ops
+            .iter()
+            .filter(|op| matches!(op, SyncOp::Delete(_)))
+            .map(SyncOp::path)
+            .collect()
+    }
+}
+
+/// Return the operations which would make `dir_comp` equal to `dir` from the differences
+/// of the report. The renamed files are copied under their old path and deleted from the
+/// new one, the relinked files are skipped because their content is already the same
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::report::{DiffReport, Difference};
+/// use spielrs_diff::sync::{plan, SyncOp};
+///
+/// let report = DiffReport {
+///     differences: vec![
+///         Difference::Added("hello.js".to_string()),
+///         Difference::Modified("hello.txt".to_string()),
+///         Difference::Removed("vlang".to_string()),
+///     ],
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     plan(&report).ops,
+///     vec![
+///         SyncOp::Delete("hello.js".to_string()),
+///         SyncOp::Copy("hello.txt".to_string()),
+///         SyncOp::Copy("vlang".to_string()),
+///     ]
+/// );
+/// ```
+pub fn plan(report: &DiffReport) -> SyncPlan {
+    let mut ops: Vec<SyncOp> = vec![];
+    for difference in &report.differences {
+        match difference {
+            Difference::Added(path) => ops.push(SyncOp::Delete(path.clone())),
+            Difference::Removed(path) | Difference::Modified(path) => {
+                ops.push(SyncOp::Copy(path.clone()))
+            }
+            Difference::Renamed { from, to, .. } => {
+                ops.push(SyncOp::Copy(from.clone()));
+                ops.push(SyncOp::Delete(to.clone()));
+            }
+            Difference::Relinked(_) => {}
+        }
+    }
+    ops.sort_by(|a, b| a.path().cmp(b.path()));
+
+    SyncPlan {
+        dir: report.dir.clone(),
+        dir_comp: report.dir_comp.clone(),
+        ops,
+    }
+}
+
+/// One line per operation, like the output of `rsync --dry-run`
+impl fmt::Display for SyncPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.ops {
+            match op {
+                SyncOp::Copy(path) => writeln!(f, "copy {}", path)?,
+                SyncOp::Delete(path) => writeln!(f, "delete {}", path)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn should_copy_renamed_files_under_the_old_path() {
+    let report = DiffReport {
+        dir: "dir_one".to_string(),
+        dir_comp: "dir_two".to_string(),
+        differences: vec![
+            Difference::Relinked("linked.txt".to_string()),
+            Difference::Renamed {
+                from: "hello.py".to_string(),
+                to: "hola.py".to_string(),
+                similarity: 1.0,
+            },
+        ],
+        ..Default::default()
+    };
+    let plan = plan(&report);
+
+    assert_eq!(plan.copies(), vec!["hello.py"]);
+    assert_eq!(plan.deletes(), vec!["hola.py"]);
+    assert_eq!(plan.to_string(), "copy hello.py\ndelete hola.py\n");
+}
@@ -0,0 +1,14 @@
+//! Macro to emit the `tracing` events only with the `tracing` feature, without it the
+//! arguments are not evaluated
+
+#[cfg(feature = "tracing")]
+macro_rules! debug_event {
+    ($($arg:tt)*) => {
+        tracing::debug!($($arg)*)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug_event {
+    ($($arg:tt)*) => {};
+}
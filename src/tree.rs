@@ -1,7 +1,21 @@
+use crate::diff::{DirDiff, DirEntryInfo, ErrorPolicy};
+use crate::error::DiffError;
+use crate::mime;
+use crate::pattern::PathPattern;
+use crate::vfs::{self, DiffFs, EntryKind, LocalFs, VfsEntry, VfsMetadata};
 use async_trait::async_trait;
-use futures::StreamExt;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::collections::BTreeMap;
+use std::io;
 use std::iter::FromIterator;
-use tokio::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Number of files read at the same time by `get_content_files` and `mismatched_files`,
+/// the other readings take it from the caller
+pub const READ_CONCURRENCY: usize = 64;
 
 /// Trait for `Tree` to create your own `TreeBuilder`
 #[async_trait]
@@ -12,77 +26,1370 @@ pub trait TreeBuilder {
         excluding: Option<Vec<String>>,
         recursive_excluding: bool,
     ) -> Vec<Tree>;
+
+    /// Build a vector of `Tree` reading the directories from any `DiffFs`
+    /// like a remote http file server instead of the local filesystem
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    /// use spielrs_diff::vfs::LocalFs;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree_with(
+    ///         &LocalFs,
+    ///         "./mocks/dir_one".to_string(),
+    ///         None,
+    ///         false
+    ///     ).await;
+    ///
+    ///     println!("{:#?}", dir_one);
+    /// }
+    /// ```
+    async fn build_tree_with<F: DiffFs + ?Sized>(
+        fs: &F,
+        dir_path: String,
+        excluding: Option<Vec<String>>,
+        recursive_excluding: bool,
+    ) -> Vec<Tree> {
+        let options = DirDiff {
+            excluding,
+            recursive_excluding,
+            ..Default::default()
+        };
+
+        Tree::build_tree_with_options(fs, dir_path, &options).await
+    }
+
     /// Compare two tree directories and return true if are different
     fn tree_diff(dir_tree: Vec<Tree>, dir_tree_comp: Vec<Tree>) -> bool;
     /// Get the content by string of all the files in one tree directory
     async fn get_content_files(dir_tree: Vec<Tree>) -> Vec<String>;
+
+    /// Get the content by string of all the files in one tree directory reading them from a
+    /// `DiffFs`, at most `concurrency` files at the same time. The invalid UTF-8 sequences
+    /// are replaced by `U+FFFD` and an error is returned if a file can't be read
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    /// use spielrs_diff::vfs::LocalFs;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree_with(&LocalFs, "./mocks/dir_one".to_string(), None, false).await;
+    ///     let content = Tree::get_content_files_with(&LocalFs, dir_one, 16).await.unwrap();
+    ///
+    ///     println!("{:#?}", content);
+    /// }
+    /// ```
+    async fn get_content_files_with<F: DiffFs + ?Sized>(
+        fs: &F,
+        dir_tree: Vec<Tree>,
+        concurrency: usize,
+    ) -> Result<Vec<String>, DiffError> {
+        let file_list: TreeFlatted = TreeFlatted::from_iter(dir_tree);
+        let mut file_content: Vec<(usize, String)> = stream::iter(file_list.0)
+            .enumerate()
+            .map(|(index, file)| async move {
+                match fs.read(&file.path).await {
+                    Ok(content) => Ok((index, String::from_utf8_lossy(&content).into_owned())),
+                    Err(error) => Err(DiffError::from_io(&file.path, error)),
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await?;
+        file_content.sort_by_key(|(index, _)| *index);
+
+        Ok(file_content
+            .into_iter()
+            .map(|(_, content)| content)
+            .collect())
+    }
+
     /// compare all the content from two tree directories and return true if both are equal
     fn compare_dir_content(dir_content: Vec<String>, dir_content_comp: Vec<String>) -> bool;
 }
 
-/// Represent a tree directory
-#[derive(Debug, PartialEq, Clone)]
-pub struct Tree {
-    pub name: String,
-    pub path: String,
-    pub subdir: Option<Vec<Tree>>,
+/// Represent a tree directory, the entries of every directory are sorted by name so the
+/// trees built from the same directories are equal whatever the filesystem is
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tree {
+    pub name: String,
+    pub path: String,
+    /// path relative to the root of the tree, separated by `/`, the same in both
+    /// trees of a comparison whatever their roots are
+    pub rel_path: String,
+    /// kind of the entry, only the directories have a `subdir`
+    pub kind: EntryKind,
+    /// size in bytes of the file, 0 for directories
+    pub size: u64,
+    /// device and inode of the file if it has more than one hard link
+    /// and the `DiffFs` knows it
+    pub inode: Option<(u64, u64)>,
+    /// content type of the file detected from its first bytes, like `image/png`, only
+    /// built with `DirDiff::detect_content_type`
+    pub content_type: Option<String>,
+    pub subdir: Option<Vec<Tree>>,
+}
+
+/// Totals of a tree returned by `Tree::stats`
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct TreeStats {
+    /// number of files
+    pub files: usize,
+    /// number of directories, without the root
+    pub dirs: usize,
+    /// sum of the size of all the files
+    pub bytes: u64,
+}
+
+/// Change of the filesystem applied to a tree with `Tree::apply_event`, like the
+/// events of [notify](https://docs.rs/notify). The paths are paths of the source,
+/// under the directory the tree was built from
+#[derive(Debug, PartialEq, Clone)]
+pub enum FsEvent {
+    /// file or directory created
+    Created(String),
+    /// content or metadata of a file or directory modified
+    Modified(String),
+    /// file or directory removed
+    Removed(String),
+    /// file or directory moved from a path to another
+    Renamed {
+        /// previous path
+        from: String,
+        /// new path
+        to: String,
+    },
+}
+
+/// Callbacks of the walk of a tree with `Tree::accept`, for the analyses which need to
+/// know where each directory ends, like the size of every directory
+pub trait TreeVisitor {
+    /// Called with every directory before its content, which is skipped if it returns false
+    fn visit_dir(&mut self, _dir: &Tree) -> bool {
+        true
+    }
+    /// Called with every directory after its content, even if it was skipped
+    fn leave_dir(&mut self, _dir: &Tree) {}
+    /// Called with every entry which is not a directory: the files, the symbolic links
+    /// and the special files
+    fn visit_file(&mut self, _file: &Tree) {}
+}
+
+/// Depth first iterator over the entries of a tree, returned by `Tree::iter`.
+/// Every item is the path of the entry relative to the root and the entry,
+/// a directory is returned before its content
+pub struct TreeIter<'a> {
+    stack: Vec<(String, std::slice::Iter<'a, Tree>)>,
+}
+
+impl<'a> Iterator for TreeIter<'a> {
+    type Item = (String, &'a Tree);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (prefix, entries) = self.stack.last_mut()?;
+            let entry = match entries.next() {
+                Some(entry) => entry,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let path = if prefix.is_empty() {
+                entry.name.clone()
+            } else {
+                format!("{}/{}", prefix, entry.name)
+            };
+
+            if let Some(subdir) = &entry.subdir {
+                self.stack.push((path.clone(), subdir.iter()));
+            }
+
+            return Some((path, entry));
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Tree {
+    type Item = (String, &'a Tree);
+    type IntoIter = TreeIter<'a>;
+
+    /// Iterate the entry and all its content, the paths start with the name of the entry
+    fn into_iter(self) -> Self::IntoIter {
+        Tree::iter(std::slice::from_ref(self))
+    }
+}
+
+/// File of a tree returned by `Tree::flatten`
+#[derive(Debug, PartialEq, Clone)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+}
+
+struct TreeFlatted(Vec<FileEntry>);
+
+impl TreeFlatted {
+    fn new() -> Self {
+        TreeFlatted(Vec::new())
+    }
+
+    fn add(&mut self, elem: FileEntry) {
+        self.0.push(elem);
+    }
+}
+
+impl FromIterator<Tree> for TreeFlatted {
+    fn from_iter<I: IntoIterator<Item = Tree>>(iter: I) -> Self {
+        let mut tree_flatted = TreeFlatted::new();
+
+        for i in iter {
+            if let Some(entry) = i.subdir {
+                for sub_iter in TreeFlatted::from_iter(entry).0 {
+                    tree_flatted.add(sub_iter);
+                }
+            } else if i.kind == EntryKind::File {
+                let file_entry = FileEntry {
+                    name: i.name,
+                    path: i.path,
+                };
+
+                tree_flatted.add(file_entry);
+            }
+        }
+
+        tree_flatted
+    }
+}
+
+impl Tree {
+    /// Walk the tree depth first returning the path relative to the root of every entry
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///
+    ///     for (path, entry) in Tree::iter(&dir_one) {
+    ///         if entry.subdir.is_none() {
+    ///             println!("{}", path);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn iter(tree: &[Tree]) -> TreeIter<'_> {
+        TreeIter {
+            stack: vec![(String::new(), tree.iter())],
+        }
+    }
+
+    /// Walk the local directory lazily, see `walk_with`
+    pub fn walk(root: &str) -> impl Stream<Item = Result<VfsEntry, DiffError>> + 'static {
+        Tree::walk_with(&LocalFs, root)
+    }
+
+    /// Walk the directory of any `DiffFs` depth first returning its entries as they are
+    /// listed, without building the tree, so only the directories being walked are in
+    /// memory. The entries of every directory are returned in the order of their names
+    /// and a directory is returned before its content. The directories which can't be
+    /// listed are returned followed by their error, the walk goes on without them
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::Tree;
+    /// use futures::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut entries = Box::pin(Tree::walk("./mocks/dir_one"));
+    ///     let mut bytes = 0;
+    ///     while let Some(entry) = entries.next().await {
+    ///         bytes += entry.unwrap().len;
+    ///     }
+    ///
+    ///     assert!(bytes > 0);
+    /// }
+    /// ```
+    pub fn walk_with<'a, F: DiffFs + ?Sized>(
+        fs: &'a F,
+        root: &str,
+    ) -> impl Stream<Item = Result<VfsEntry, DiffError>> + 'a {
+        // the directory to list before the next entry and the entries left of every
+        // directory being walked
+        let state: (Option<String>, Vec<std::vec::IntoIter<VfsEntry>>) =
+            (Some(root.to_string()), vec![]);
+
+        stream::unfold(state, move |(mut listing, mut stack)| async move {
+            loop {
+                if let Some(dir) = listing.take() {
+                    match fs.read_dir(&dir).await {
+                        Ok(mut entries) => {
+                            entries.sort_by(|a, b| a.name.cmp(&b.name));
+                            stack.push(entries.into_iter());
+                        }
+                        Err(error) => {
+                            return Some((Err(DiffError::from_io(&dir, error)), (None, stack)))
+                        }
+                    }
+                }
+                match stack.last_mut()?.next() {
+                    Some(entry) => {
+                        let listing = Some(entry.path.clone()).filter(|_| entry.is_dir());
+                        return Some((Ok(entry), (listing, stack)));
+                    }
+                    None => {
+                        stack.pop();
+                    }
+                }
+            }
+        })
+    }
+
+    /// Walk the tree depth first calling the visitor with every entry, the entries of
+    /// every directory are visited in the order of their names
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder, TreeVisitor};
+    ///
+    /// // files bigger than the limit
+    /// struct TooBig(Vec<String>);
+    ///
+    /// impl TreeVisitor for TooBig {
+    ///     fn visit_file(&mut self, file: &Tree) {
+    ///         if file.size > 10 {
+    ///             self.0.push(file.rel_path.clone());
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///     let mut too_big = TooBig(vec![]);
+    ///     Tree::accept(&dir_one, &mut too_big);
+    ///
+    ///     assert!(too_big.0.contains(&"hello.txt".to_string()));
+    /// }
+    /// ```
+    pub fn accept<V: TreeVisitor + ?Sized>(tree: &[Tree], visitor: &mut V) {
+        for entry in tree {
+            match &entry.subdir {
+                Some(subdir) => {
+                    if visitor.visit_dir(entry) {
+                        Tree::accept(subdir, visitor);
+                    }
+                    visitor.leave_dir(entry);
+                }
+                None => visitor.visit_file(entry),
+            }
+        }
+    }
+
+    /// Return all the files of the tree, without the directories, in the same order
+    /// that their content is read by `get_content_files`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///
+    ///     for file in Tree::flatten(dir_one) {
+    ///         println!("{} -> {}", file.name, file.path);
+    ///     }
+    /// }
+    /// ```
+    pub fn flatten(tree: Vec<Tree>) -> Vec<FileEntry> {
+        TreeFlatted::from_iter(tree).0
+    }
+
+    /// Combine two trees like an overlay filesystem: the entries of `overlay` replace the
+    /// entries of `base` with the same name, except when both are directories, in that
+    /// case their content is merged. Every entry keeps the path where it was found, so the
+    /// file contents of the merged tree are read from the layer that provides them. The
+    /// entries only in `overlay` are inserted before the first entry with a greater name
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let base = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///     let overlay = Tree::build_tree("./mocks/dir_three".to_string(), None, false).await;
+    ///     let merged = Tree::merge(base, overlay);
+    ///
+    ///     assert!(Tree::find(&merged, "hello.js").is_some());
+    ///     assert!(Tree::find(&merged, "vlang/hello.v").is_some());
+    /// }
+    /// ```
+    pub fn merge(base: Vec<Tree>, overlay: Vec<Tree>) -> Vec<Tree> {
+        let mut merged: Vec<Tree> = base;
+
+        for entry in overlay {
+            match merged.iter().position(|item| item.name == entry.name) {
+                Some(index) => {
+                    let current = merged.remove(index);
+                    let replacement = match (current.subdir, entry.subdir) {
+                        (Some(subdir), Some(overlay_subdir)) => Tree {
+                            name: entry.name,
+                            path: entry.path,
+                            rel_path: entry.rel_path,
+                            kind: entry.kind,
+                            size: entry.size,
+                            inode: entry.inode,
+                            content_type: entry.content_type,
+                            subdir: Some(Tree::merge(subdir, overlay_subdir)),
+                        },
+                        (_, subdir) => Tree {
+                            name: entry.name,
+                            path: entry.path,
+                            rel_path: entry.rel_path,
+                            kind: entry.kind,
+                            size: entry.size,
+                            inode: entry.inode,
+                            content_type: entry.content_type,
+                            subdir,
+                        },
+                    };
+                    merged.insert(index, replacement);
+                }
+                None => {
+                    let position = merged
+                        .iter()
+                        .position(|item| item.name > entry.name)
+                        .unwrap_or(merged.len());
+                    merged.insert(position, entry);
+                }
+            }
+        }
+
+        merged
+    }
+
+    /// Count the regular files and directories of the tree and the bytes of all the files,
+    /// the symbolic links and the other entries are not counted
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///     let stats = Tree::stats(&dir_one);
+    ///
+    ///     assert_eq!((stats.files, stats.dirs), (4, 2));
+    /// }
+    /// ```
+    pub fn stats(tree: &[Tree]) -> TreeStats {
+        Tree::iter(tree).fold(TreeStats::default(), |mut stats, (_, entry)| {
+            match entry.kind {
+                EntryKind::Dir => stats.dirs += 1,
+                EntryKind::File => {
+                    stats.files += 1;
+                    stats.bytes += entry.size;
+                }
+                _ => {}
+            }
+
+            stats
+        })
+    }
+
+    /// Find an entry by its path relative to the root of the tree, like `vlang/purpose`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///     let purpose = Tree::find(&dir_one, "vlang/purpose").unwrap();
+    ///
+    ///     assert_eq!(purpose.name, "purpose");
+    /// }
+    /// ```
+    pub fn find<'a>(tree: &'a [Tree], path: &str) -> Option<&'a Tree> {
+        let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+        let first = segments.next()?;
+        let mut entry = tree.iter().find(|entry| entry.name == first)?;
+
+        for segment in segments {
+            entry = entry
+                .subdir
+                .as_ref()?
+                .iter()
+                .find(|child| child.name == segment)?;
+        }
+
+        Some(entry)
+    }
+
+    /// Find all the entries with the name in any level of the tree
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///     let files = Tree::find_by_name(&dir_one, "purpose.txt");
+    ///
+    ///     assert_eq!(files.len(), 1);
+    /// }
+    /// ```
+    pub fn find_by_name<'a>(tree: &'a [Tree], name: &str) -> Vec<&'a Tree> {
+        let mut found: Vec<&Tree> = vec![];
+
+        for entry in tree {
+            if entry.name == name {
+                found.push(entry);
+            }
+            if let Some(subdir) = &entry.subdir {
+                found.append(&mut Tree::find_by_name(subdir, name));
+            }
+        }
+
+        found
+    }
+
+    /// Build a vector of `Tree` from any `DiffFs` applying all the exclusions of the
+    /// options, `dir` and `dir_comp` are ignored
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::{diff::DirDiff, tree::Tree, vfs::LocalFs};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let options = DirDiff {
+    ///         exclude_extensions: Some(vec!["py".to_string()]),
+    ///         ..Default::default()
+    ///     };
+    ///     let dir_one =
+    ///         Tree::build_tree_with_options(&LocalFs, "./mocks/dir_one".to_string(), &options).await;
+    ///
+    ///     assert!(Tree::find(&dir_one, "hello.py").is_none());
+    /// }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(fs, options)))]
+    pub async fn build_tree_with_options<F: DiffFs + ?Sized>(
+        fs: &F,
+        dir_path: String,
+        options: &DirDiff,
+    ) -> Vec<Tree> {
+        match Tree::try_build_tree_with_options(fs, dir_path, options).await {
+            Ok((tree, _, _)) => tree,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Build the tree and return the errors of the subdirectories which couldn't be
+    /// listed, or the first one with `ErrorPolicy::FailFast`, and true if an entry was
+    /// left out by `DirDiff::max_entries`
+    pub(crate) async fn try_build_tree_with_options<F: DiffFs + ?Sized>(
+        fs: &F,
+        dir_path: String,
+        options: &DirDiff,
+    ) -> Result<(Vec<Tree>, Vec<DiffError>, bool), DiffError> {
+        let rules = Rules::new(options);
+        let tree = build_entries(fs, dir_path, String::new(), vec![], &rules).await?;
+
+        Ok((
+            tree,
+            rules.errors.into_inner().unwrap_or_default(),
+            rules.truncated.into_inner(),
+        ))
+    }
+
+    /// Build a vector of `Tree` from a known list of paths instead of walking the
+    /// directory, like the output of `git ls-files`, so the comparison is scoped to
+    /// them. The paths are relative to `root`, or start with it. The directories are
+    /// created from the segments of the paths and the paths which don't exist are skipped
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::Tree;
+    /// use std::path::PathBuf;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let paths = vec![PathBuf::from("hello.txt"), PathBuf::from("vlang/purpose/purpose.txt")];
+    ///     let dir_one = Tree::from_paths("./mocks/dir_one", paths).await;
+    ///
+    ///     assert!(Tree::find(&dir_one, "vlang/purpose/purpose.txt").is_some());
+    ///     assert!(Tree::find(&dir_one, "vlang/hello.v").is_none());
+    /// }
+    /// ```
+    pub async fn from_paths(root: &str, paths: Vec<PathBuf>) -> Vec<Tree> {
+        Tree::from_paths_with(&LocalFs, root, paths).await
+    }
+
+    /// Same than `from_paths` reading the metadata of the paths from any `DiffFs`
+    pub async fn from_paths_with<F: DiffFs + ?Sized>(
+        fs: &F,
+        root: &str,
+        paths: Vec<PathBuf>,
+    ) -> Vec<Tree> {
+        let mut tree: Vec<Tree> = vec![];
+
+        for path in paths {
+            let path = path.to_string_lossy();
+            let rel_path = match vfs::path::relative_to(root, &path) {
+                Some(rel_path) => rel_path,
+                None => vfs::path::normalize_separators(&path)
+                    .split('/')
+                    .filter(|segment| !segment.is_empty() && *segment != ".")
+                    .collect::<Vec<&str>>()
+                    .join("/"),
+            };
+            if rel_path.is_empty() {
+                continue;
+            }
+            let path = vfs::path::join(root, &rel_path);
+            if let Ok(metadata) = fs.metadata(&path).await {
+                insert_path(&mut tree, root, &rel_path, &metadata);
+            } else {
+                debug_event!(path = %rel_path, "path skipped, it doesn't exist");
+            }
+        }
+
+        tree
+    }
+
+    /// Update a tree built from `dir_path` with the change of the event instead of building
+    /// it again, the entries of the event are listed again from the source applying the
+    /// exclusions of the options, with all their content if they are directories. Return
+    /// true if the tree changed, the events outside of `dir_path` or inside of excluded
+    /// directories are ignored. If a directory can't be listed the error is returned and
+    /// the tree is unchanged, the entries whose parent directory doesn't exist anymore
+    /// are removed
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::{diff::DirDiff, tree::{FsEvent, Tree}, vfs::mem::MemFs};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let fs = MemFs::builder().file("dir_one/hello.txt", "Hello world").build();
+    ///     let options = DirDiff::default();
+    ///     let mut tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+    ///
+    ///     let fs = MemFs::builder().file("dir_one/vlang/hello.v", "fn main() {}").build();
+    ///     let event = FsEvent::Renamed {
+    ///         from: "dir_one/hello.txt".to_string(),
+    ///         to: "dir_one/vlang".to_string(),
+    ///     };
+    ///     assert!(Tree::apply_event(&fs, &mut tree, "dir_one", &event, &options).await.unwrap());
+    ///
+    ///     assert!(Tree::find(&tree, "hello.txt").is_none());
+    ///     assert!(Tree::find(&tree, "vlang/hello.v").is_some());
+    /// }
+    /// ```
+    pub async fn apply_event<F: DiffFs + ?Sized>(
+        fs: &F,
+        tree: &mut Vec<Tree>,
+        dir_path: &str,
+        event: &FsEvent,
+        options: &DirDiff,
+    ) -> Result<bool, DiffError> {
+        let rules = Rules::new(options);
+
+        match event {
+            FsEvent::Created(path) | FsEvent::Modified(path) | FsEvent::Removed(path) => {
+                let refreshed = refresh_entry(fs, tree, dir_path, path, &rules).await?;
+                Ok(refreshed.is_some_and(|refreshed| replace_entry(tree, refreshed)))
+            }
+            FsEvent::Renamed { from, to } => {
+                // both are listed before changing the tree, so a failure leaves it unchanged
+                let removed = refresh_entry(fs, tree, dir_path, from, &rules).await?;
+                let added = refresh_entry(fs, tree, dir_path, to, &rules).await?;
+                let removed = removed.is_some_and(|removed| replace_entry(tree, removed));
+                let added = added.is_some_and(|added| replace_entry(tree, added));
+                Ok(removed || added)
+            }
+        }
+    }
+
+    /// Read the content of all the files of the tree from any `DiffFs`, at most
+    /// `concurrency` files at the same time, and return it by path relative to the root
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    /// use spielrs_diff::vfs::LocalFs;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///     let contents = Tree::read_files_with(&LocalFs, &dir_one, 16).await.unwrap();
+    ///
+    ///     assert_eq!(contents["hello.txt"], b"Hello world".to_vec());
+    /// }
+    /// ```
+    pub async fn read_files_with<F: DiffFs + ?Sized>(
+        fs: &F,
+        tree: &[Tree],
+        concurrency: usize,
+    ) -> Result<BTreeMap<String, Vec<u8>>, DiffError> {
+        stream::iter(Tree::iter(tree).filter(|(_, entry)| entry.kind == EntryKind::File))
+            .map(|(path, entry)| async move {
+                match fs.read(&entry.path).await {
+                    Ok(content) => Ok((path, content)),
+                    Err(error) => Err(DiffError::from_io(&entry.path, error)),
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// Pair the files of both trees by their path relative to the root and return the
+    /// paths whose content is different or which are only in one of them. Unlike
+    /// `compare_dir_content`, two files swapping their contents are detected. The symbolic
+    /// links are compared by their target, without following them, and an entry whose kind
+    /// changed is mismatched
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    ///     let dir_four = Tree::build_tree("./mocks/dir_four".to_string(), None, false).await;
+    ///
+    ///     assert_eq!(
+    ///         Tree::mismatched_files(&dir_one, &dir_four).await,
+    ///         vec!["vlang/purpose/purpose.txt"]
+    ///     );
+    /// }
+    /// ```
+    pub async fn mismatched_files(dir_tree: &[Tree], dir_tree_comp: &[Tree]) -> Vec<String> {
+        Tree::mismatched_files_with(&LocalFs, dir_tree, &LocalFs, dir_tree_comp).await
+    }
+
+    /// Same than `mismatched_files` reading the files from any `DiffFs`, `READ_CONCURRENCY`
+    /// pairs at the same time. The files which can't be read are skipped, the links whose
+    /// target can't be read are mismatched
+    pub async fn mismatched_files_with<F: DiffFs + ?Sized, C: DiffFs + ?Sized>(
+        fs: &F,
+        dir_tree: &[Tree],
+        fs_comp: &C,
+        dir_tree_comp: &[Tree],
+    ) -> Vec<String> {
+        let is_link = |kind: EntryKind| matches!(kind, EntryKind::Symlink | EntryKind::Cycle);
+        let files = |tree: &'_ [Tree]| -> BTreeMap<String, (EntryKind, String)> {
+            Tree::iter(tree)
+                .filter(|(_, entry)| entry.kind == EntryKind::File || is_link(entry.kind))
+                .map(|(path, entry)| (path, (entry.kind, entry.path.clone())))
+                .collect()
+        };
+        let files_one = files(dir_tree);
+        let files_two = files(dir_tree_comp);
+
+        let files_comp = &files_two;
+        let mut mismatched: Vec<String> = stream::iter(&files_one)
+            .map(|(path, (kind, file))| async move {
+                let differs = match files_comp.get(path) {
+                    Some((kind_comp, _)) if kind != kind_comp => true,
+                    // the links are equal if they point to the same target, not followed
+                    Some((_, file_comp)) if is_link(*kind) => {
+                        let target = fs.read_link(file).await.ok();
+                        let target_comp = fs_comp.read_link(file_comp).await.ok();
+                        target.is_none() || target != target_comp
+                    }
+                    Some((_, file_comp)) => {
+                        match (fs.read(file).await, fs_comp.read(file_comp).await) {
+                            (Ok(content), Ok(content_comp)) => content != content_comp,
+                            _ => {
+                                debug_event!(path = %path, "file not readable, skipped");
+                                false
+                            }
+                        }
+                    }
+                    None => true,
+                };
+                differs.then(|| path.clone())
+            })
+            .buffer_unordered(READ_CONCURRENCY)
+            .filter_map(|path| async move { path })
+            .collect()
+            .await;
+        mismatched.extend(
+            files_two
+                .keys()
+                .filter(|path| !files_one.contains_key(*path))
+                .cloned(),
+        );
+        mismatched.sort();
+
+        mismatched
+    }
+}
+
+/// Options of the build with the regular expressions already compiled
+struct Rules<'a> {
+    options: &'a DirDiff,
+    /// entries added to the tree, counted for `DirDiff::max_entries`
+    entries: AtomicUsize,
+    /// true once an entry was refused by `DirDiff::max_entries`
+    truncated: AtomicBool,
+    /// errors of the directories which couldn't be listed with `ErrorPolicy::Collect`
+    errors: Mutex<Vec<DiffError>>,
+    /// `DirDiff::excluding_patterns` parsed
+    patterns: Vec<PathPattern>,
+}
+
+impl<'a> Rules<'a> {
+    fn new(options: &'a DirDiff) -> Self {
+        Rules {
+            options,
+            entries: AtomicUsize::new(0),
+            truncated: AtomicBool::new(false),
+            errors: Mutex::new(vec![]),
+            patterns: options
+                .excluding_patterns
+                .iter()
+                .flatten()
+                .map(|pattern| PathPattern::new(pattern))
+                .collect(),
+        }
+    }
+
+    /// Count one more entry, false once `DirDiff::max_entries` were already added, then
+    /// the tree is flagged as truncated
+    fn admit(&self) -> bool {
+        let admitted = match self.options.max_entries {
+            Some(max_entries) => self.entries.fetch_add(1, Ordering::Relaxed) < max_entries,
+            None => true,
+        };
+        if !admitted {
+            self.truncated.store(true, Ordering::Relaxed);
+        }
+
+        admitted
+    }
+
+    #[cfg(feature = "regex")]
+    fn matches_regex(&self, rel_path: &str) -> bool {
+        self.options
+            .excluding_regex
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(rel_path))
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn matches_regex(&self, _rel_path: &str) -> bool {
+        false
+    }
+}
+
+/// Return true if the entry must not be in the tree by its listing or, with
+/// `DirDiff::exclude_by_metadata`, by its metadata. The entries whose metadata can't be
+/// read are not excluded by it
+async fn excluded<F: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &VfsEntry,
+    rel_path: &str,
+    rel_dir: &str,
+    rules: &Rules<'_>,
+) -> bool {
+    if is_excluded(entry, rel_path, rel_dir, rules) {
+        return true;
+    }
+    match &rules.options.exclude_by_metadata {
+        Some(exclusion) => match fs.metadata(&entry.path).await {
+            Ok(metadata) => exclusion.excludes(&entry_info(entry, rel_path), &metadata),
+            Err(_) => false,
+        },
+        None => false,
+    }
+}
+
+fn entry_info(entry: &VfsEntry, rel_path: &str) -> DirEntryInfo {
+    DirEntryInfo {
+        name: entry.name.clone(),
+        rel_path: rel_path.to_string(),
+        is_dir: entry.is_dir(),
+        len: entry.len,
+        depth: rel_path.matches('/').count(),
+    }
+}
+
+/// Return true if the entry must not be in the tree, `rel_dir` is empty in the root
+fn is_excluded(entry: &VfsEntry, rel_path: &str, rel_dir: &str, rules: &Rules) -> bool {
+    let options = rules.options;
+    if rules.matches_regex(rel_path) {
+        return true;
+    }
+
+    let excluding = options.excluding.as_deref().unwrap_or_default();
+    if (rel_dir.is_empty() || options.recursive_excluding) && excluding.contains(&entry.name) {
+        return true;
+    }
+    if rules
+        .patterns
+        .iter()
+        .any(|pattern| pattern.matches(rel_path, entry.is_dir()))
+    {
+        return true;
+    }
+    #[cfg(feature = "globset")]
+    if options
+        .excluding_globs
+        .as_ref()
+        .is_some_and(|globs| globs.is_match(rel_path))
+    {
+        return true;
+    }
+    let excluding_paths = options.excluding_paths.as_deref().unwrap_or_default();
+    if excluding_paths
+        .iter()
+        .any(|path| path.replace('\\', "/").trim_matches('/') == rel_path)
+    {
+        return true;
+    }
+    let scoped_excluding = options.scoped_excluding.as_deref().unwrap_or_default();
+    if scoped_excluding.iter().any(|pattern| {
+        let pattern = pattern.trim_matches('/');
+        match pattern.rsplit_once('/') {
+            Some((scope, name)) => {
+                name == entry.name
+                    && (rel_dir == scope || rel_dir.starts_with(&format!("{}/", scope)))
+            }
+            // the scope of a bare name is the root
+            None => pattern == entry.name,
+        }
+    }) {
+        return true;
+    }
+    if !entry.is_dir()
+        && (excluded_by_size(entry, options) || excluded_by_extension(entry, options))
+    {
+        return true;
+    }
+
+    // the filter is consulted last, only with the entries which are not excluded
+    options
+        .filter
+        .as_ref()
+        .is_some_and(|filter| !filter.keep(&entry_info(entry, rel_path)))
+}
+
+/// Return true if the file is smaller than `DirDiff::min_size` or bigger than `max_size`
+fn excluded_by_size(entry: &VfsEntry, options: &DirDiff) -> bool {
+    options
+        .min_size
+        .is_some_and(|min_size| entry.len < min_size)
+        || options
+            .max_size
+            .is_some_and(|max_size| entry.len > max_size)
+}
+
+/// Return true if the file has one of `DirDiff::exclude_extensions`
+fn excluded_by_extension(entry: &VfsEntry, options: &DirDiff) -> bool {
+    match (
+        &options.exclude_extensions,
+        entry.name.rsplit_once('.').map(|(_, extension)| extension),
+    ) {
+        (Some(extensions), Some(extension)) => extensions.iter().any(|excluded| {
+            excluded
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(extension)
+        }),
+        _ => false,
+    }
+}
+
+/// List the directory and its subdirectories, `rel_dir` is the path of the directory
+/// relative to the root of the tree and `ancestors` the device and inode of the
+/// directories which contain it, only known following the symbolic links. The
+/// subdirectories which can't be listed are kept as `EntryKind::Unreadable`
+fn build_entries<'a, F: DiffFs + ?Sized>(
+    fs: &'a F,
+    dir_path: String,
+    rel_dir: String,
+    mut ancestors: Vec<(u64, u64)>,
+    rules: &'a Rules<'a>,
+) -> BoxFuture<'a, Result<Vec<Tree>, DiffError>> {
+    async move {
+        let mut entries = fs
+            .read_dir(&dir_path)
+            .await
+            .map_err(|error| DiffError::from_io(&dir_path, error))?;
+        // the order of the listings depends on the filesystem
+        entries.sort_by(|entry, entry_comp| entry.name.cmp(&entry_comp.name));
+        let mut tree: Vec<Tree> = vec![];
+        if rules.options.follow_symlinks {
+            ancestors.extend(inode_of(fs, &dir_path).await);
+        }
+
+        for entry in entries {
+            let mut entry = follow_symlink(fs, entry, &ancestors, rules).await;
+            let rel_path = if rel_dir.is_empty() {
+                entry.name.clone()
+            } else {
+                format!("{}/{}", rel_dir, entry.name)
+            };
+            if excluded(fs, &entry, &rel_path, &rel_dir, rules).await {
+                debug_event!(path = %rel_path, "entry excluded");
+                continue;
+            }
+            if !rules.admit() {
+                debug_event!(path = %rel_path, "max_entries reached, the tree is truncated");
+                break;
+            }
+
+            let subdir = if entry.is_dir() {
+                build_subdir(fs, &mut entry, &rel_path, ancestors.clone(), rules).await?
+            } else {
+                None
+            };
+            if is_ignored_dir(&subdir, rules) {
+                debug_event!(path = %rel_path, "empty directory ignored");
+                continue;
+            }
+            if entry.kind == EntryKind::Cycle {
+                debug_event!(path = %rel_path, "symbolic link cycle, not followed");
+            }
+            if entry.kind.is_special() {
+                debug_event!(path = %rel_path, kind = ?entry.kind, "special file, its content is not read");
+            }
+
+            let content_type = detect_content_type(fs, &entry, rules).await;
+            tree.push(Tree {
+                name: entry.name,
+                path: entry.path,
+                rel_path,
+                kind: entry.kind,
+                size: entry.len,
+                inode: entry.inode,
+                content_type,
+                subdir,
+            });
+        }
+
+        Ok(tree)
+    }
+    .boxed()
+}
+
+/// Entries of the directory, or `None` marking the entry as `EntryKind::Unreadable` if it
+/// can't be listed, like without the permission to read it. The error is collected or
+/// returned following `DirDiff::on_error`
+async fn build_subdir<F: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &mut VfsEntry,
+    rel_path: &str,
+    ancestors: Vec<(u64, u64)>,
+    rules: &Rules<'_>,
+) -> Result<Option<Vec<Tree>>, DiffError> {
+    let dir_path = entry.path.clone();
+    match build_entries(fs, dir_path, rel_path.to_string(), ancestors, rules).await {
+        Ok(subdir) => Ok(Some(subdir)),
+        Err(error) => {
+            debug_event!(path = %rel_path, "directory not readable, its content is unknown");
+            entry.kind = EntryKind::Unreadable;
+            if rules.options.on_error == ErrorPolicy::FailFast {
+                return Err(error);
+            }
+            if let Ok(mut errors) = rules.errors.lock() {
+                errors.push(error);
+            }
+
+            Ok(None)
+        }
+    }
+}
+
+/// Entry of the target of the symbolic link with `DirDiff::follow_symlinks`, or a `Cycle`
+/// if it is one of the `ancestors`. The links whose target doesn't exist are kept
+async fn follow_symlink<F: DiffFs + ?Sized>(
+    fs: &F,
+    mut entry: VfsEntry,
+    ancestors: &[(u64, u64)],
+    rules: &Rules<'_>,
+) -> VfsEntry {
+    if entry.kind != EntryKind::Symlink || !rules.options.follow_symlinks {
+        return entry;
+    }
+    let metadata = match fs.metadata(&entry.path).await {
+        Ok(metadata) => metadata,
+        Err(_) => return entry,
+    };
+
+    // a link to a FIFO or a device keeps the kind of its target, it is never read
+    entry.kind = match metadata.kind {
+        EntryKind::Dir
+            if metadata
+                .inode
+                .is_some_and(|inode| ancestors.contains(&inode)) =>
+        {
+            EntryKind::Cycle
+        }
+        EntryKind::File => {
+            entry.len = metadata.len;
+            EntryKind::File
+        }
+        kind => kind,
+    };
+
+    entry
 }
 
-#[derive(Debug, PartialEq)]
-struct TreeComp {
-    pub name: String,
-    pub subdir: Option<Vec<TreeComp>>,
+/// Device and inode of the root and of every directory of `rel_dir` which the source knows
+async fn ancestors_of<F: DiffFs + ?Sized>(
+    fs: &F,
+    dir_path: &str,
+    rel_dir: &str,
+) -> Vec<(u64, u64)> {
+    let mut ancestors: Vec<(u64, u64)> = inode_of(fs, dir_path).await.into_iter().collect();
+    let mut rel_path = String::new();
+
+    for segment in rel_dir.split('/').filter(|segment| !segment.is_empty()) {
+        if !rel_path.is_empty() {
+            rel_path.push('/');
+        }
+        rel_path.push_str(segment);
+        ancestors.extend(inode_of(fs, &vfs::path::join(dir_path, &rel_path)).await);
+    }
+
+    ancestors
 }
 
-struct ExtratedFile {
-    pub name: String,
-    pub path: String,
+/// Device and inode of the directory, if the source knows them
+async fn inode_of<F: DiffFs + ?Sized>(fs: &F, path: &str) -> Option<(u64, u64)> {
+    fs.metadata(path).await.ok()?.inode
 }
 
-struct TreeFlatted(Vec<ExtratedFile>);
+/// Insert the entry of the path relative to the root in the tree, creating its parent
+/// directories, every new entry before the first entry with a greater name
+fn insert_path(tree: &mut Vec<Tree>, root: &str, rel_path: &str, metadata: &VfsMetadata) {
+    let segments: Vec<&str> = rel_path.split('/').collect();
+    let mut entries = tree;
 
-impl TreeFlatted {
-    fn new() -> Self {
-        TreeFlatted(Vec::new())
+    for (index, segment) in segments.iter().enumerate() {
+        let entry_rel_path = segments[..=index].join("/");
+        let is_last = index == segments.len() - 1;
+        let position = match entries.iter().position(|entry| entry.name == *segment) {
+            Some(position) if !is_last => position,
+            found => {
+                if let Some(position) = found {
+                    entries.remove(position);
+                }
+                let is_dir = !is_last || metadata.is_dir();
+                let position = entries
+                    .iter()
+                    .position(|entry| entry.name.as_str() > *segment)
+                    .unwrap_or(entries.len());
+                entries.insert(
+                    position,
+                    Tree {
+                        name: segment.to_string(),
+                        path: vfs::path::join(root, &entry_rel_path),
+                        rel_path: entry_rel_path,
+                        kind: if is_dir {
+                            EntryKind::Dir
+                        } else {
+                            EntryKind::File
+                        },
+                        size: if is_dir { 0 } else { metadata.len },
+                        inode: None,
+                        content_type: None,
+                        subdir: if is_dir { Some(vec![]) } else { None },
+                    },
+                );
+                position
+            }
+        };
+        entries = match entries[position].subdir.as_mut() {
+            Some(subdir) => subdir,
+            None => return,
+        };
     }
+}
 
-    fn add(&mut self, elem: ExtratedFile) {
-        self.0.push(elem);
+/// Content type of the file read from its first bytes with `DirDiff::detect_content_type`,
+/// None for the entries which aren't regular files or if it can't be read
+async fn detect_content_type<F: DiffFs + ?Sized>(
+    fs: &F,
+    entry: &VfsEntry,
+    rules: &Rules<'_>,
+) -> Option<String> {
+    if entry.kind != EntryKind::File || !rules.options.detect_content_type {
+        return None;
     }
+    let content = fs.read_range(&entry.path, 0, mime::SNIFF_LEN).await.ok()?;
+
+    Some(mime::content_type(&content).to_string())
 }
 
-impl FromIterator<Tree> for TreeFlatted {
-    fn from_iter<I: IntoIterator<Item = Tree>>(iter: I) -> Self {
-        let mut tree_flatted = TreeFlatted::new();
+/// Return true if the entry is an empty directory skipped by `DirDiff::ignore_empty_dirs`,
+/// its empty subdirectories were already skipped
+fn is_ignored_dir(subdir: &Option<Vec<Tree>>, rules: &Rules<'_>) -> bool {
+    rules.options.ignore_empty_dirs && subdir.as_ref().is_some_and(Vec::is_empty)
+}
 
-        for i in iter {
-            if let Some(entry) = i.subdir {
-                for sub_iter in TreeFlatted::from_iter(entry).0 {
-                    tree_flatted.add(sub_iter);
-                }
-            } else {
-                let extrated_file = ExtratedFile {
-                    name: i.name,
-                    path: i.path,
-                };
+/// Entry of the path of an event listed again from the source
+struct Refreshed {
+    rel_dir: String,
+    name: String,
+    /// `None` if it doesn't exist anymore or is excluded
+    entry: Option<Tree>,
+}
 
-                tree_flatted.add(extrated_file);
-            }
+/// List again the entry of the path in its parent directory, with all its content if it
+/// is a directory. `None` if the path is outside of `dir_path` or its parent directory is
+/// not in the tree, an error if a directory can't be listed. A parent directory which
+/// doesn't exist anymore has no entries
+async fn refresh_entry<F: DiffFs + ?Sized>(
+    fs: &F,
+    tree: &[Tree],
+    dir_path: &str,
+    path: &str,
+    rules: &Rules<'_>,
+) -> Result<Option<Refreshed>, DiffError> {
+    let rel_path = match vfs::path::relative_to(dir_path, path) {
+        Some(rel_path) => rel_path,
+        None => return Ok(None),
+    };
+    let (rel_dir, name) = match rel_path.rsplit_once('/') {
+        Some((rel_dir, name)) => (rel_dir.to_string(), name.to_string()),
+        None => (String::new(), rel_path.clone()),
+    };
+    if !rel_dir.is_empty() && Tree::find(tree, &rel_dir).is_none_or(|dir| dir.subdir.is_none()) {
+        return Ok(None);
+    }
+    let parent_path = if rel_dir.is_empty() {
+        dir_path.to_string()
+    } else {
+        vfs::path::join(dir_path, &rel_dir)
+    };
+
+    let ancestors = if rules.options.follow_symlinks {
+        ancestors_of(fs, dir_path, &rel_dir).await
+    } else {
+        vec![]
+    };
+    let listing = match fs.read_dir(&parent_path).await {
+        Ok(listing) => listing,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => vec![],
+        Err(error) => return Err(DiffError::from_io(&parent_path, error)),
+    };
+    let found = match listing.into_iter().find(|entry| entry.name == name) {
+        Some(entry) => Some(follow_symlink(fs, entry, &ancestors, rules).await),
+        None => None,
+    };
+    let mut found = match found {
+        Some(entry) if !excluded(fs, &entry, &rel_path, &rel_dir, rules).await => entry,
+        _ => {
+            return Ok(Some(Refreshed {
+                rel_dir,
+                name,
+                entry: None,
+            }))
         }
+    };
 
-        tree_flatted
-    }
+    let subdir = if found.is_dir() {
+        build_subdir(fs, &mut found, &rel_path, ancestors, rules).await?
+    } else {
+        None
+    };
+    let entry = if is_ignored_dir(&subdir, rules) {
+        None
+    } else {
+        let content_type = detect_content_type(fs, &found, rules).await;
+        Some(Tree {
+            name: found.name,
+            path: found.path,
+            rel_path,
+            kind: found.kind,
+            size: found.len,
+            inode: found.inode,
+            content_type,
+            subdir,
+        })
+    };
+
+    Ok(Some(Refreshed {
+        rel_dir,
+        name,
+        entry,
+    }))
 }
 
-impl From<Tree> for TreeComp {
-    fn from(tree: Tree) -> Self {
-        TreeComp {
-            name: tree.name,
-            subdir: if let Some(entry) = tree.subdir {
-                Some(entry.into_iter().map(TreeComp::from).collect())
-            } else {
-                None
-            },
+/// Replace the refreshed entry in the tree, or remove it if it doesn't exist anymore. The
+/// new entries are inserted before the first entry with a greater name. Return true if
+/// the tree changed
+fn replace_entry(tree: &mut Vec<Tree>, refreshed: Refreshed) -> bool {
+    let mut entries = tree;
+    for segment in refreshed
+        .rel_dir
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+    {
+        match entries
+            .iter_mut()
+            .find(|entry| entry.name == segment)
+            .and_then(|entry| entry.subdir.as_mut())
+        {
+            Some(subdir) => entries = subdir,
+            None => return false,
+        }
+    }
+    let position = entries
+        .iter()
+        .position(|entry| entry.name == refreshed.name);
+
+    match (refreshed.entry, position) {
+        (None, None) => false,
+        (None, Some(position)) => {
+            entries.remove(position);
+            true
+        }
+        (Some(updated), Some(position)) => {
+            let changed = entries[position] != updated;
+            entries[position] = updated;
+            changed
+        }
+        (Some(updated), None) => {
+            let position = entries
+                .iter()
+                .position(|entry| entry.name > updated.name)
+                .unwrap_or(entries.len());
+            entries.insert(position, updated);
+            true
         }
     }
 }
@@ -114,42 +1421,7 @@ impl TreeBuilder for Tree {
         excluding: Option<Vec<String>>,
         recursive_excluding: bool,
     ) -> Vec<Tree> {
-        let mut entries = fs::read_dir(dir_path).await.unwrap();
-        let mut tree: Vec<Tree> = vec![];
-        let mut exclude: Vec<String> = vec![];
-        if let Some(mut item) = excluding {
-            exclude.append(&mut item);
-        }
-
-        while let Some(entry) = entries.next_entry().await.unwrap() {
-            let file_name = entry.file_name().into_string().unwrap();
-
-            if !exclude.clone().into_iter().any(|item| item == file_name) {
-                let path: String = entry.path().into_os_string().into_string().unwrap();
-                tree.push(Tree {
-                    name: entry.file_name().into_string().unwrap(),
-                    path: path.clone(),
-                    subdir: if entry.path().is_dir() {
-                        Some(
-                            Tree::build_tree(
-                                path,
-                                if recursive_excluding {
-                                    Some(exclude.clone())
-                                } else {
-                                    None
-                                },
-                                recursive_excluding,
-                            )
-                            .await,
-                        )
-                    } else {
-                        None
-                    },
-                });
-            }
-        }
-
-        tree
+        Tree::build_tree_with(&LocalFs, dir_path, excluding, recursive_excluding).await
     }
 
     /// Compare two tree directories and return true if are different
@@ -170,11 +1442,18 @@ impl TreeBuilder for Tree {
     /// }
     /// ```
     fn tree_diff(dir_tree: Vec<Tree>, dir_tree_comp: Vec<Tree>) -> bool {
-        let dir_tree_from: Vec<TreeComp> = dir_tree.into_iter().map(TreeComp::from).collect();
-        let dir_tree_comp_from: Vec<TreeComp> =
-            dir_tree_comp.into_iter().map(TreeComp::from).collect();
+        let entries = |tree: &[Tree]| -> Vec<(String, EntryKind)> {
+            let mut entries: Vec<(String, EntryKind)> = Tree::iter(tree)
+                .map(|(_, entry)| {
+                    let rel_path = vfs::path::normalize_separators(&entry.rel_path);
+                    (rel_path.into_owned(), entry.kind)
+                })
+                .collect();
+            entries.sort();
+            entries
+        };
 
-        dir_tree_from != dir_tree_comp_from
+        entries(&dir_tree) != entries(&dir_tree_comp)
     }
 
     /// Get the content by string of all the files in one tree directory
@@ -192,24 +1471,18 @@ impl TreeBuilder for Tree {
     ///     assert_eq!(
     ///         content,
     ///         vec!(
-    ///             "Hello world",
     ///             "print(\"This line will be printed.\")",
-    ///             "new language",
+    ///             "Hello world",
     ///             "fn main() {\n    println(\"hello world\")\n}\n",
+    ///             "new language",
     ///         )
     ///     )
     /// }
     /// ```
     async fn get_content_files(dir_tree: Vec<Tree>) -> Vec<String> {
-        let file_list: TreeFlatted = TreeFlatted::from_iter(dir_tree);
-        let files = tokio_stream::iter(file_list.0);
-
-        let file_content: Vec<String> = files
-            .then(|file| async { fs::read_to_string(file.path).await.unwrap() })
-            .collect::<Vec<String>>()
-            .await;
-
-        file_content
+        Tree::get_content_files_with(&LocalFs, dir_tree, READ_CONCURRENCY)
+            .await
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 
     /// compare all the content from two tree directories and return true if both are equal
@@ -240,6 +1513,7 @@ impl TreeBuilder for Tree {
     }
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_false_equal_dir_tree() {
     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
@@ -250,6 +1524,7 @@ async fn should_return_false_equal_dir_tree() {
     assert_eq!(diff, false);
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_true_different_dir_tree() {
     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
@@ -260,6 +1535,7 @@ async fn should_return_true_different_dir_tree() {
     assert_eq!(diff, true);
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_all_file_content() {
     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
@@ -268,14 +1544,59 @@ async fn should_return_all_file_content() {
     assert_eq!(
         content,
         vec!(
-            "Hello world",
             "print(\"This line will be printed.\")",
-            "new language",
+            "Hello world",
             "fn main() {\n    println(\"hello world\")\n}\n",
+            "new language",
         )
     )
 }
 
+#[cfg(test)]
+#[tokio::test]
+async fn should_read_the_files_by_path() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .build();
+    let dir_one = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let contents = Tree::read_files_with(&fs, &dir_one, 1).await.unwrap();
+
+    assert_eq!(
+        contents.into_iter().collect::<Vec<_>>(),
+        vec![
+            ("hello.txt".to_string(), b"Hello world".to_vec()),
+            ("vlang/hello.v".to_string(), b"fn main() {}".to_vec()),
+        ]
+    );
+    assert!(matches!(
+        Tree::read_files_with(&crate::vfs::mem::MemFs::default(), &dir_one, 4).await,
+        Err(DiffError::NotFound { .. })
+    ));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_read_the_content_of_the_files_lossy() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/latin1.txt", vec![b'c', 0xe9])
+        .build();
+    let dir_one = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+
+    assert_eq!(
+        Tree::get_content_files_with(&fs, dir_one.clone(), 1)
+            .await
+            .unwrap(),
+        vec!["Hello world", "c\u{fffd}"]
+    );
+    assert!(matches!(
+        Tree::get_content_files_with(&crate::vfs::mem::MemFs::default(), dir_one, 4).await,
+        Err(DiffError::NotFound { .. })
+    ));
+}
+
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_true_if_both_dir_content_are_equal() {
     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
@@ -287,6 +1608,7 @@ async fn should_return_true_if_both_dir_content_are_equal() {
     assert_eq!(Tree::compare_dir_content(content_one, content_two), true);
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_false_if_both_dir_content_are_differents() {
     let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
@@ -298,6 +1620,7 @@ async fn should_return_false_if_both_dir_content_are_differents() {
     assert_eq!(Tree::compare_dir_content(content_one, content_four), false);
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_true_if_both_dir_tree_have_different_subdir_excluded_recursively() {
     let dir_one = Tree::build_tree(
@@ -319,6 +1642,7 @@ async fn should_return_true_if_both_dir_tree_have_different_subdir_excluded_recu
     assert_eq!(Tree::compare_dir_content(content_one, content_five), true);
 }
 
+#[cfg(test)]
 #[tokio::test]
 async fn should_return_false_if_both_dir_tree_have_different_subdir_excluded_not_recursively() {
     let dir_one = Tree::build_tree(
@@ -339,3 +1663,770 @@ async fn should_return_false_if_both_dir_tree_have_different_subdir_excluded_not
 
     assert_eq!(Tree::compare_dir_content(content_one, content_five), false);
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_find_entry_by_path() {
+    let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+
+    let purpose = Tree::find(&dir_one, "vlang/purpose/purpose.txt").unwrap();
+    assert_eq!(purpose.name, "purpose.txt");
+    assert_eq!(purpose.subdir, None);
+
+    assert!(Tree::find(&dir_one, "vlang/").unwrap().subdir.is_some());
+    assert_eq!(Tree::find(&dir_one, "vlang/hello.js"), None);
+    assert_eq!(Tree::find(&dir_one, "hello.txt/purpose"), None);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_find_entries_by_name() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir/hello.txt", "one")
+        .file("dir/sub/hello.txt", "two")
+        .file("dir/sub/other.txt", "three")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir".to_string(), None, false).await;
+
+    let found: Vec<&str> = Tree::find_by_name(&tree, "hello.txt")
+        .into_iter()
+        .map(|entry| entry.path.as_str())
+        .collect();
+    assert_eq!(found, vec!["dir/hello.txt", "dir/sub/hello.txt"]);
+    assert!(Tree::find_by_name(&tree, "missing").is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_iterate_depth_first() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir/a.txt", "a")
+        .file("dir/sub/b.txt", "b")
+        .file("dir/sub/deep/c.txt", "c")
+        .file("dir/z.txt", "z")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir".to_string(), None, false).await;
+
+    let paths: Vec<String> = Tree::iter(&tree).map(|(path, _)| path).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "a.txt",
+            "sub",
+            "sub/b.txt",
+            "sub/deep",
+            "sub/deep/c.txt",
+            "z.txt"
+        ]
+    );
+
+    let sub = Tree::find(&tree, "sub").unwrap();
+    let paths: Vec<String> = sub.into_iter().map(|(path, _)| path).collect();
+    assert_eq!(
+        paths,
+        vec!["sub", "sub/b.txt", "sub/deep", "sub/deep/c.txt"]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_flatten_only_files() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir/a.txt", "a")
+        .file("dir/sub/b.txt", "b")
+        .dir("dir/empty")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "dir".to_string(), None, false).await;
+
+    assert_eq!(
+        Tree::flatten(tree),
+        vec![
+            FileEntry {
+                name: "a.txt".to_string(),
+                path: "dir/a.txt".to_string(),
+            },
+            FileEntry {
+                name: "b.txt".to_string(),
+                path: "dir/sub/b.txt".to_string(),
+            },
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_merge_overlay_into_base() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("base/config.toml", "base")
+        .file("base/conf.d/a.conf", "base a")
+        .file("base/conf.d/b.conf", "base b")
+        .file("base/replaced/file.txt", "base")
+        .file("overlay/config.toml", "overlay")
+        .file("overlay/conf.d/b.conf", "overlay b")
+        .file("overlay/conf.d/c.conf", "overlay c")
+        .file("overlay/replaced", "now a file")
+        .build();
+    let base = Tree::build_tree_with(&fs, "base".to_string(), None, false).await;
+    let overlay = Tree::build_tree_with(&fs, "overlay".to_string(), None, false).await;
+    let merged = Tree::merge(base, overlay);
+
+    let paths: Vec<(String, String)> = Tree::iter(&merged)
+        .map(|(path, entry)| (path, entry.path.clone()))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            ("conf.d".to_string(), "overlay/conf.d".to_string()),
+            (
+                "conf.d/a.conf".to_string(),
+                "base/conf.d/a.conf".to_string()
+            ),
+            (
+                "conf.d/b.conf".to_string(),
+                "overlay/conf.d/b.conf".to_string()
+            ),
+            (
+                "conf.d/c.conf".to_string(),
+                "overlay/conf.d/c.conf".to_string()
+            ),
+            ("config.toml".to_string(), "overlay/config.toml".to_string()),
+            ("replaced".to_string(), "overlay/replaced".to_string()),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_count_files_dirs_and_bytes() {
+    let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+
+    assert_eq!(
+        Tree::stats(&dir_one),
+        TreeStats {
+            files: 4,
+            dirs: 2,
+            bytes: 11 + 35 + 41 + 12,
+        }
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_detect_files_swapping_contents() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/a.txt", "A")
+        .file("dir_one/b.txt", "B")
+        .file("dir_one/c.txt", "C")
+        .file("dir_two/a.txt", "B")
+        .file("dir_two/b.txt", "A")
+        .file("dir_two/c.txt", "C")
+        .build();
+    let dir_one = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let dir_two = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    let content_one = Tree::get_content_files_with(&fs, dir_one.clone(), 2)
+        .await
+        .unwrap();
+    let content_two = Tree::get_content_files_with(&fs, dir_two.clone(), 2)
+        .await
+        .unwrap();
+    assert!(Tree::compare_dir_content(content_one, content_two));
+
+    assert_eq!(
+        Tree::mismatched_files_with(&fs, &dir_one, &fs, &dir_two).await,
+        vec!["a.txt", "b.txt"]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_the_targets_of_the_symlinks() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/a.txt", "A")
+        .file("dir_one/b.txt", "A")
+        .symlink("dir_one/current", "a.txt")
+        .file("dir_two/a.txt", "A")
+        .file("dir_two/b.txt", "A")
+        .symlink("dir_two/current", "b.txt")
+        .build();
+    let dir_one = Tree::build_tree_with(&fs, "dir_one".to_string(), None, false).await;
+    let dir_two = Tree::build_tree_with(&fs, "dir_two".to_string(), None, false).await;
+
+    assert_eq!(
+        Tree::mismatched_files_with(&fs, &dir_one, &fs, &dir_two).await,
+        vec!["current"]
+    );
+    assert!(
+        crate::dir_diff_with(
+            &fs,
+            &fs,
+            DirDiff {
+                dir: "dir_one".to_string(),
+                dir_comp: "dir_two".to_string(),
+                ..Default::default()
+            }
+        )
+        .await
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_build_paths_relative_to_the_root() {
+    let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    let purpose = Tree::find(&dir_one, "vlang/purpose/purpose.txt").unwrap();
+
+    assert_eq!(purpose.rel_path, "vlang/purpose/purpose.txt");
+    assert!(Tree::iter(&dir_one).all(|(path, entry)| path == entry.rel_path));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_match_the_paths_of_a_windows_snapshot() {
+    fn to_windows(tree: Vec<Tree>) -> Vec<Tree> {
+        tree.into_iter()
+            .map(|entry| Tree {
+                rel_path: entry.rel_path.replace('/', "\\"),
+                subdir: entry.subdir.map(to_windows),
+                ..entry
+            })
+            .collect()
+    }
+    let dir_one = Tree::build_tree("./mocks/dir_one".to_string(), None, false).await;
+    let snapshot = to_windows(dir_one.clone());
+
+    assert_eq!(
+        Tree::find(&snapshot, "vlang/purpose/purpose.txt")
+            .unwrap()
+            .rel_path,
+        "vlang\\purpose\\purpose.txt"
+    );
+    assert!(!Tree::tree_diff(dir_one, snapshot));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_exclude_files_by_extension() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/app.js", "")
+        .file("dir_one/debug.LOG", "")
+        .file("dir_one/cache/module.pyc", "")
+        .file("dir_one/cache/module.py", "")
+        .dir("dir_one/logs.log")
+        .file("dir_one/Makefile", "")
+        .build();
+    let options = DirDiff {
+        exclude_extensions: Some(vec![".log".to_string(), "pyc".to_string()]),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec!["Makefile", "app.js", "cache", "cache/module.py", "logs.log"]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_exclude_files_by_size() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/empty.txt", "")
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/big/disk.img", vec![0u8; 4096])
+        .build();
+    let options = DirDiff {
+        min_size: Some(1),
+        max_size: Some(1024),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec!["big", "hello.txt"]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_ignore_empty_dirs() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/big/disk.img", vec![0u8; 4096])
+        .dir("dir_one/empty/nested")
+        .build();
+    let options = DirDiff {
+        max_size: Some(1024),
+        ignore_empty_dirs: true,
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec!["hello.txt"]
+    );
+}
+
+#[cfg(feature = "regex")]
+#[cfg(test)]
+#[tokio::test]
+async fn should_exclude_entries_matching_a_regex() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/src/main.rs", "")
+        .file("dir_one/src/main.rs.bak", "")
+        .file("dir_one/target/debug/app", "")
+        .file("dir_one/docs/target/index.md", "")
+        .build();
+    let options = DirDiff {
+        excluding_regex: Some(regex::RegexSet::new([r"^target$", r"\.bak$"]).unwrap()),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec![
+            "docs",
+            "docs/target",
+            "docs/target/index.md",
+            "src",
+            "src/main.rs"
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_keep_the_entries_selected_by_the_filter() {
+    use std::sync::Arc;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_one/vlang/purpose/purpose.txt", "test")
+        .build();
+    let options = DirDiff {
+        filter: Some(Arc::new(|entry: &DirEntryInfo| {
+            entry.depth <= 1 && entry.name != "hello.txt"
+        })),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec!["vlang", "vlang/hello.v", "vlang/purpose"]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_apply_the_events_to_the_tree() {
+    let options = DirDiff {
+        excluding: Some(vec!["target".to_string()]),
+        recursive_excluding: true,
+        ..Default::default()
+    };
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .build();
+    let mut tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello vlang!")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .file("dir_one/vlang/target/app", "")
+        .file("dir_one/docs/index.md", "# Docs")
+        .build();
+    let events = vec![
+        FsEvent::Modified("dir_one/hello.txt".to_string()),
+        FsEvent::Created("dir_one/vlang/target".to_string()),
+        FsEvent::Created("dir_one/docs".to_string()),
+        FsEvent::Created("other/hello.txt".to_string()),
+    ];
+    let mut changed = vec![];
+    for event in &events {
+        changed.push(
+            Tree::apply_event(&fs, &mut tree, "dir_one", event, &options)
+                .await
+                .unwrap(),
+        );
+    }
+
+    assert_eq!(changed, vec![true, false, true, false]);
+    assert_eq!(Tree::find(&tree, "hello.txt").unwrap().size, 12);
+    assert_eq!(
+        tree,
+        Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await
+    );
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello vlang!")
+        .file("dir_one/docs/index.md", "# Docs")
+        .build();
+    let event = FsEvent::Removed("dir_one/vlang".to_string());
+    assert!(
+        Tree::apply_event(&fs, &mut tree, "dir_one/", &event, &options)
+            .await
+            .unwrap()
+    );
+    assert!(Tree::find(&tree, "vlang").is_none());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_refresh_the_content_of_the_directories_of_the_events() {
+    let options = DirDiff::default();
+    let fs = crate::Forbidden::new();
+    let mut tree = Tree::build_tree_with_options(&fs.0, "dir_one".to_string(), &options).await;
+    let built = tree.clone();
+
+    // the listing of the secret directory fails
+    let event = FsEvent::Removed("dir_one/secret/hello.txt".to_string());
+    assert!(matches!(
+        Tree::apply_event(&fs, &mut tree, "dir_one", &event, &options).await,
+        Err(DiffError::PermissionDenied { .. })
+    ));
+    assert_eq!(tree, built);
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .file("dir_one/secret.txt", "Hello world")
+        .file("dir_one/secret/hello.txt", "Hello vlang")
+        .file("dir_one/secret/vlang/hello.v", "fn main() {}")
+        .build();
+    let event = FsEvent::Modified("dir_one/secret".to_string());
+    assert!(
+        Tree::apply_event(&fs, &mut tree, "dir_one", &event, &options)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        tree,
+        Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_build_the_tree_from_the_paths() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("repo/src/lib.rs", "pub mod tree;")
+        .file("repo/src/tree.rs", "pub struct Tree;")
+        .file("repo/target/debug/build.log", "ignored")
+        .file("repo/README.md", "# Repo")
+        .build();
+    let paths = vec![
+        PathBuf::from("src/tree.rs"),
+        PathBuf::from("repo/README.md"),
+        PathBuf::from("./src/lib.rs"),
+        PathBuf::from("src/deleted.rs"),
+    ];
+    let tree = Tree::from_paths_with(&fs, "repo", paths).await;
+
+    let entries: Vec<(String, String, u64)> = Tree::iter(&tree)
+        .map(|(path, entry)| (path, entry.path.clone(), entry.size))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("README.md".to_string(), "repo/README.md".to_string(), 6),
+            ("src".to_string(), "repo/src".to_string(), 0),
+            ("src/lib.rs".to_string(), "repo/src/lib.rs".to_string(), 13),
+            (
+                "src/tree.rs".to_string(),
+                "repo/src/tree.rs".to_string(),
+                16
+            ),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_stop_at_the_symlink_cycles() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir/a.txt", "a")
+        .file("dir/sub/b.txt", "b")
+        .symlink("dir/sub/loop", "..")
+        .symlink("dir/link", "sub")
+        .symlink("dir/a_link", "a.txt")
+        .symlink("dir/broken", "missing.txt")
+        .build();
+    let options = DirDiff {
+        follow_symlinks: true,
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir".to_string(), &options).await;
+
+    let entries: Vec<(String, EntryKind)> = Tree::iter(&tree)
+        .map(|(path, entry)| (path, entry.kind))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("a.txt".to_string(), EntryKind::File),
+            ("a_link".to_string(), EntryKind::File),
+            ("broken".to_string(), EntryKind::Symlink),
+            ("link".to_string(), EntryKind::Dir),
+            ("link/b.txt".to_string(), EntryKind::File),
+            ("link/loop".to_string(), EntryKind::Cycle),
+            ("sub".to_string(), EntryKind::Dir),
+            ("sub/b.txt".to_string(), EntryKind::File),
+            ("sub/loop".to_string(), EntryKind::Cycle),
+        ]
+    );
+    assert_eq!(
+        Tree::find(&tree, "link/b.txt").unwrap().path,
+        "dir/link/b.txt"
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_stop_at_the_max_entries() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir/a.txt", "a")
+        .file("dir/b/c.txt", "c")
+        .file("dir/b/d.txt", "d")
+        .file("dir/e.txt", "e")
+        .build();
+    let options = DirDiff {
+        max_entries: Some(3),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir".to_string(), &options).await;
+
+    let paths: Vec<String> = Tree::iter(&tree).map(|(path, _)| path).collect();
+    assert_eq!(paths, vec!["a.txt", "b", "b/c.txt"]);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_visit_the_tree() {
+    use crate::vfs::mem::MemFs;
+
+    // bytes of every directory, without the ones of `skipped`
+    struct DirSizes {
+        sizes: Vec<(String, u64)>,
+        stack: Vec<u64>,
+    }
+
+    impl TreeVisitor for DirSizes {
+        fn visit_dir(&mut self, dir: &Tree) -> bool {
+            self.stack.push(0);
+            dir.name != "skipped"
+        }
+
+        fn leave_dir(&mut self, dir: &Tree) {
+            let size = self.stack.pop().unwrap();
+            if let Some(parent) = self.stack.last_mut() {
+                *parent += size;
+            }
+            self.sizes.push((dir.rel_path.clone(), size));
+        }
+
+        fn visit_file(&mut self, file: &Tree) {
+            *self.stack.last_mut().unwrap() += file.size;
+        }
+    }
+
+    let fs = MemFs::builder()
+        .file("root/a/one.txt", "1")
+        .file("root/a/b/two.txt", "22")
+        .file("root/skipped/three.txt", "333")
+        .symlink("root/a/link", "one.txt")
+        .build();
+    let tree = Tree::build_tree_with(&fs, "root".to_string(), None, false).await;
+    let mut visitor = DirSizes {
+        sizes: vec![],
+        stack: vec![0],
+    };
+    Tree::accept(&tree, &mut visitor);
+
+    assert_eq!(
+        visitor.sizes,
+        vec![
+            ("a/b".to_string(), 2),
+            ("a".to_string(), 3),
+            ("skipped".to_string(), 0)
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_walk_the_directory_lazily() {
+    use crate::vfs::mem::MemFs;
+
+    let fs = MemFs::builder()
+        .file("root/b.txt", "b")
+        .file("root/a/two.txt", "22")
+        .file("root/a/one.txt", "1")
+        .dir("root/c")
+        .build();
+    let paths: Vec<String> = Tree::walk_with(&fs, "root")
+        .map(|entry| entry.unwrap().path)
+        .collect()
+        .await;
+
+    assert_eq!(
+        paths,
+        vec![
+            "root/a",
+            "root/a/one.txt",
+            "root/a/two.txt",
+            "root/b.txt",
+            "root/c"
+        ]
+    );
+    let missing: Vec<Result<VfsEntry, DiffError>> = Tree::walk_with(&fs, "missing").collect().await;
+    assert!(matches!(missing[..], [Err(DiffError::NotFound { .. })]));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_exclude_the_entries_by_their_metadata() {
+    use crate::diff::MetadataExclusion;
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now();
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file_modified("dir_one/new.log", "new", now)
+        .file_modified("dir_one/old.log", "old", now - Duration::from_secs(3600))
+        .file_modified(
+            "dir_one/logs/old.log",
+            "old",
+            now - Duration::from_secs(3600),
+        )
+        .build();
+    let options = DirDiff {
+        exclude_by_metadata: Some(MetadataExclusion::new(move |entry, metadata| {
+            !entry.is_dir
+                && metadata
+                    .modified
+                    .is_some_and(|modified| modified < now - Duration::from_secs(60))
+        })),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec!["logs", "new.log"]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_exclude_the_names_only_inside_of_their_scope() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("root/cache/a", "a")
+        .file("root/var/cache/b", "b")
+        .file("root/var/lib/cache/c", "c")
+        .file("root/usr/cache/d", "d")
+        .build();
+    let options = DirDiff {
+        scoped_excluding: Some(vec!["var/cache".to_string()]),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "root".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree)
+            .filter(|(_, entry)| entry.subdir.is_none())
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>(),
+        vec!["cache/a", "usr/cache/d"]
+    );
+
+    let options = DirDiff {
+        scoped_excluding: Some(vec!["cache".to_string()]),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "root".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree)
+            .filter(|(_, entry)| entry.subdir.is_none())
+            .count(),
+        0
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_exclude_the_entries_by_their_relative_path() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("dir_one/purpose/purpose.txt", "root")
+        .file("dir_one/vlang/purpose/purpose.txt", "vlang")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .build();
+    let options = DirDiff {
+        excluding_paths: Some(vec!["vlang/purpose/".to_string()]),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "dir_one".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec!["purpose", "purpose/purpose.txt", "vlang", "vlang/hello.v"]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_exclude_the_entries_matching_the_patterns() {
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("root/build/a", "a")
+        .file("root/vlang/build/b", "b")
+        .file("root/vlang/notes/build", "c")
+        .file("root/docs/target/d", "d")
+        .file("root/docs/e.tmp", "e")
+        .build();
+    let options = DirDiff {
+        excluding_patterns: Some(vec![
+            "/build".to_string(),
+            "target/".to_string(),
+            "**/*.tmp".to_string(),
+        ]),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "root".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree)
+            .filter(|(_, entry)| entry.subdir.is_none())
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>(),
+        vec!["vlang/build/b", "vlang/notes/build"]
+    );
+}
+
+#[cfg(all(test, feature = "globset"))]
+#[tokio::test]
+async fn should_exclude_the_entries_matching_the_glob_set() {
+    use globset::{Glob, GlobSetBuilder};
+
+    let fs = crate::vfs::mem::MemFs::builder()
+        .file("root/hello.txt", "a")
+        .file("root/logs/today.log", "b")
+        .file("root/vlang/hello.v", "c")
+        .build();
+    let mut globs = GlobSetBuilder::new();
+    globs.add(Glob::new("logs").unwrap());
+    globs.add(Glob::new("**/*.v").unwrap());
+    let options = DirDiff {
+        excluding_globs: Some(globs.build().unwrap()),
+        ..Default::default()
+    };
+    let tree = Tree::build_tree_with_options(&fs, "root".to_string(), &options).await;
+
+    assert_eq!(
+        Tree::iter(&tree).map(|(path, _)| path).collect::<Vec<_>>(),
+        vec!["hello.txt", "vlang"]
+    );
+}
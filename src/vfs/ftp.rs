@@ -0,0 +1,193 @@
+//! Remote directories exposed by a FTP or FTPS server through [suppaftp](https://crates.io/crates/suppaftp)
+use super::{DiffFs, EntryKind, VfsEntry, VfsMetadata};
+use async_trait::async_trait;
+use std::convert::TryFrom;
+use std::io;
+use suppaftp::list::File;
+use suppaftp::tokio::{AsyncFtpStream, ImplAsyncFtpStream, TokioTlsStream};
+use suppaftp::types::FileType;
+use suppaftp::FtpError;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Mutex;
+
+pub use suppaftp;
+
+/// FTP server source, the paths of the tree are the absolute paths inside of the server.
+/// The commands are sent one by one through the same control connection
+pub struct FtpFs<T: TokioTlsStream + Send = suppaftp::tokio::AsyncNoTlsStream> {
+    stream: Mutex<ImplAsyncFtpStream<T>>,
+}
+
+impl FtpFs {
+    /// Connect to a plain FTP server and login with the user and password
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use spielrs_diff::{dir_diff_with, diff::DirDiff, vfs::{LocalFs, ftp::FtpFs}};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let remote = FtpFs::connect("ftp.example.com:21", "anonymous", "anonymous")
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let diff = dir_diff_with(&remote, &LocalFs, DirDiff {
+    ///         dir: "/pub/mirror".to_string(),
+    ///         dir_comp: "./mirror".to_string(),
+    ///         excluding: None,
+    ///         recursive_excluding: false,
+    ///         ..Default::default()
+    ///     })
+    ///     .await;
+    ///
+    ///     println!("{}", diff);
+    /// }
+    /// ```
+    pub async fn connect(addr: &str, user: &str, password: &str) -> io::Result<Self> {
+        let mut stream = AsyncFtpStream::connect(addr).await.map_err(ftp_error)?;
+        stream.login(user, password).await.map_err(ftp_error)?;
+
+        FtpFs::from_stream(stream).await
+    }
+}
+
+impl<T: TokioTlsStream + Send> FtpFs<T> {
+    /// Use an already connected and logged in stream, for example a FTPS stream
+    /// secured with `into_secure` (feature `ftps`)
+    pub async fn from_stream(mut stream: ImplAsyncFtpStream<T>) -> io::Result<Self> {
+        stream
+            .transfer_type(FileType::Binary)
+            .await
+            .map_err(ftp_error)?;
+
+        Ok(FtpFs {
+            stream: Mutex::new(stream),
+        })
+    }
+}
+
+#[async_trait]
+impl<T: TokioTlsStream + Send + Sync + 'static> DiffFs for FtpFs<T> {
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<VfsEntry>> {
+        let lines = self
+            .stream
+            .lock()
+            .await
+            .list(Some(path))
+            .await
+            .map_err(ftp_error)?;
+
+        Ok(parse_list(path, &lines))
+    }
+
+    /// The metadata is taken from the listing of the parent directory
+    async fn metadata(&self, path: &str) -> io::Result<VfsMetadata> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let parent = if parent.is_empty() { "/" } else { parent };
+        let lines = self
+            .stream
+            .lock()
+            .await
+            .list(Some(parent))
+            .await
+            .map_err(ftp_error)?;
+
+        lines
+            .iter()
+            .filter_map(|line| File::try_from(line.as_str()).ok())
+            .find(|file| file.name() == name)
+            .map(|file| VfsMetadata {
+                kind: if file.is_directory() {
+                    EntryKind::Dir
+                } else {
+                    EntryKind::File
+                },
+                len: file.size() as u64,
+                modified: Some(file.modified()),
+                inode: None,
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_string()))
+    }
+
+    async fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let mut stream = self.stream.lock().await;
+        let mut transfer = stream.retr_as_stream(path).await.map_err(ftp_error)?;
+        let mut content: Vec<u8> = vec![];
+        transfer.read_to_end(&mut content).await?;
+        transfer.finish().await.map_err(ftp_error)?;
+
+        Ok(content)
+    }
+}
+
+fn ftp_error(error: FtpError) -> io::Error {
+    match error {
+        FtpError::ConnectionError(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+/// Parse the lines of a `LIST` response, in posix or dos format
+fn parse_list(dir: &str, lines: &[String]) -> Vec<VfsEntry> {
+    lines
+        .iter()
+        .filter_map(|line| File::try_from(line.as_str()).ok())
+        .filter(|file| file.name() != "." && file.name() != "..")
+        .map(|file| VfsEntry {
+            name: file.name().to_string(),
+            path: format!("{}/{}", dir.trim_end_matches('/'), file.name()),
+            kind: if file.is_directory() {
+                EntryKind::Dir
+            } else if file.is_symlink() {
+                EntryKind::Symlink
+            } else {
+                EntryKind::File
+            },
+            len: if file.is_directory() {
+                0
+            } else {
+                file.size() as u64
+            },
+            inode: None,
+        })
+        .collect()
+}
+
+#[test]
+fn should_parse_ftp_listing() {
+    let lines: Vec<String> = vec![
+        "drwxr-xr-x    3 ftp      ftp          4096 Jun 02 10:00 .".to_string(),
+        "-rw-r--r--    1 ftp      ftp            36 Jun 02 10:00 hello.py".to_string(),
+        "-rw-r--r--    1 ftp      ftp            11 Jun 02 10:00 hello.txt".to_string(),
+        "10-19-20  03:19PM       <DIR>          vlang".to_string(),
+    ];
+
+    assert_eq!(
+        parse_list("/pub/dir_one/", &lines),
+        vec![
+            VfsEntry {
+                name: "hello.py".to_string(),
+                path: "/pub/dir_one/hello.py".to_string(),
+                kind: EntryKind::File,
+                len: 36,
+                inode: None,
+            },
+            VfsEntry {
+                name: "hello.txt".to_string(),
+                path: "/pub/dir_one/hello.txt".to_string(),
+                kind: EntryKind::File,
+                len: 11,
+                inode: None,
+            },
+            VfsEntry {
+                name: "vlang".to_string(),
+                path: "/pub/dir_one/vlang".to_string(),
+                kind: EntryKind::Dir,
+                len: 0,
+                inode: None,
+            },
+        ]
+    );
+}
@@ -0,0 +1,372 @@
+//! Trees of the revisions of a git repository through [git2](https://crates.io/crates/git2)
+use super::{DiffFs, EntryKind, VfsEntry, VfsMetadata};
+use crate::diff::DirDiff;
+use crate::report::DiffReport;
+use crate::{dir_diff_report_with, rt};
+use async_trait::async_trait;
+use git2::{ErrorCode, ObjectType, Oid, Repository};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub use git2;
+
+/// File modes of the git trees which aren't files nor directories
+const MODE_SYMLINK: i32 = 0o120000;
+const MODE_SUBMODULE: i32 = 0o160000;
+
+/// Tree of a revision of a git repository, read from its objects without checking it
+/// out. The paths are relative to the root of the repository, which is `""` or `.`.
+/// The submodules are `EntryKind::Other`
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use spielrs_diff::{dir_diff_with, diff::DirDiff, vfs::{LocalFs, git::GitFs}};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     // does the deployed directory match the tag?
+///     let release = GitFs::open(".", "v1.2.3").unwrap();
+///
+///     let diff = dir_diff_with(&release, &LocalFs, DirDiff {
+///         dir: "www".to_string(),
+///         dir_comp: "/srv/www".to_string(),
+///         ..Default::default()
+///     })
+///     .await;
+///
+///     println!("{}", diff);
+/// }
+/// ```
+pub struct GitFs {
+    repository: Arc<Mutex<Repository>>,
+    tree: Oid,
+}
+
+impl GitFs {
+    /// Open the repository containing `path` and resolve the revision to its tree, the
+    /// revision is anything accepted by `git rev-parse`, like `v1.2.3`, `main~2` or the
+    /// id of a commit
+    pub fn open(path: &str, revision: &str) -> io::Result<Self> {
+        let repository = Repository::discover(path).map_err(git_error)?;
+
+        GitFs::from_repository(repository, revision)
+    }
+
+    /// Use an already opened repository, like a bare one
+    pub fn from_repository(repository: Repository, revision: &str) -> io::Result<Self> {
+        let tree = tree_of(&repository, revision)?;
+
+        Ok(GitFs {
+            repository: Arc::new(Mutex::new(repository)),
+            tree,
+        })
+    }
+
+    /// Another revision of the same repository, which is not opened again
+    pub fn at(&self, revision: &str) -> io::Result<Self> {
+        let tree = tree_of(&self.repository.lock().unwrap(), revision)?;
+
+        Ok(GitFs {
+            repository: self.repository.clone(),
+            tree,
+        })
+    }
+
+    /// Run the function with the repository, the object at the path of the tree and its
+    /// file mode in the threads for the blocking tasks
+    async fn with_object<T: Send + 'static>(
+        &self,
+        path: &str,
+        function: impl FnOnce(&Repository, git2::Object, i32) -> io::Result<T> + Send + 'static,
+    ) -> io::Result<T> {
+        let repository = self.repository.clone();
+        let tree = self.tree;
+        let path = relative(path).to_string();
+
+        rt::spawn_blocking(move || {
+            let repository = repository.lock().unwrap();
+            let tree = repository.find_tree(tree).map_err(git_error)?;
+            let (object, mode) = if path.is_empty() {
+                (tree.into_object(), 0o040000)
+            } else {
+                let entry = tree.get_path(Path::new(&path)).map_err(git_error)?;
+                let mode = entry.filemode();
+                if mode == MODE_SUBMODULE {
+                    return Err(io::Error::other(format!("{} is a submodule", path)));
+                }
+                (entry.to_object(&repository).map_err(git_error)?, mode)
+            };
+
+            function(&repository, object, mode)
+        })
+        .await?
+    }
+}
+
+/// Compare the trees of two revisions of the repository containing `path` without
+/// checking them out, `dir` and `dir_comp` are the directories compared inside of each
+/// tree, the root if they are empty
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use spielrs_diff::{diff::DirDiff, vfs::git::diff_revisions};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let report = diff_revisions(".", "v1.2.2", "v1.2.3", DirDiff::default())
+///         .await
+///         .unwrap();
+///
+///     print!("{}", report);
+/// }
+/// ```
+pub async fn diff_revisions(
+    path: &str,
+    revision: &str,
+    revision_comp: &str,
+    dir_diff_options: DirDiff,
+) -> io::Result<DiffReport> {
+    let fs = GitFs::open(path, revision)?;
+    let fs_comp = fs.at(revision_comp)?;
+
+    Ok(dir_diff_report_with(&fs, &fs_comp, dir_diff_options).await)
+}
+
+/// Id of the tree of the revision
+fn tree_of(repository: &Repository, revision: &str) -> io::Result<Oid> {
+    Ok(repository
+        .revparse_single(revision)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(git_error)?
+        .id())
+}
+
+/// Path inside of the tree, without the `./` and the separators at the edges
+fn relative(path: &str) -> &str {
+    let path = path.trim_start_matches("./").trim_matches('/');
+    if path == "." {
+        ""
+    } else {
+        path
+    }
+}
+
+fn git_error(error: git2::Error) -> io::Error {
+    match error.code() {
+        ErrorCode::NotFound => io::Error::new(io::ErrorKind::NotFound, error.message()),
+        _ => io::Error::other(error.message()),
+    }
+}
+
+fn kind(mode: i32, object_type: Option<ObjectType>) -> EntryKind {
+    match (mode, object_type) {
+        (MODE_SYMLINK, _) => EntryKind::Symlink,
+        (MODE_SUBMODULE, _) => EntryKind::Other,
+        (_, Some(ObjectType::Tree)) => EntryKind::Dir,
+        (_, Some(ObjectType::Blob)) => EntryKind::File,
+        _ => EntryKind::Other,
+    }
+}
+
+#[async_trait]
+impl DiffFs for GitFs {
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<VfsEntry>> {
+        let dir = relative(path).to_string();
+
+        self.with_object(path, move |repository, object, _| {
+            let tree = object
+                .into_tree()
+                .map_err(|_| io::Error::other(format!("{} is not a directory", dir)))?;
+            let odb = repository.odb().map_err(git_error)?;
+
+            tree.iter()
+                .map(|entry| {
+                    let name = String::from_utf8_lossy(entry.name_bytes()).into_owned();
+                    let kind = kind(entry.filemode(), entry.kind());
+                    // the size is in the header of the object, the content is not read
+                    let len = if kind == EntryKind::File {
+                        odb.read_header(entry.id()).map_err(git_error)?.0 as u64
+                    } else {
+                        0
+                    };
+
+                    Ok(VfsEntry {
+                        path: if dir.is_empty() {
+                            name.clone()
+                        } else {
+                            format!("{}/{}", dir, name)
+                        },
+                        name,
+                        kind,
+                        len,
+                        inode: None,
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn metadata(&self, path: &str) -> io::Result<VfsMetadata> {
+        self.with_object(path, |_, object, _| {
+            Ok(VfsMetadata {
+                kind: if object.kind() == Some(ObjectType::Tree) {
+                    EntryKind::Dir
+                } else {
+                    EntryKind::File
+                },
+                len: object.as_blob().map_or(0, |blob| blob.size() as u64),
+                modified: None,
+                inode: None,
+            })
+        })
+        .await
+    }
+
+    async fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let file = path.to_string();
+
+        self.with_object(path, move |_, object, _| match object.as_blob() {
+            Some(blob) => Ok(blob.content().to_vec()),
+            None => Err(io::Error::other(format!("{} is not a file", file))),
+        })
+        .await
+    }
+
+    async fn read_link(&self, path: &str) -> io::Result<String> {
+        let link = path.to_string();
+
+        self.with_object(path, move |_, object, mode| match object.as_blob() {
+            Some(blob) if mode == MODE_SYMLINK => {
+                Ok(String::from_utf8_lossy(blob.content()).into_owned())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a symbolic link", link),
+            )),
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_a_tag_with_a_directory() {
+    use crate::{diff::DirDiff, dir_diff_report_with, report::Difference, vfs::mem::MemFs};
+
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_git_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repository = Repository::init_bare(&dir).unwrap();
+    {
+        let blob = |content: &[u8]| repository.blob(content).unwrap();
+        let mut www = repository.treebuilder(None).unwrap();
+        www.insert("index.html", blob(b"<h1>v1</h1>"), 0o100644)
+            .unwrap();
+        www.insert("current", blob(b"index.html"), MODE_SYMLINK)
+            .unwrap();
+        let www = www.write().unwrap();
+        let mut root = repository.treebuilder(None).unwrap();
+        root.insert("www", www, 0o040000).unwrap();
+        root.insert("README.md", blob(b"# site"), 0o100644).unwrap();
+        let tree = repository.find_tree(root.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("spielrs", "spielrs@example.com").unwrap();
+        let commit = repository
+            .commit(None, &signature, &signature, "v1", &tree, &[])
+            .unwrap();
+        repository
+            .tag_lightweight(
+                "v1.2.3",
+                &repository.find_object(commit, None).unwrap(),
+                false,
+            )
+            .unwrap();
+    }
+    let release = GitFs::from_repository(repository, "v1.2.3").unwrap();
+    let deployed = MemFs::builder()
+        .file("srv/www/index.html", "<h1>v2</h1>")
+        .symlink("srv/www/current", "index.html")
+        .file("srv/www/robots.txt", "")
+        .build();
+
+    assert_eq!(release.read("README.md").await.unwrap(), b"# site");
+    assert_eq!(
+        release.read_link("www/current").await.unwrap(),
+        "index.html"
+    );
+    assert!(release.metadata(".").await.unwrap().is_dir());
+    let report = dir_diff_report_with(
+        &release,
+        &deployed,
+        DirDiff {
+            dir: "www".to_string(),
+            dir_comp: "srv/www".to_string(),
+            ..Default::default()
+        },
+    )
+    .await;
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        report.differences,
+        vec![
+            Difference::Modified("index.html".to_string()),
+            Difference::Added("robots.txt".to_string()),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_two_revisions() {
+    use crate::report::Difference;
+
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_revisions_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repository = Repository::init_bare(&dir).unwrap();
+    let commit = |files: &[(&str, &str)], tag: &str| {
+        let mut root = repository.treebuilder(None).unwrap();
+        for (name, content) in files {
+            let blob = repository.blob(content.as_bytes()).unwrap();
+            root.insert(name, blob, 0o100644).unwrap();
+        }
+        let tree = repository.find_tree(root.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("spielrs", "spielrs@example.com").unwrap();
+        let commit = repository
+            .commit(None, &signature, &signature, tag, &tree, &[])
+            .unwrap();
+        repository
+            .tag_lightweight(tag, &repository.find_object(commit, None).unwrap(), false)
+            .unwrap();
+    };
+    commit(&[("main.rs", "fn main() {}"), ("old.rs", "// old")], "v1");
+    commit(
+        &[("main.rs", "fn main() { run() }"), ("new.rs", "// new")],
+        "v2",
+    );
+
+    let report = diff_revisions(
+        dir.to_str().unwrap(),
+        "v1",
+        "v2",
+        DirDiff {
+            report_text_diffs: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        report.differences,
+        vec![
+            Difference::Modified("main.rs".to_string()),
+            Difference::Added("new.rs".to_string()),
+            Difference::Removed("old.rs".to_string()),
+        ]
+    );
+    assert_eq!(report.text_diffs["main.rs"][0].header(), "@@ -1 +1 @@");
+}
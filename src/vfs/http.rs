@@ -0,0 +1,325 @@
+//! Remote directories exposed by a http file server, through a [WebDAV](http://webdav.org)
+//! `PROPFIND` listing or a static html index page (like the autoindex of nginx or apache)
+use super::{DiffFs, EntryKind, VfsEntry, VfsMetadata};
+use async_trait::async_trait;
+use percent_encoding::percent_decode_str;
+use reqwest::{header::HeaderValue, Client, Method, Url};
+use std::io;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?><propfind xmlns="DAV:"><prop><resourcetype/><getcontentlength/></prop></propfind>"#;
+
+/// How the http server lists the content of a directory
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Listing {
+    /// `PROPFIND` request with `Depth: 1`
+    WebDav,
+    /// html page with a link by entry, directories end with `/`
+    Index,
+}
+
+/// Http file server source, the paths of the tree are the urls of every entry
+#[derive(Debug, Clone)]
+pub struct HttpFs {
+    client: Client,
+    listing: Listing,
+}
+
+impl HttpFs {
+    /// Create a http source which lists the directories with the `listing` method
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use spielrs_diff::tree::{Tree, TreeBuilder};
+    /// use spielrs_diff::vfs::http::{HttpFs, Listing};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let remote = HttpFs::new(Listing::Index);
+    ///     let tree = Tree::build_tree_with(
+    ///         &remote,
+    ///         "http://localhost:8080/mirror/".to_string(),
+    ///         None,
+    ///         false,
+    ///     )
+    ///     .await;
+    ///
+    ///     println!("{:#?}", tree);
+    /// }
+    /// ```
+    pub fn new(listing: Listing) -> Self {
+        HttpFs::with_client(Client::new(), listing)
+    }
+
+    /// Create a http source using an already configured client (authentication, proxies, timeouts...)
+    pub fn with_client(client: Client, listing: Listing) -> Self {
+        HttpFs { client, listing }
+    }
+}
+
+#[async_trait]
+impl DiffFs for HttpFs {
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<VfsEntry>> {
+        let dir = dir_url(path)?;
+        let request = match self.listing {
+            Listing::WebDav => self
+                .client
+                .request(Method::from_bytes(b"PROPFIND").unwrap(), dir.clone())
+                .header("Depth", HeaderValue::from_static("1"))
+                .header("Content-Type", HeaderValue::from_static("application/xml"))
+                .body(PROPFIND_BODY),
+            Listing::Index => self.client.get(dir.clone()),
+        };
+        let body = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(io::Error::other)?
+            .text()
+            .await
+            .map_err(io::Error::other)?;
+
+        Ok(match self.listing {
+            Listing::WebDav => parse_propfind(&dir, &body),
+            Listing::Index => parse_index(&dir, &body),
+        })
+    }
+
+    /// The size is taken from the `Content-Length` of a `HEAD` request and the
+    /// urls ending with `/` are directories
+    async fn metadata(&self, path: &str) -> io::Result<VfsMetadata> {
+        let url = Url::parse(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let response = self
+            .client
+            .head(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(io::Error::other)?;
+
+        Ok(VfsMetadata {
+            kind: if path.ends_with('/') {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            },
+            len: response.content_length().unwrap_or(0),
+            modified: None,
+            inode: None,
+        })
+    }
+
+    async fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let url = Url::parse(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let content = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(io::Error::other)?
+            .bytes()
+            .await
+            .map_err(io::Error::other)?;
+
+        Ok(content.to_vec())
+    }
+}
+
+/// Parse the url of a directory, making sure that it ends with `/` to resolve its entries
+fn dir_url(path: &str) -> io::Result<Url> {
+    let path = if path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/", path)
+    };
+
+    Url::parse(&path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Return the entry if the href is a direct child of the directory
+fn child_entry(dir: &Url, href: &str, is_dir: Option<bool>) -> Option<VfsEntry> {
+    let url = dir.join(&decode_entities(href)).ok()?;
+    if url.origin() != dir.origin() {
+        return None;
+    }
+
+    let rest = url.path().strip_prefix(dir.path())?.to_string();
+    let name = rest.trim_end_matches('/');
+    if name.is_empty() || name.contains('/') {
+        return None;
+    }
+
+    let is_dir = is_dir.unwrap_or_else(|| rest.ends_with('/'));
+    let mut url = url;
+    url.set_query(None);
+    url.set_fragment(None);
+    let mut path = url.to_string();
+    if is_dir && !path.ends_with('/') {
+        path.push('/');
+    }
+
+    Some(VfsEntry {
+        name: percent_decode_str(name).decode_utf8_lossy().into_owned(),
+        path,
+        kind: if is_dir {
+            EntryKind::Dir
+        } else {
+            EntryKind::File
+        },
+        len: 0,
+        inode: None,
+    })
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Local name of a xml tag without the namespace prefix, attributes and closing marks
+fn tag_name(tag: &str) -> &str {
+    let tag = tag.trim_start_matches('/');
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .next()
+        .unwrap_or("");
+
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Extract the entries of a WebDAV multistatus response
+fn parse_propfind(dir: &Url, body: &str) -> Vec<VfsEntry> {
+    let mut entries: Vec<VfsEntry> = vec![];
+    let mut href: Option<String> = None;
+    let mut is_collection = false;
+    let mut len: u64 = 0;
+    let mut in_href = false;
+
+    for chunk in body.split('<').skip(1) {
+        let (tag, text) = chunk.split_once('>').unwrap_or((chunk, ""));
+        let closing = tag.starts_with('/');
+
+        match (tag_name(tag), closing) {
+            ("response", false) => {
+                href = None;
+                is_collection = false;
+                len = 0;
+            }
+            ("response", true) => {
+                if let Some(mut entry) = href
+                    .take()
+                    .and_then(|href| child_entry(dir, &href, Some(is_collection)))
+                {
+                    if !entry.is_dir() {
+                        entry.len = len;
+                    }
+                    entries.push(entry);
+                }
+            }
+            ("getcontentlength", false) => len = text.trim().parse().unwrap_or(0),
+            ("href", false) => {
+                in_href = true;
+                href = Some(text.trim().to_string());
+            }
+            ("href", true) => in_href = false,
+            ("collection", false) => is_collection = true,
+            _ => {
+                if in_href {
+                    if let Some(href) = href.as_mut() {
+                        href.push_str(text.trim());
+                    }
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/// Extract the entries linked from a html index page
+fn parse_index(dir: &Url, body: &str) -> Vec<VfsEntry> {
+    let mut entries: Vec<VfsEntry> = vec![];
+
+    for chunk in body.split('<').skip(1) {
+        let tag = chunk.split('>').next().unwrap_or("");
+        if !tag_name(tag).eq_ignore_ascii_case("a") || tag.starts_with('/') {
+            continue;
+        }
+
+        let href = tag.find("href=").and_then(|start| {
+            let value = &tag[start + 5..];
+            match value.chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => value[1..].split(quote).next(),
+                _ => value.split_whitespace().next(),
+            }
+        });
+
+        if let Some(entry) = href.and_then(|href| child_entry(dir, href, None)) {
+            if !entries.iter().any(|item| item.path == entry.path) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    entries
+}
+
+#[test]
+fn should_parse_webdav_listing() {
+    let dir = dir_url("http://localhost/dav/dir_one").unwrap();
+    let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:multistatus xmlns:D="DAV:">
+  <D:response><D:href>/dav/dir_one/</D:href><D:propstat><D:prop><D:resourcetype><D:collection/></D:resourcetype></D:prop></D:propstat></D:response>
+  <D:response><D:href>/dav/dir_one/hello%20world.txt</D:href><D:propstat><D:prop><D:resourcetype/><D:getcontentlength>11</D:getcontentlength></D:prop></D:propstat></D:response>
+  <D:response><D:href>http://localhost/dav/dir_one/vlang/</D:href><D:propstat><D:prop><D:resourcetype><D:collection/></D:resourcetype></D:prop></D:propstat></D:response>
+</D:multistatus>"#;
+
+    assert_eq!(
+        parse_propfind(&dir, body),
+        vec![
+            VfsEntry {
+                name: "hello world.txt".to_string(),
+                path: "http://localhost/dav/dir_one/hello%20world.txt".to_string(),
+                kind: EntryKind::File,
+                len: 11,
+                inode: None,
+            },
+            VfsEntry {
+                name: "vlang".to_string(),
+                path: "http://localhost/dav/dir_one/vlang/".to_string(),
+                kind: EntryKind::Dir,
+                len: 0,
+                inode: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn should_parse_index_listing() {
+    let dir = dir_url("http://localhost/dir_one/").unwrap();
+    let body = r#"<html><body><h1>Index of /dir_one/</h1><hr><pre>
+<a href="../">../</a>
+<a href="?C=N;O=D">Name</a>
+<a href="hello.py">hello.py</a>
+<a href="/dir_one/hello.txt">hello.txt</a>
+<a href="vlang/">vlang/</a>
+<a href="http://example.com/other">other</a>
+</pre></body></html>"#;
+
+    assert_eq!(
+        parse_index(&dir, body)
+            .into_iter()
+            .map(|entry| (entry.name, entry.kind == EntryKind::Dir))
+            .collect::<Vec<_>>(),
+        vec![
+            ("hello.py".to_string(), false),
+            ("hello.txt".to_string(), false),
+            ("vlang".to_string(), true),
+        ]
+    );
+}
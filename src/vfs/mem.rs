@@ -0,0 +1,497 @@
+//! In memory filesystem, useful to test the diff logic without creating directories in disk
+use super::{DiffFs, EntryKind, VfsEntry, VfsMetadata};
+use crate::caps::FileCapabilities;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::io;
+use std::time::SystemTime;
+
+#[derive(Debug, PartialEq, Clone)]
+enum MemEntry {
+    File {
+        content: Vec<u8>,
+        modified: Option<SystemTime>,
+        /// inode shared by the hard links
+        inode: Option<u64>,
+        /// allocated regions if it is a sparse file
+        extents: Option<Vec<(u64, u64)>>,
+        capabilities: FileCapabilities,
+        /// alternate data streams by name
+        streams: BTreeMap<String, Vec<u8>>,
+    },
+    Dir,
+    Symlink {
+        target: String,
+    },
+}
+
+/// Filesystem stored in memory, created through `MemFs::builder`
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct MemFs {
+    entries: BTreeMap<String, MemEntry>,
+}
+
+/// Builder of a `MemFs`, the parent directories of every file are created automatically
+#[derive(Debug, Default)]
+pub struct MemFsBuilder {
+    fs: MemFs,
+}
+
+/// Remove the `./` and `/` at the end so the same path can be written of different ways
+fn normalize(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+    let path = path.strip_prefix("./").unwrap_or(path);
+
+    if path == "." {
+        String::new()
+    } else {
+        path.to_string()
+    }
+}
+
+fn parent(path: &str) -> Option<&str> {
+    path.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+/// Symbolic links followed at most resolving a path, like the `ELOOP` of linux
+const MAX_LINKS: usize = 40;
+
+impl MemFs {
+    /// Create a builder of an empty filesystem
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use spielrs_diff::{dir_diff_with, diff::DirDiff, vfs::mem::MemFs};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let fs = MemFs::builder()
+    ///         .file("dir_one/hello.txt", "Hello world")
+    ///         .file("dir_two/hello.txt", "Hello world")
+    ///         .dir("dir_two/empty")
+    ///         .build();
+    ///
+    ///     let diff = dir_diff_with(&fs, &fs, DirDiff {
+    ///         dir: "dir_one".to_string(),
+    ///         dir_comp: "dir_two".to_string(),
+    ///         excluding: None,
+    ///         recursive_excluding: false,
+    ///         ..Default::default()
+    ///     })
+    ///     .await;
+    ///
+    ///     assert_eq!(diff, true);
+    /// }
+    /// ```
+    pub fn builder() -> MemFsBuilder {
+        MemFsBuilder::default()
+    }
+
+    fn not_found(path: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, path.to_string())
+    }
+
+    /// Path of the entry following the symbolic links of its parents, and its own link
+    /// too if `follow` is true. The targets are relative to the directory of the link, or
+    /// to the root if they start with `/`. None if there are too many links
+    fn resolve(&self, path: &str, follow: bool) -> Option<String> {
+        self.resolve_links(&normalize(path), follow, &mut 0)
+    }
+
+    fn resolve_links(&self, path: &str, follow: bool, links: &mut usize) -> Option<String> {
+        let segments: Vec<&str> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let mut resolved = String::new();
+
+        for (index, segment) in segments.iter().enumerate() {
+            match *segment {
+                "." => continue,
+                ".." => {
+                    resolved = parent(&resolved).unwrap_or("").to_string();
+                    continue;
+                }
+                _ => {}
+            }
+            let candidate = if resolved.is_empty() {
+                segment.to_string()
+            } else {
+                format!("{}/{}", resolved, segment)
+            };
+            match self.entries.get(&candidate) {
+                Some(MemEntry::Symlink { target }) if follow || index + 1 < segments.len() => {
+                    *links += 1;
+                    if *links > MAX_LINKS {
+                        return None;
+                    }
+                    let target = match target.strip_prefix('/') {
+                        Some(absolute) => absolute.to_string(),
+                        None if resolved.is_empty() => target.clone(),
+                        None => format!("{}/{}", resolved, target),
+                    };
+                    resolved = self.resolve_links(&target, true, links)?;
+                }
+                _ => resolved = candidate,
+            }
+        }
+
+        Some(resolved)
+    }
+
+    /// Entry of the path following all its symbolic links
+    fn entry(&self, path: &str) -> Option<&MemEntry> {
+        self.entries.get(&self.resolve(path, true)?)
+    }
+}
+
+impl MemFsBuilder {
+    /// Add a directory and its parents
+    pub fn dir(mut self, path: &str) -> Self {
+        let mut path = normalize(path);
+        if path.is_empty() {
+            return self;
+        }
+
+        loop {
+            self.fs.entries.entry(path.clone()).or_insert(MemEntry::Dir);
+            match parent(&path) {
+                Some(parent_path) => path = parent_path.to_string(),
+                None => break,
+            }
+        }
+
+        self
+    }
+
+    /// Add a file with its content, replacing it if already exists
+    pub fn file<C: Into<Vec<u8>>>(self, path: &str, content: C) -> Self {
+        self.insert_file(path, content.into(), None)
+    }
+
+    /// Add a file with its content and modification time
+    pub fn file_modified<C: Into<Vec<u8>>>(
+        self,
+        path: &str,
+        content: C,
+        modified: SystemTime,
+    ) -> Self {
+        self.insert_file(path, content.into(), Some(modified))
+    }
+
+    /// Add a hard link to an existing file, both share the content and the inode.
+    /// The content is not updated if the file is replaced later
+    pub fn hard_link(mut self, path: &str, original: &str) -> Self {
+        let next_inode = self.fs.entries.len() as u64 + 1;
+        let (content, modified, inode, extents, capabilities, streams) =
+            match self.fs.entries.get_mut(&normalize(original)) {
+                Some(MemEntry::File {
+                    content,
+                    modified,
+                    inode,
+                    extents,
+                    capabilities,
+                    streams,
+                }) => (
+                    content.clone(),
+                    *modified,
+                    *inode.get_or_insert(next_inode),
+                    extents.clone(),
+                    *capabilities,
+                    streams.clone(),
+                ),
+                _ => panic!("{} is not a file", original),
+            };
+
+        self.insert(
+            path,
+            MemEntry::File {
+                content,
+                modified,
+                inode: Some(inode),
+                extents,
+                capabilities,
+                streams,
+            },
+        )
+    }
+
+    /// Add a symbolic link to the target, which doesn't need to exist
+    pub fn symlink(self, path: &str, target: &str) -> Self {
+        self.insert(
+            path,
+            MemEntry::Symlink {
+                target: target.to_string(),
+            },
+        )
+    }
+
+    /// Set the linux capabilities of an existing file
+    pub fn capabilities(mut self, path: &str, capabilities: FileCapabilities) -> Self {
+        match self.fs.entries.get_mut(&normalize(path)) {
+            Some(MemEntry::File {
+                capabilities: current,
+                ..
+            }) => *current = capabilities,
+            _ => panic!("{} is not a file", path),
+        }
+
+        self
+    }
+
+    /// Add an alternate data stream to an existing file, replacing it if already exists
+    pub fn stream<C: Into<Vec<u8>>>(mut self, path: &str, name: &str, content: C) -> Self {
+        match self.fs.entries.get_mut(&normalize(path)) {
+            Some(MemEntry::File { streams, .. }) => {
+                streams.insert(name.to_string(), content.into());
+            }
+            _ => panic!("{} is not a file", path),
+        }
+
+        self
+    }
+
+    fn insert_file(self, path: &str, content: Vec<u8>, modified: Option<SystemTime>) -> Self {
+        self.insert(
+            path,
+            MemEntry::File {
+                content,
+                modified,
+                inode: None,
+                extents: None,
+                capabilities: FileCapabilities::default(),
+                streams: BTreeMap::new(),
+            },
+        )
+    }
+
+    /// Add a sparse file of `len` bytes, only the regions of `data` are allocated and
+    /// the rest are holes filled with zeros
+    pub fn sparse_file(self, path: &str, len: u64, data: Vec<(u64, Vec<u8>)>) -> Self {
+        let mut content = vec![0u8; len as usize];
+        let mut extents: Vec<(u64, u64)> = vec![];
+        for (offset, bytes) in data {
+            content[offset as usize..offset as usize + bytes.len()].copy_from_slice(&bytes);
+            extents.push((offset, bytes.len() as u64));
+        }
+        extents.sort();
+
+        self.insert(
+            path,
+            MemEntry::File {
+                content,
+                modified: None,
+                inode: None,
+                extents: Some(extents),
+                capabilities: FileCapabilities::default(),
+                streams: BTreeMap::new(),
+            },
+        )
+    }
+
+    fn insert(self, path: &str, entry: MemEntry) -> Self {
+        let path = normalize(path);
+        let mut builder = match parent(&path) {
+            Some(parent_path) => self.dir(parent_path),
+            None => self,
+        };
+        builder.fs.entries.insert(path, entry);
+
+        builder
+    }
+
+    /// Return the filesystem
+    pub fn build(self) -> MemFs {
+        self.fs
+    }
+}
+
+#[async_trait]
+impl DiffFs for MemFs {
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<VfsEntry>> {
+        let dir = self
+            .resolve(path, true)
+            .ok_or_else(|| MemFs::not_found(path))?;
+        if !dir.is_empty() && self.entries.get(&dir) != Some(&MemEntry::Dir) {
+            return Err(MemFs::not_found(path));
+        }
+        // the entries of a directory reached through a link are inside of the link
+        let listed = normalize(path);
+
+        Ok(self
+            .entries
+            .iter()
+            .filter(|(entry_path, _)| {
+                parent(entry_path).unwrap_or("") == dir && !entry_path.is_empty()
+            })
+            .map(|(entry_path, entry)| {
+                let name = entry_path.rsplit('/').next().unwrap_or("").to_string();
+                VfsEntry {
+                    path: if listed.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}/{}", listed, name)
+                    },
+                    name,
+                    kind: match entry {
+                        MemEntry::File { .. } => EntryKind::File,
+                        MemEntry::Dir => EntryKind::Dir,
+                        MemEntry::Symlink { .. } => EntryKind::Symlink,
+                    },
+                    len: match entry {
+                        MemEntry::File { content, .. } => content.len() as u64,
+                        _ => 0,
+                    },
+                    inode: match entry {
+                        MemEntry::File {
+                            inode: Some(inode), ..
+                        } => Some((0, *inode)),
+                        _ => None,
+                    },
+                }
+            })
+            .collect())
+    }
+
+    async fn metadata(&self, path: &str) -> io::Result<VfsMetadata> {
+        let resolved = self
+            .resolve(path, true)
+            .ok_or_else(|| MemFs::not_found(path))?;
+        // the directories are identified by their position, after the root
+        let dir_inode = |resolved: &str| {
+            let position = self.entries.keys().position(|key| key == resolved);
+            Some((1, position.map_or(0, |position| position as u64 + 1)))
+        };
+
+        match self.entries.get(&resolved) {
+            Some(MemEntry::File {
+                content,
+                modified,
+                inode,
+                ..
+            }) => Ok(VfsMetadata {
+                kind: EntryKind::File,
+                len: content.len() as u64,
+                modified: *modified,
+                inode: inode.map(|inode| (0, inode)),
+            }),
+            Some(MemEntry::Dir) => Ok(VfsMetadata {
+                kind: EntryKind::Dir,
+                len: 0,
+                modified: None,
+                inode: dir_inode(&resolved),
+            }),
+            None if resolved.is_empty() => Ok(VfsMetadata {
+                kind: EntryKind::Dir,
+                len: 0,
+                modified: None,
+                inode: dir_inode(&resolved),
+            }),
+            _ => Err(MemFs::not_found(path)),
+        }
+    }
+
+    async fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        match self.entry(path) {
+            Some(MemEntry::File { content, .. }) => Ok(content.clone()),
+            _ => Err(MemFs::not_found(path)),
+        }
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        match self.entry(path) {
+            Some(MemEntry::File { content, .. }) => {
+                let start = (offset as usize).min(content.len());
+                let end = (offset.saturating_add(len) as usize).min(content.len());
+                Ok(content[start..end].to_vec())
+            }
+            _ => Err(MemFs::not_found(path)),
+        }
+    }
+
+    async fn data_extents(&self, path: &str) -> io::Result<Option<Vec<(u64, u64)>>> {
+        match self.entry(path) {
+            Some(MemEntry::File { extents, .. }) => Ok(extents.clone()),
+            _ => Err(MemFs::not_found(path)),
+        }
+    }
+
+    async fn capabilities(&self, path: &str) -> io::Result<Option<FileCapabilities>> {
+        match self.entry(path) {
+            Some(MemEntry::File { capabilities, .. }) => Ok(Some(*capabilities)),
+            _ => Err(MemFs::not_found(path)),
+        }
+    }
+
+    async fn alternate_streams(&self, path: &str) -> io::Result<Option<BTreeMap<String, Vec<u8>>>> {
+        match self.entry(path) {
+            Some(MemEntry::File { streams, .. }) => Ok(Some(streams.clone())),
+            _ => Err(MemFs::not_found(path)),
+        }
+    }
+
+    async fn read_link(&self, path: &str) -> io::Result<String> {
+        let resolved = self
+            .resolve(path, false)
+            .ok_or_else(|| MemFs::not_found(path))?;
+        match self.entries.get(&resolved) {
+            Some(MemEntry::Symlink { target }) => Ok(target.clone()),
+            _ => Err(MemFs::not_found(path)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_list_mem_dir() {
+    let fs = MemFs::builder()
+        .file("./dir_one/hello.txt", "Hello world")
+        .file("dir_one/vlang/hello.v", "fn main() {}")
+        .dir("dir_one/empty/")
+        .build();
+
+    assert_eq!(
+        fs.read_dir("dir_one").await.unwrap(),
+        vec![
+            VfsEntry {
+                name: "empty".to_string(),
+                path: "dir_one/empty".to_string(),
+                kind: EntryKind::Dir,
+                len: 0,
+                inode: None,
+            },
+            VfsEntry {
+                name: "hello.txt".to_string(),
+                path: "dir_one/hello.txt".to_string(),
+                kind: EntryKind::File,
+                len: 11,
+                inode: None,
+            },
+            VfsEntry {
+                name: "vlang".to_string(),
+                path: "dir_one/vlang".to_string(),
+                kind: EntryKind::Dir,
+                len: 0,
+                inode: None,
+            },
+        ]
+    );
+    assert!(fs.read_dir("dir_two").await.is_err());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_read_mem_file() {
+    let fs = MemFs::builder()
+        .file("dir_one/hello.txt", "Hello world")
+        .build();
+
+    assert_eq!(
+        fs.read("dir_one/hello.txt").await.unwrap(),
+        b"Hello world".to_vec()
+    );
+    assert_eq!(fs.metadata("dir_one/hello.txt").await.unwrap().len, 11);
+    assert!(fs.metadata("dir_one").await.unwrap().is_dir());
+    assert!(fs.read("dir_one").await.is_err());
+}
@@ -0,0 +1,639 @@
+//! Sources which can be used to build a `Tree` and read the content of its files.
+//!
+//! The local filesystem is the default source, but any other storage which is able
+//! to list directories and read files can be compared through the `DiffFs` trait.
+//!
+//! # Custom backend
+//!
+//! ```rust
+//! use async_trait::async_trait;
+//! use spielrs_diff::vfs::{DiffFs, EntryKind, VfsEntry, VfsMetadata};
+//! use std::io;
+//!
+//! /// Backend with a single file stored in memory
+//! struct SingleFile(Vec<u8>);
+//!
+//! #[async_trait]
+//! impl DiffFs for SingleFile {
+//!     async fn read_dir(&self, path: &str) -> io::Result<Vec<VfsEntry>> {
+//!         Ok(vec![VfsEntry {
+//!             name: "file.txt".to_string(),
+//!             path: format!("{}/file.txt", path),
+//!             kind: EntryKind::File,
+//!             len: self.0.len() as u64,
+//!             inode: None,
+//!         }])
+//!     }
+//!
+//!     async fn metadata(&self, path: &str) -> io::Result<VfsMetadata> {
+//!         Ok(VfsMetadata {
+//!             kind: if path.ends_with("file.txt") {
+//!                 EntryKind::File
+//!             } else {
+//!                 EntryKind::Dir
+//!             },
+//!             len: self.0.len() as u64,
+//!             modified: None,
+//!             inode: None,
+//!         })
+//!     }
+//!
+//!     async fn read(&self, _path: &str) -> io::Result<Vec<u8>> {
+//!         Ok(self.0.clone())
+//!     }
+//! }
+//! ```
+use crate::caps::FileCapabilities;
+use crate::rt::{self, fs, AsyncRead};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+#[cfg(feature = "ftp")]
+pub mod ftp;
+#[cfg(feature = "git")]
+pub mod git;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod mem;
+pub mod path;
+#[cfg(feature = "tarball")]
+pub mod tarball;
+
+/// Kind of an entry of a `DiffFs` or a `Tree`
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum EntryKind {
+    /// regular file
+    #[default]
+    File,
+    /// directory
+    Dir,
+    /// symbolic link, not followed
+    Symlink,
+    /// symbolic link to one of the directories which contain it, found following the
+    /// links with `DirDiff::follow_symlinks`
+    Cycle,
+    /// named pipe
+    Fifo,
+    /// unix domain socket
+    Socket,
+    /// block device
+    BlockDevice,
+    /// character device
+    CharDevice,
+    /// any other kind of entry
+    Other,
+    /// directory which can't be listed, like without the permission to read it, so its
+    /// content is unknown
+    Unreadable,
+}
+
+impl EntryKind {
+    /// Return true if the entry is a special file, like a FIFO or a device, whose content
+    /// is never read
+    pub fn is_special(self) -> bool {
+        !matches!(
+            self,
+            EntryKind::File
+                | EntryKind::Dir
+                | EntryKind::Symlink
+                | EntryKind::Cycle
+                | EntryKind::Unreadable
+        )
+    }
+}
+
+/// Entry returned when a directory of a `DiffFs` is listed
+#[derive(Debug, PartialEq, Clone)]
+pub struct VfsEntry {
+    /// name of the file or directory
+    pub name: String,
+    /// path of the entry inside of the source
+    pub path: String,
+    /// kind of the entry
+    pub kind: EntryKind,
+    /// size in bytes of the file, 0 if the source doesn't know it
+    pub len: u64,
+    /// device and inode of the file if it has more than one hard link
+    pub inode: Option<(u64, u64)>,
+}
+
+/// Metadata of a file or directory of a `DiffFs`
+#[derive(Debug, PartialEq, Clone)]
+pub struct VfsMetadata {
+    /// kind of the entry, of the target if it is a symbolic link
+    pub kind: EntryKind,
+    /// size in bytes of the file
+    pub len: u64,
+    /// last modification time, if the source knows it
+    pub modified: Option<SystemTime>,
+    /// device and inode of the file or directory, if the source knows them
+    pub inode: Option<(u64, u64)>,
+}
+
+impl VfsMetadata {
+    /// Return true if the entry is a directory, or a symbolic link to one
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+}
+
+/// Reader returned when a file of a `DiffFs` is opened
+pub type VfsReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Source from which the directories and files to compare are read.
+/// `Tree::build_tree_with`, `dir_diff_with` and `file_diff_with` are generic over it,
+/// so any storage (database blobs, FUSE, cloud buckets...) can reuse the diff logic
+#[async_trait]
+pub trait DiffFs: Send + Sync {
+    /// List the entries of the directory
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<VfsEntry>>;
+    /// Get the metadata of a file or directory
+    async fn metadata(&self, path: &str) -> io::Result<VfsMetadata>;
+    /// Read the whole content of the file
+    async fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+    /// Open the file to read its content progressively, by default the whole
+    /// content is read in memory
+    async fn open(&self, path: &str) -> io::Result<VfsReader> {
+        Ok(rt::reader(self.read(path).await?))
+    }
+    /// Read `len` bytes from `offset`, or less if the file ends before. By default the
+    /// whole content is read
+    async fn read_range(&self, path: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let content = self.read(path).await?;
+        let start = (offset as usize).min(content.len());
+        let end = (offset.saturating_add(len) as usize).min(content.len());
+
+        Ok(content[start..end].to_vec())
+    }
+    /// Regions `(offset, len)` of a sparse file which are allocated, the rest of the file
+    /// are holes read as zeros. `None` if the source doesn't know them, the default
+    async fn data_extents(&self, _path: &str) -> io::Result<Option<Vec<(u64, u64)>>> {
+        Ok(None)
+    }
+    /// Linux capabilities of the file, empty if it has none. `None` if the source doesn't
+    /// know them, the default
+    async fn capabilities(&self, _path: &str) -> io::Result<Option<FileCapabilities>> {
+        Ok(None)
+    }
+    /// Content of the alternate data streams of the file by name, without the main one.
+    /// `None` if the source doesn't know them, the default
+    async fn alternate_streams(
+        &self,
+        _path: &str,
+    ) -> io::Result<Option<BTreeMap<String, Vec<u8>>>> {
+        Ok(None)
+    }
+    /// Target of the symbolic link, by default the sources don't support them
+    async fn read_link(&self, path: &str) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} can't be read as a symbolic link", path),
+        ))
+    }
+    /// Path of the file in the local filesystem if the source reads it from there, so
+    /// it can be compared without going through the async reads. `None` by default
+    fn local_path(&self, _path: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+impl VfsEntry {
+    /// Return true if the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+}
+
+/// Kind of the entry of the file type, without following the symbolic links
+fn entry_kind(file_type: std::fs::FileType) -> EntryKind {
+    if file_type.is_dir() {
+        EntryKind::Dir
+    } else if file_type.is_file() {
+        EntryKind::File
+    } else if file_type.is_symlink() {
+        EntryKind::Symlink
+    } else {
+        special_kind(file_type)
+    }
+}
+
+#[cfg(unix)]
+fn special_kind(file_type: std::fs::FileType) -> EntryKind {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_fifo() {
+        EntryKind::Fifo
+    } else if file_type.is_socket() {
+        EntryKind::Socket
+    } else if file_type.is_block_device() {
+        EntryKind::BlockDevice
+    } else if file_type.is_char_device() {
+        EntryKind::CharDevice
+    } else {
+        EntryKind::Other
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_file_type: std::fs::FileType) -> EntryKind {
+    EntryKind::Other
+}
+
+/// Device and inode of a file with more than one hard link
+#[cfg(unix)]
+fn hard_link(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.is_file() && metadata.nlink() > 1 {
+        Some((metadata.dev(), metadata.ino()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn hard_link(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Device and inode of any file or directory
+#[cfg(unix)]
+fn identity(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Local filesystem through the async runtime of the crate, see `rt`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalFs;
+
+#[async_trait]
+impl DiffFs for LocalFs {
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<VfsEntry>> {
+        let entries = fs::read_dir(&path::local(path)).await?;
+
+        Ok(entries
+            .into_iter()
+            .map(|(name, path, metadata)| {
+                let kind = entry_kind(metadata.file_type());
+                VfsEntry {
+                    name,
+                    path,
+                    kind,
+                    len: if kind == EntryKind::File {
+                        metadata.len()
+                    } else {
+                        0
+                    },
+                    inode: hard_link(&metadata),
+                }
+            })
+            .collect())
+    }
+
+    async fn metadata(&self, path: &str) -> io::Result<VfsMetadata> {
+        let metadata = fs::metadata(&path::local(path)).await?;
+
+        Ok(VfsMetadata {
+            kind: entry_kind(metadata.file_type()),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            inode: identity(&metadata),
+        })
+    }
+
+    async fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        fs::read(&path::local(path)).await
+    }
+
+    async fn open(&self, path: &str) -> io::Result<VfsReader> {
+        fs::open(&path::local(path)).await
+    }
+
+    async fn read_link(&self, path: &str) -> io::Result<String> {
+        fs::read_link(&path::local(path)).await
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        Some(PathBuf::from(&*path::local(path)))
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        fs::read_range(&path::local(path), offset, len).await
+    }
+
+    #[cfg(all(feature = "sparse", any(target_os = "linux", target_os = "android")))]
+    async fn data_extents(&self, path: &str) -> io::Result<Option<Vec<(u64, u64)>>> {
+        let path = path.to_string();
+
+        rt::spawn_blocking(move || seek_data_extents(&path).map(Some)).await?
+    }
+
+    #[cfg(all(
+        feature = "capabilities",
+        any(target_os = "linux", target_os = "android")
+    ))]
+    async fn capabilities(&self, path: &str) -> io::Result<Option<FileCapabilities>> {
+        let path = path.to_string();
+
+        rt::spawn_blocking(move || capability_xattr(&path).map(Some)).await?
+    }
+
+    #[cfg(all(feature = "streams", windows))]
+    async fn alternate_streams(&self, path: &str) -> io::Result<Option<BTreeMap<String, Vec<u8>>>> {
+        let path = path.to_string();
+
+        rt::spawn_blocking(move || read_streams(&path).map(Some)).await?
+    }
+}
+
+/// Find the alternate data streams of the file with `FindFirstStreamW` and read them
+#[cfg(all(feature = "streams", windows))]
+fn read_streams(path: &str) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::{ERROR_HANDLE_EOF, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard,
+        WIN32_FIND_STREAM_DATA,
+    };
+
+    let wide_path: Vec<u16> = std::ffi::OsStr::new(path)
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    // SAFETY: the struct only has integers, all zeros is a valid value
+    let mut data: WIN32_FIND_STREAM_DATA = unsafe { std::mem::zeroed() };
+    let data_ptr = &mut data as *mut WIN32_FIND_STREAM_DATA as *mut std::ffi::c_void;
+    // SAFETY: the path ends with a nul and `data` outlives the handle
+    let handle =
+        unsafe { FindFirstStreamW(wide_path.as_ptr(), FindStreamInfoStandard, data_ptr, 0) };
+    if handle == INVALID_HANDLE_VALUE {
+        let error = io::Error::last_os_error();
+        // ERROR_HANDLE_EOF: the entry has no stream, like the directories
+        return match error.raw_os_error() {
+            Some(code) if code == ERROR_HANDLE_EOF as i32 => Ok(BTreeMap::new()),
+            _ => Err(error),
+        };
+    }
+
+    let mut names: Vec<String> = vec![];
+    let found = loop {
+        let len = data
+            .cStreamName
+            .iter()
+            .position(|unit| *unit == 0)
+            .unwrap_or(data.cStreamName.len());
+        names.push(String::from_utf16_lossy(&data.cStreamName[..len]));
+        // SAFETY: the handle is still open and `data` is the same buffer
+        if unsafe { FindNextStreamW(handle, data_ptr) } == 0 {
+            let error = io::Error::last_os_error();
+            break match error.raw_os_error() {
+                Some(code) if code == ERROR_HANDLE_EOF as i32 => Ok(()),
+                _ => Err(error),
+            };
+        }
+    };
+    // SAFETY: the handle was returned by FindFirstStreamW and is closed only once
+    unsafe { FindClose(handle) };
+    found?;
+
+    // the streams are named `:name:$DATA`, the main one is `::$DATA`
+    let mut streams: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    for name in names {
+        match name
+            .strip_prefix(':')
+            .and_then(|name| name.strip_suffix(":$DATA"))
+        {
+            Some(stream) if !stream.is_empty() => {
+                let content = std::fs::read(format!("{}:{}", path, stream))?;
+                streams.insert(stream.to_string(), content);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(streams)
+}
+
+/// Read and decode the `security.capability` extended attribute of the file
+#[cfg(all(
+    feature = "capabilities",
+    any(target_os = "linux", target_os = "android")
+))]
+fn capability_xattr(path: &str) -> io::Result<FileCapabilities> {
+    use std::ffi::CString;
+
+    let path =
+        CString::new(path).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let name = b"security.capability\0";
+    // the biggest revision of the attribute has 24 bytes
+    let mut value = [0u8; 24];
+    // SAFETY: both strings end with a nul and the length is the one of the buffer
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr() as *const libc::c_char,
+            value.as_mut_ptr() as *mut libc::c_void,
+            value.len(),
+        )
+    };
+    if len < 0 {
+        let error = io::Error::last_os_error();
+        // ENODATA: the file has no capability, ENOTSUP: the filesystem can't store them
+        return match error.raw_os_error() {
+            Some(libc::ENODATA) | Some(libc::ENOTSUP) => Ok(FileCapabilities::default()),
+            _ => Err(error),
+        };
+    }
+
+    FileCapabilities::from_xattr(&value[..len as usize]).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid security.capability attribute",
+        )
+    })
+}
+
+/// Walk the file with `SEEK_DATA` and `SEEK_HOLE` to find its allocated regions
+#[cfg(all(feature = "sparse", any(target_os = "linux", target_os = "android")))]
+fn seek_data_extents(path: &str) -> io::Result<Vec<(u64, u64)>> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len() as libc::off_t;
+    let fd = file.as_raw_fd();
+    let mut extents: Vec<(u64, u64)> = vec![];
+    let mut offset: libc::off_t = 0;
+
+    while offset < len {
+        // SAFETY: lseek only moves the position of the file descriptor owned by `file`
+        let data = unsafe { libc::lseek(fd, offset, libc::SEEK_DATA) };
+        if data < 0 {
+            let error = io::Error::last_os_error();
+            // ENXIO: there is no more data after the offset
+            if error.raw_os_error() == Some(libc::ENXIO) {
+                break;
+            }
+            return Err(error);
+        }
+        // SAFETY: same than above
+        let hole = unsafe { libc::lseek(fd, data, libc::SEEK_HOLE) };
+        if hole < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        extents.push((data as u64, (hole - data) as u64));
+        offset = hole;
+    }
+
+    Ok(extents)
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_list_local_dir() {
+    let mut entries = LocalFs.read_dir("./mocks/dir_one").await.unwrap();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    assert_eq!(
+        entries
+            .into_iter()
+            .map(|e| (e.name, e.kind == EntryKind::Dir))
+            .collect::<Vec<_>>(),
+        vec![
+            ("hello.py".to_string(), false),
+            ("hello.txt".to_string(), false),
+            ("vlang".to_string(), true),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_read_local_file() {
+    let content = LocalFs.read("./mocks/dir_one/hello.txt").await.unwrap();
+
+    assert_eq!(content, b"Hello world".to_vec());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_local_metadata() {
+    let file = LocalFs.metadata("./mocks/dir_one/hello.txt").await.unwrap();
+    let dir = LocalFs.metadata("./mocks/dir_one/vlang").await.unwrap();
+
+    assert!(!file.is_dir());
+    assert_eq!(file.len, 11);
+    assert!(dir.is_dir());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_open_local_file() {
+    use crate::rt::AsyncReadExt;
+
+    let mut reader = LocalFs.open("./mocks/dir_one/hello.txt").await.unwrap();
+    let mut content = String::new();
+    reader.read_to_string(&mut content).await.unwrap();
+
+    assert_eq!(content, "Hello world");
+}
+
+#[cfg(unix)]
+#[cfg(test)]
+#[tokio::test]
+async fn should_list_hard_links() {
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_links_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("original.txt"), "Hello world").unwrap();
+    std::fs::write(dir.join("single.txt"), "Hello world").unwrap();
+    std::fs::hard_link(dir.join("original.txt"), dir.join("link.txt")).unwrap();
+
+    let mut entries = LocalFs.read_dir(dir.to_str().unwrap()).await.unwrap();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(entries[0].inode.is_some());
+    assert_eq!(entries[0].inode, entries[1].inode);
+    assert_eq!(entries[2].inode, None);
+}
+
+#[cfg(unix)]
+#[cfg(test)]
+#[tokio::test]
+async fn should_list_local_symlinks_without_following_them() {
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_symlinks_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("vlang")).unwrap();
+    std::os::unix::fs::symlink("vlang", dir.join("link")).unwrap();
+
+    let entries = LocalFs.read_dir(dir.to_str().unwrap()).await.unwrap();
+    let target = LocalFs.read_link(dir.join("link").to_str().unwrap()).await;
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let link = entries.iter().find(|entry| entry.name == "link").unwrap();
+    assert_eq!(link.kind, EntryKind::Symlink);
+    assert_eq!(target.unwrap(), "vlang");
+}
+
+#[cfg(unix)]
+#[cfg(test)]
+#[tokio::test]
+async fn should_list_local_special_files() {
+    let dir = std::env::temp_dir().join(format!("spielrs_diff_special_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let listener = std::os::unix::net::UnixListener::bind(dir.join("server.sock")).unwrap();
+
+    let entries = LocalFs.read_dir(dir.to_str().unwrap()).await.unwrap();
+    drop(listener);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(entries[0].kind, EntryKind::Socket);
+    assert!(entries[0].kind.is_special());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_read_local_range() {
+    let content = LocalFs
+        .read_range("./mocks/dir_one/hello.txt", 6, 100)
+        .await
+        .unwrap();
+
+    assert_eq!(content, b"world".to_vec());
+}
+
+#[cfg(all(feature = "sparse", any(target_os = "linux", target_os = "android")))]
+#[cfg(test)]
+#[tokio::test]
+async fn should_return_data_extents_of_sparse_files() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let path = std::env::temp_dir().join(format!("spielrs_diff_sparse_{}", std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.set_len(16 * 1024 * 1024).unwrap();
+    file.seek(SeekFrom::Start(8 * 1024 * 1024)).unwrap();
+    file.write_all(b"data").unwrap();
+    drop(file);
+
+    let extents = LocalFs
+        .data_extents(path.to_str().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // the filesystem can allocate more than the written bytes, but not the whole file
+    let allocated: u64 = extents.iter().map(|(_, len)| len).sum();
+    assert!((4..16 * 1024 * 1024).contains(&allocated));
+    assert!(extents
+        .iter()
+        .any(|(offset, len)| *offset <= 8 * 1024 * 1024 && offset + len >= 8 * 1024 * 1024 + 4));
+}
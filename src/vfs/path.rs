@@ -0,0 +1,214 @@
+//! Handling of the Windows paths given as strings: drive paths like `C:\data`, UNC
+//! shares like `\\server\share` and verbatim paths like `\\?\C:\data`, which can be
+//! longer than 260 characters but are not normalized by Windows
+use std::borrow::Cow;
+
+const VERBATIM: &str = r"\\?\";
+const VERBATIM_UNC: &str = r"\\?\UNC\";
+const DEVICE: &str = r"\\.\";
+
+/// Return true if the path is a Windows drive, UNC or verbatim path, whose separator
+/// is `\`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::vfs::path::is_windows_path;
+///
+/// assert!(is_windows_path(r"C:\data"));
+/// assert!(is_windows_path(r"\\server\share\data"));
+/// assert!(!is_windows_path("./mocks/dir_one"));
+/// ```
+pub fn is_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+
+    path.starts_with(r"\\")
+        || bytes.len() >= 3
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && bytes[2] == b'\\'
+}
+
+/// Return the verbatim form of an absolute Windows path, `\\?\C:\data` for `C:\data` and
+/// `\\?\UNC\server\share` for `\\server\share`, so it isn't limited to 260 characters.
+/// The `/` are replaced by `\` and the `.` and `..` segments are resolved because Windows
+/// doesn't do it for the verbatim paths. The relative, verbatim and device paths are
+/// returned as they are
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::vfs::path::to_verbatim;
+///
+/// assert_eq!(to_verbatim(r"C:\data\..\backup/2020"), r"\\?\C:\backup\2020");
+/// assert_eq!(to_verbatim(r"\\server\share\data"), r"\\?\UNC\server\share\data");
+/// assert_eq!(to_verbatim("./mocks/dir_one"), "./mocks/dir_one");
+/// ```
+pub fn to_verbatim(path: &str) -> Cow<'_, str> {
+    if path.starts_with(VERBATIM) || path.starts_with(DEVICE) {
+        return Cow::Borrowed(path);
+    }
+
+    let bytes = path.as_bytes();
+    let (prefix, rest, root_segments) = if path.starts_with(r"\\") || path.starts_with("//") {
+        // the server and the share can't be removed by `..`
+        (VERBATIM_UNC, &path[2..], 2)
+    } else if bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/')
+    {
+        (VERBATIM, path, 1)
+    } else {
+        return Cow::Borrowed(path);
+    };
+
+    let mut segments: Vec<&str> = vec![];
+    for segment in rest.split(['\\', '/']) {
+        match segment {
+            "" | "." => {}
+            ".." if segments.len() > root_segments => {
+                segments.pop();
+            }
+            ".." => {}
+            segment => segments.push(segment),
+        }
+    }
+
+    Cow::Owned(format!("{}{}", prefix, segments.join("\\")))
+}
+
+/// Join the path relative to the root of a tree, separated by `/`, to the directory with
+/// the separator of the directory, `\` for the Windows paths. The other paths are joined
+/// as they are, a `\` is a character of the name there
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::vfs::path::join;
+///
+/// assert_eq!(join(r"\\?\C:\data\", "vlang/hello.v"), r"\\?\C:\data\vlang\hello.v");
+/// assert_eq!(join("./mocks/dir_one/", "vlang/hello.v"), "./mocks/dir_one/vlang/hello.v");
+/// assert_eq!(join("./mocks/dir_one", r"back\slash.txt"), r"./mocks/dir_one/back\slash.txt");
+/// ```
+pub fn join(dir: &str, rel_path: &str) -> String {
+    if is_windows_path(dir) {
+        format!("{}\\{}", trim_separator(dir), rel_path.replace('/', "\\"))
+    } else {
+        format!("{}/{}", trim_separator(dir), rel_path)
+    }
+}
+
+/// Replace the `\` of a path relative to the root of a tree by `/`, so the paths of
+/// the trees taken in Windows or of the archives created there match the paths of
+/// the other systems
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::vfs::path::normalize_separators;
+///
+/// assert_eq!(normalize_separators(r"vlang\purpose\purpose.txt"), "vlang/purpose/purpose.txt");
+/// ```
+pub fn normalize_separators(rel_path: &str) -> Cow<'_, str> {
+    if rel_path.contains('\\') {
+        Cow::Owned(rel_path.replace('\\', "/"))
+    } else {
+        Cow::Borrowed(rel_path)
+    }
+}
+
+/// Remove the separators at the end of the directory, `\` too for the Windows paths
+pub fn trim_separator(dir: &str) -> &str {
+    if is_windows_path(dir) {
+        dir.trim_end_matches(['\\', '/'])
+    } else {
+        dir.trim_end_matches('/')
+    }
+}
+
+/// Return the path relative to the directory separated by `/`, or None if the path is not
+/// inside of it. Both are compared in their verbatim form if the directory is a Windows
+/// path, so `C:\data\hello.txt` is inside of `\\?\C:\data`
+///
+/// # Example
+///
+/// ```rust
+/// use spielrs_diff::vfs::path::relative_to;
+///
+/// assert_eq!(
+///     relative_to(r"\\?\C:\data", r"C:\data\vlang\hello.v"),
+///     Some("vlang/hello.v".to_string())
+/// );
+/// assert_eq!(relative_to("dir_one", "dir_one/hello.txt"), Some("hello.txt".to_string()));
+/// assert_eq!(relative_to("dir_one", "dir_two/hello.txt"), None);
+/// ```
+pub fn relative_to(dir: &str, path: &str) -> Option<String> {
+    let rel_path = if is_windows_path(dir) {
+        let dir = to_verbatim(dir);
+        let path = to_verbatim(path);
+        path.strip_prefix(dir.trim_end_matches('\\'))?
+            .strip_prefix('\\')?
+            .trim_end_matches('\\')
+            .replace('\\', "/")
+    } else {
+        path.strip_prefix(dir.trim_end_matches('/'))?
+            .strip_prefix('/')?
+            .trim_end_matches('/')
+            .to_string()
+    };
+
+    if rel_path.is_empty() {
+        None
+    } else {
+        Some(rel_path)
+    }
+}
+
+/// Path given to the local filesystem, the verbatim form in Windows so the long paths
+/// can be read
+#[cfg(windows)]
+pub(crate) fn local(path: &str) -> Cow<'_, str> {
+    to_verbatim(path)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn local(path: &str) -> Cow<'_, str> {
+    Cow::Borrowed(path)
+}
+
+#[test]
+fn should_convert_to_verbatim_paths() {
+    assert_eq!(to_verbatim(r"\\?\C:\data/.."), r"\\?\C:\data/..");
+    assert_eq!(to_verbatim(r"\\.\pipe\name"), r"\\.\pipe\name");
+    assert_eq!(to_verbatim("C:/data/./vlang/"), r"\\?\C:\data\vlang");
+    assert_eq!(to_verbatim(r"C:\..\data"), r"\\?\C:\data");
+    assert_eq!(
+        to_verbatim(r"\\server\share\..\..\data"),
+        r"\\?\UNC\server\share\data"
+    );
+    assert_eq!(to_verbatim("/home/data"), "/home/data");
+
+    let long = format!(r"C:\{}", vec!["segment"; 40].join(r"\"));
+    assert!(to_verbatim(&long).len() > 260);
+    assert!(to_verbatim(&long).starts_with(r"\\?\C:\segment\"));
+}
+
+#[test]
+fn should_return_the_path_relative_to_unc_shares() {
+    assert_eq!(
+        relative_to(
+            r"\\server\share\dir_one",
+            r"\\?\UNC\server\share\dir_one\vlang\"
+        ),
+        Some("vlang".to_string())
+    );
+    assert_eq!(
+        relative_to(r"\\server\share\dir_one", r"\\server\share\dir_one"),
+        None
+    );
+    assert_eq!(
+        join(r"\\server\share\dir_one", "vlang/hello.v"),
+        r"\\server\share\dir_one\vlang\hello.v"
+    );
+}
@@ -0,0 +1,247 @@
+//! Tar archives, compressed with gzip or not, indexed in a `MemFs` through
+//! [tar](https://crates.io/crates/tar) so a release artifact can be compared with the
+//! directory where it was unpacked
+use super::mem::{MemFs, MemFsBuilder};
+use flate2::read::GzDecoder;
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Read};
+use std::time::{Duration, UNIX_EPOCH};
+use tar::{Archive, EntryType};
+
+/// First bytes of the gzip streams
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Read the archive entry by entry and keep it in memory, the content of the files and
+/// their modification times, the directories and the symbolic links. The hard links are
+/// copies of their files and the devices and FIFOs are skipped. The paths are the ones
+/// of the archive, usually inside of a directory like `package-1.2.3`
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use spielrs_diff::{dir_diff_with, diff::DirDiff, vfs::{LocalFs, tarball::read_tarball}};
+/// use std::fs::File;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let release = read_tarball(File::open("package-1.2.3.tar.gz").unwrap()).unwrap();
+///
+///     let diff = dir_diff_with(&release, &LocalFs, DirDiff {
+///         dir: "package-1.2.3".to_string(),
+///         dir_comp: "/opt/package".to_string(),
+///         ..Default::default()
+///     })
+///     .await;
+///
+///     println!("{}", diff);
+/// }
+/// ```
+pub fn read_tarball<R: Read>(reader: R) -> io::Result<MemFs> {
+    let mut reader = BufReader::new(reader);
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        read_entries(Archive::new(GzDecoder::new(reader)))
+    } else {
+        read_entries(Archive::new(reader))
+    }
+}
+
+fn read_entries<R: Read>(mut archive: Archive<R>) -> io::Result<MemFs> {
+    let mut builder: MemFsBuilder = MemFs::builder();
+    let mut files: HashSet<String> = HashSet::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+
+        builder = match entry.header().entry_type() {
+            EntryType::Directory => builder.dir(&path),
+            EntryType::Symlink => builder.symlink(&path, &link_name(&entry, &path)?),
+            EntryType::Link => {
+                let original = link_name(&entry, &path)?;
+                if !files.contains(original.trim_start_matches("./")) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} is linked to {} which is not a file before it",
+                            path, original
+                        ),
+                    ));
+                }
+                files.insert(path.trim_start_matches("./").to_string());
+                builder.hard_link(&path, &original)
+            }
+            EntryType::Regular | EntryType::Continuous | EntryType::GNUSparse => {
+                let modified = UNIX_EPOCH + Duration::from_secs(entry.header().mtime()?);
+                let mut content: Vec<u8> = vec![];
+                entry.read_to_end(&mut content)?;
+                files.insert(path.trim_start_matches("./").to_string());
+                builder.file_modified(&path, content, modified)
+            }
+            // devices, FIFOs and the headers of the extensions
+            _ => builder,
+        };
+    }
+
+    Ok(builder.build())
+}
+
+/// Target of the symbolic or hard link
+fn link_name<R: Read>(entry: &tar::Entry<R>, path: &str) -> io::Result<String> {
+    match entry.link_name()? {
+        Some(link) => Ok(link.to_string_lossy().into_owned()),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the link {} has no target", path),
+        )),
+    }
+}
+
+/// Download the archive and index it while it is downloaded, without writing it in disk.
+/// See `read_tarball`
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use spielrs_diff::{dir_diff_report_with, diff::DirDiff, vfs::{LocalFs, tarball::fetch_tarball}};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let release = fetch_tarball("https://example.com/package-1.2.3.tar.gz")
+///         .await
+///         .unwrap();
+///
+///     let report = dir_diff_report_with(&release, &LocalFs, DirDiff {
+///         dir: "package-1.2.3".to_string(),
+///         dir_comp: "/opt/package".to_string(),
+///         ..Default::default()
+///     })
+///     .await;
+///
+///     print!("{}", report);
+/// }
+/// ```
+#[cfg(feature = "http")]
+pub async fn fetch_tarball(url: &str) -> io::Result<MemFs> {
+    let mut response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(io::Error::other)?;
+    // the chunks go to the archive reader in the threads for the blocking tasks, the
+    // download waits while it is behind
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Vec<u8>>(16);
+    let index = crate::rt::spawn_blocking(move || {
+        read_tarball(ChannelReader {
+            receiver,
+            chunk: vec![],
+            position: 0,
+        })
+    });
+
+    let downloaded = async {
+        while let Some(chunk) = response.chunk().await.map_err(io::Error::other)? {
+            if sender.send(chunk.to_vec()).await.is_err() {
+                // the archive reader failed and stopped
+                break;
+            }
+        }
+        drop(sender);
+        Ok::<(), io::Error>(())
+    };
+    let (downloaded, index) = futures::join!(downloaded, index);
+    downloaded?;
+
+    index?
+}
+
+/// Blocking reader of the chunks received through the channel, it ends when the
+/// channel is closed
+#[cfg(feature = "http")]
+struct ChannelReader {
+    receiver: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+#[cfg(feature = "http")]
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn should_compare_a_tarball_with_a_directory() {
+    use crate::{diff::DirDiff, dir_diff_report_with, report::Difference};
+    use flate2::{write::GzEncoder, Compression};
+
+    let mut archive = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+    let mut append = |path: &str, entry_type: EntryType, content: &[u8], link: Option<&str>| {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(1_600_000_000);
+        if let Some(link) = link {
+            header.set_link_name(link).unwrap();
+        }
+        archive.append_data(&mut header, path, content).unwrap();
+    };
+    append("package-1.2.3/", EntryType::Directory, b"", None);
+    append(
+        "package-1.2.3/bin/tool",
+        EntryType::Regular,
+        b"#!/bin/sh",
+        None,
+    );
+    append(
+        "package-1.2.3/bin/alias",
+        EntryType::Link,
+        b"",
+        Some("package-1.2.3/bin/tool"),
+    );
+    append(
+        "package-1.2.3/current",
+        EntryType::Symlink,
+        b"",
+        Some("bin/tool"),
+    );
+    append("package-1.2.3/README", EntryType::Regular, b"1.2.3", None);
+    let tarball = archive.into_inner().unwrap().finish().unwrap();
+
+    let release = read_tarball(&tarball[..]).unwrap();
+    let installed = MemFs::builder()
+        .file("opt/package/bin/tool", "#!/bin/sh")
+        .file("opt/package/bin/alias", "#!/bin/sh")
+        .symlink("opt/package/current", "bin/tool")
+        .file("opt/package/README", "1.2.2")
+        .build();
+    let report = dir_diff_report_with(
+        &release,
+        &installed,
+        DirDiff {
+            dir: "package-1.2.3".to_string(),
+            dir_comp: "opt/package".to_string(),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        report.differences,
+        vec![Difference::Modified("README".to_string())]
+    );
+}